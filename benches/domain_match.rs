@@ -0,0 +1,58 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use liu_proxy::common::geosite::{DomainMatcher, DomainRule, DomainRuleGroup, DomainRuleType};
+
+///构造一组与geosite:category-ads-all规模相近的规则
+fn build_group() -> DomainRuleGroup {
+    let mut group = DomainRuleGroup::default();
+    let rule_list = [
+        (DomainRuleType::Domain, 20000, "ads"),
+        (DomainRuleType::Full, 5000, "track"),
+        (DomainRuleType::Keyword, 200, "kw"),
+    ];
+    for (rule_type, count, prefix) in rule_list {
+        for index in 0..count {
+            let value = match rule_type {
+                DomainRuleType::Keyword => format!("{prefix}{index}x"),
+                _ => format!("{prefix}{index}.example{}.com", index % 97),
+            };
+            group.add_rule(DomainRule::new(rule_type.clone(), value));
+        }
+    }
+    for index in 0..20 {
+        let value = format!(r"^ad{index}-[0-9]+\.cdn\.net$");
+        group.add_rule(DomainRule::new(DomainRuleType::Regexp, value));
+    }
+    group
+}
+
+///需要匹配的域名, 大部分不在规则中
+const DOMAIN_LIST: [&str; 6] = [
+    "www.google.com",
+    "static.github.io",
+    "img.ads1234.example70.com",
+    "track42.example42.com",
+    "ad7-123.cdn.net",
+    "a.b.c.d.not-matched.org",
+];
+
+fn domain_match_benchmark(c: &mut Criterion) {
+    let group = build_group();
+    c.bench_function("rule_group_linear", |b| {
+        b.iter(|| {
+            for domain in DOMAIN_LIST {
+                black_box(group.match_domain(black_box(domain)));
+            }
+        })
+    });
+    let matcher = DomainMatcher::try_from(build_group()).unwrap();
+    c.bench_function("compiled_matcher", |b| {
+        b.iter(|| {
+            for domain in DOMAIN_LIST {
+                black_box(matcher.match_domain(black_box(domain)));
+            }
+        })
+    });
+}
+
+criterion_group!(benches, domain_match_benchmark);
+criterion_main!(benches);
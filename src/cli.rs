@@ -2,6 +2,10 @@ mod app;
 mod app_command;
 mod build_geosite_command;
 mod commands;
+mod geo_lookup_command;
+mod geosite_diff_command;
+mod geosite_list_command;
+mod geosite_show_command;
 mod hello_command;
 mod proxy_client_command;
 mod proxy_server_command;
@@ -1,14 +1,15 @@
 use super::app_command::AppCommand;
 use crate::{rt, services::geosite};
 use clap::Args;
+use std::path::Path;
 
 /// 构建geosite文件命令
 #[derive(Args)]
 pub struct BuildGeositeCommand {
-    ///input dir path
+    ///input dir path (domain-list-community source), or a geosite.pak/geosite.dat file
     #[clap(long = "input", short = 'i', value_parser, default_value_t = String::from("./geo_data_src"))]
     input_dir: String,
-    ///output file path
+    ///output file path, use v2fly protobuf format if the extension is .dat
     #[clap(long = "output", short = 'o', value_parser, default_value_t = String::from("./data/geosite.pak"))]
     output_file: String,
 }
@@ -24,11 +25,25 @@ impl AppCommand for BuildGeositeCommand {
 
 impl BuildGeositeCommand {
     async fn build(input_dir: &str, output_file: &str) -> Result<(), String> {
-        let geosite_data = geosite::from_source_dir(input_dir)
-            .await
-            .map_err(|e| e.to_string())?;
-        geosite::save_as_binary(output_file, &geosite_data)
-            .await
-            .map_err(|e| e.to_string())
+        let input_path = Path::new(input_dir);
+        //输入为文件时转换格式
+        let geosite_data = if input_path.is_file() {
+            geosite::from_file(input_path)
+                .await
+                .map_err(|e| e.to_string())?
+        } else {
+            geosite::from_source_dir(input_dir)
+                .await
+                .map_err(|e| e.to_string())?
+        };
+        if output_file.ends_with(".dat") {
+            geosite::save_as_dat(output_file, &geosite_data)
+                .await
+                .map_err(|e| e.to_string())
+        } else {
+            geosite::save_as_binary(output_file, &geosite_data)
+                .await
+                .map_err(|e| e.to_string())
+        }
     }
 }
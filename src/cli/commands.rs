@@ -1,5 +1,7 @@
 use super::{
     app_command::AppCommand, build_geosite_command::BuildGeositeCommand,
+    geo_lookup_command::GeoLookupCommand, geosite_diff_command::GeositeDiffCommand,
+    geosite_list_command::GeositeListCommand, geosite_show_command::GeositeShowCommand,
     hello_command::HelloCommand, proxy_client_command::ProxyClientCommand,
     proxy_server_command::ProxyServerCommand,
 };
@@ -19,6 +21,20 @@ pub enum Commands {
     ProxyClient(ProxyClientCommand),
     #[clap(name = "build_geosite", about = "build geosite data file command")]
     BuildGeoSite(BuildGeositeCommand),
+    #[clap(name = "geosite_list", about = "list geosite tags and attributes")]
+    GeoSiteList(GeositeListCommand),
+    #[clap(
+        name = "geosite_show",
+        about = "show the rules of selection expressions"
+    )]
+    GeoSiteShow(GeositeShowCommand),
+    #[clap(
+        name = "geo_lookup",
+        about = "look up geosite/geoip tags of a domain or ip"
+    )]
+    GeoLookup(GeoLookupCommand),
+    #[clap(name = "geosite_diff", about = "diff two geosite data files")]
+    GeoSiteDiff(GeositeDiffCommand),
 }
 
 impl AppCommand for Commands {
@@ -30,6 +46,10 @@ impl AppCommand for Commands {
             Self::ProxyServer(s) => s.execute(),
             Self::ProxyClient(s) => s.execute(),
             Self::BuildGeoSite(s) => s.execute(),
+            Self::GeoSiteList(s) => s.execute(),
+            Self::GeoSiteShow(s) => s.execute(),
+            Self::GeoLookup(s) => s.execute(),
+            Self::GeoSiteDiff(s) => s.execute(),
         }
     }
 }
@@ -0,0 +1,102 @@
+use super::app_command::AppCommand;
+use crate::{
+    common::geoip::{self, CountryLookup},
+    rt,
+    services::{geoip as geoip_service, geosite},
+};
+use clap::Args;
+use ipnet::IpNet;
+use std::{net::IpAddr, path::PathBuf};
+
+/// 查询域名或者ip匹配的geosite/geoip规则
+#[derive(Args)]
+pub struct GeoLookupCommand {
+    ///domain or ip address
+    #[clap(value_parser)]
+    target: String,
+    ///data dir path
+    #[clap(long, value_parser, default_value_t = String::from("./data"))]
+    data_dir: String,
+}
+
+impl AppCommand for GeoLookupCommand {
+    fn execute(&self) {
+        let fut = Self::lookup(&self.target, &self.data_dir);
+        if let Err(err) = rt::block_on(fut) {
+            log::error!("{}", err);
+        }
+    }
+}
+
+impl GeoLookupCommand {
+    async fn lookup(target: &str, data_dir: &str) -> Result<(), String> {
+        //ipv6地址可能带有中括号
+        let ip_text = target.trim_start_matches('[').trim_end_matches(']');
+        match ip_text.parse() {
+            Ok(ip_addr) => Self::lookup_ip(&ip_addr, data_dir).await,
+            Err(_) => Self::lookup_domain(&target.to_lowercase(), data_dir).await,
+        }
+    }
+
+    ///输出包含这个域名的geosite tag和匹配的规则
+    async fn lookup_domain(domain: &str, data_dir: &str) -> Result<(), String> {
+        let geosite_data = geosite::from_data_dir(data_dir)
+            .await
+            .map_err(|e| e.to_string())?;
+        let matched: Vec<bool> = geosite_data
+            .all_rules
+            .iter()
+            .map(|rule| rule.match_domain(domain))
+            .collect();
+        let mut file_names: Vec<&String> = geosite_data.file_rules.keys().collect();
+        file_names.sort();
+        for file_name in file_names {
+            let mut rule_ids: Vec<usize> = geosite_data.file_rules[file_name]
+                .iter()
+                .copied()
+                .filter(|rule_id| matched[*rule_id])
+                .collect();
+            rule_ids.sort_unstable();
+            for rule_id in rule_ids {
+                let rule = &geosite_data.all_rules[rule_id];
+                println!("geosite:{file_name:<32} {rule}");
+            }
+        }
+        Ok(())
+    }
+
+    ///输出这个ip所属的geoip tag, 包括mmdb查询到的国家/地区代码
+    async fn lookup_ip(ip_addr: &IpAddr, data_dir: &str) -> Result<(), String> {
+        let private_matched = geoip::private_cidr_list()
+            .into_iter()
+            .filter_map(|cidr| cidr.parse::<IpNet>().ok())
+            .any(|ip_net| ip_net.contains(ip_addr));
+        if private_matched {
+            println!("geoip:private (built-in)");
+        }
+        if let Some(geoip_sets) = geoip_service::from_data_dir(data_dir)
+            .await
+            .map_err(|e| e.to_string())?
+        {
+            let mut tags: Vec<&str> = geoip_sets
+                .iter()
+                .filter(|(_, net_list)| net_list.iter().any(|ip_net| ip_net.contains(ip_addr)))
+                .map(|(tag, _)| tag)
+                .collect();
+            tags.sort_unstable();
+            for tag in tags {
+                println!("geoip:{tag}");
+            }
+        }
+        let mmdb_data_path = PathBuf::from(format!("{data_dir}/GeoLite2-Country.mmdb"));
+        if mmdb_data_path.exists() {
+            let mmdb_data = geoip_service::load_mmdb(&mmdb_data_path)
+                .await
+                .map_err(|e| e.to_string())?;
+            if let Some(country_code) = CountryLookup::from(mmdb_data).lookup(ip_addr) {
+                println!("geoip:{} (mmdb)", country_code.to_lowercase());
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use super::app_command::AppCommand;
+use crate::{common::geosite::GeoSite, rt, services::geosite};
+use clap::Args;
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::Path,
+};
+
+/// 比较两个geosite数据文件
+#[derive(Args)]
+pub struct GeositeDiffCommand {
+    ///old geosite.pak/geosite.dat file
+    #[clap(value_parser)]
+    old_file: String,
+    ///new geosite.pak/geosite.dat file
+    #[clap(value_parser)]
+    new_file: String,
+}
+
+impl AppCommand for GeositeDiffCommand {
+    fn execute(&self) {
+        let fut = Self::diff(&self.old_file, &self.new_file);
+        if let Err(err) = rt::block_on(fut) {
+            log::error!("{}", err);
+        }
+    }
+}
+
+impl GeositeDiffCommand {
+    async fn diff(old_file: &str, new_file: &str) -> Result<(), String> {
+        let old_data = geosite::from_file(Path::new(old_file))
+            .await
+            .map_err(|e| format!("load {old_file} failed: {e}"))?;
+        let new_data = geosite::from_file(Path::new(new_file))
+            .await
+            .map_err(|e| format!("load {new_file} failed: {e}"))?;
+        let old_tags = Self::tag_rules(&old_data);
+        let new_tags = Self::tag_rules(&new_data);
+        let all_tags: BTreeSet<&str> = old_tags.keys().chain(new_tags.keys()).copied().collect();
+        let (mut added, mut removed, mut changed) = (0, 0, 0);
+        for tag in all_tags {
+            match (old_tags.get(tag), new_tags.get(tag)) {
+                (None, Some(rules)) => {
+                    added += 1;
+                    println!("+ {tag} ({} rules)", rules.len());
+                }
+                (Some(rules), None) => {
+                    removed += 1;
+                    println!("- {tag} ({} rules)", rules.len());
+                }
+                (Some(old_rules), Some(new_rules)) if old_rules != new_rules => {
+                    changed += 1;
+                    println!("~ {tag}");
+                    for rule in old_rules.difference(new_rules) {
+                        println!("    - {rule}");
+                    }
+                    for rule in new_rules.difference(old_rules) {
+                        println!("    + {rule}");
+                    }
+                }
+                _ => (),
+            }
+        }
+        log::info!("{added} tags added, {removed} tags removed, {changed} tags changed");
+        Ok(())
+    }
+
+    ///每个tag中的规则, 使用源文件的格式表示
+    fn tag_rules(geosite_data: &GeoSite) -> HashMap<&str, BTreeSet<String>> {
+        geosite_data
+            .file_rules
+            .iter()
+            .map(|(file_name, rule_ids)| {
+                let rules = rule_ids
+                    .iter()
+                    .map(|rule_id| geosite_data.all_rules[*rule_id].to_string())
+                    .collect();
+                (file_name.as_str(), rules)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,48 @@
+use super::app_command::AppCommand;
+use crate::{rt, services::geosite};
+use clap::Args;
+use std::collections::BTreeSet;
+
+/// 列出geosite中所有的tag和属性
+#[derive(Args)]
+pub struct GeositeListCommand {
+    ///data dir path
+    #[clap(long, value_parser, default_value_t = String::from("./data"))]
+    data_dir: String,
+}
+
+impl AppCommand for GeositeListCommand {
+    fn execute(&self) {
+        let fut = Self::list(&self.data_dir);
+        if let Err(err) = rt::block_on(fut) {
+            log::error!("{}", err);
+        }
+    }
+}
+
+impl GeositeListCommand {
+    async fn list(data_dir: &str) -> Result<(), String> {
+        let geosite_data = geosite::from_data_dir(data_dir)
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut file_names: Vec<&String> = geosite_data.file_rules.keys().collect();
+        file_names.sort();
+        for file_name in file_names {
+            let rule_ids = &geosite_data.file_rules[file_name];
+            //这个tag中的规则出现过的属性
+            let attr_names: BTreeSet<&str> = rule_ids
+                .iter()
+                .filter_map(|rule_id| geosite_data.all_rules[*rule_id].attrs.as_ref())
+                .flatten()
+                .map(|attr| attr.name.as_str())
+                .collect();
+            let mut line = format!("{file_name:<40} {:>6} rules", rule_ids.len());
+            for attr_name in attr_names {
+                line.push_str(&format!(" @{attr_name}"));
+            }
+            println!("{line}");
+        }
+        log::info!("{} tags", geosite_data.file_rules.len());
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use super::app_command::AppCommand;
+use crate::{rt, services::geosite};
+use clap::Args;
+use std::collections::HashMap;
+
+/// 打印selection表达式选择的所有规则
+#[derive(Args)]
+pub struct GeositeShowCommand {
+    ///selection expressions, for example geosite:google@cn
+    #[clap(value_parser, required = true)]
+    selection: Vec<String>,
+    ///data dir path
+    #[clap(long, value_parser, default_value_t = String::from("./data"))]
+    data_dir: String,
+}
+
+impl AppCommand for GeositeShowCommand {
+    fn execute(&self) {
+        let fut = Self::show(&self.selection, &self.data_dir);
+        if let Err(err) = rt::block_on(fut) {
+            log::error!("{}", err);
+        }
+    }
+}
+
+impl GeositeShowCommand {
+    async fn show(selection: &[String], data_dir: &str) -> Result<(), String> {
+        let geosite_data = geosite::from_data_dir(data_dir)
+            .await
+            .map_err(|e| e.to_string())?;
+        let rule_group =
+            geosite::parse_domain_rule_group(selection, &geosite_data, &HashMap::new())
+                .map_err(|e| e.to_string())?;
+        let rule_lists = [
+            ("full", &rule_group.full_list),
+            ("domain", &rule_group.domain_list),
+            ("keyword", &rule_group.keyword_list),
+            ("regexp", &rule_group.regexp_list),
+        ];
+        let mut rule_count = 0;
+        for (type_name, rule_list) in rule_lists {
+            let mut values: Vec<&String> = rule_list.iter().collect();
+            values.sort();
+            for value in values {
+                println!("{type_name}:{value}");
+            }
+            rule_count += rule_list.len();
+        }
+        log::info!("{rule_count} rules");
+        Ok(())
+    }
+}
@@ -29,7 +29,7 @@ impl AppCommand for ProxyServerCommand {
             log::info!("start {worker_count} workers")
         }
         let runtime = rt::runtime(worker_count_opt);
-        let fut = proxy_server::execute(config);
+        let fut = proxy_server::execute(config, &self.config_file);
         //使用tokio运行时
         if let Err(err) = runtime.block_on(fut) {
             log::error!("{}", err);
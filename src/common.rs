@@ -2,26 +2,66 @@ mod auth_user;
 mod client_config;
 mod client_error;
 mod config_error;
+mod dns_config;
+mod domain_strategy;
+mod fake_ip_pool;
+mod forward_outbound_config;
 ///ip规则模块
 pub mod geoip;
 ///geosite域名规则相关
 pub mod geosite;
+mod group_strategy;
+mod inbound_config;
+mod inbound_protocol;
+mod local_user;
 ///消息模块
 pub mod msg;
+mod outbound_config;
+mod outbound_group_config;
+mod resolve_domain;
+mod route_conditions;
 mod route_config;
 mod route_config_com;
+mod route_context;
+mod route_network;
+mod rule_set;
+mod rule_set_config;
+mod rule_set_type;
 mod server_config;
 mod server_error;
 ///Socket 5 协议相关
 pub mod socks5;
+mod upstream_proxy_config;
+mod upstream_proxy_protocol;
 mod websocket_request;
 
 pub use auth_user::AuthUser;
 pub use client_config::ClientConfig;
 pub use client_error::ClientError;
 pub use config_error::ConfigError;
+pub use dns_config::DnsConfig;
+pub use domain_strategy::DomainStrategy;
+pub use fake_ip_pool::FakeIpPool;
+pub use forward_outbound_config::ForwardOutboundConfig;
+pub use group_strategy::GroupStrategy;
+pub use inbound_config::InboundConfig;
+pub use inbound_protocol::InboundProtocol;
+pub use local_user::LocalUser;
+pub use outbound_config::OutboundConfig;
+pub use outbound_group_config::OutboundGroupConfig;
+pub use resolve_domain::{ResolveDomain, ResolveDomainError};
+pub use route_conditions::{ParseRouteConditionError, RouteConditions};
 pub use route_config::{RouteConfig, RouteConfigAction, RouteConfigRule};
-pub use route_config_com::{RouteConfigCom, RouteConfigDomainRuleCom, RouteConfigIpRuleCom};
+pub use route_config_com::{
+    RouteConfigCom, RouteConfigDomainRuleCom, RouteConfigIpRuleCom, RouteConfigRuleCom,
+};
+pub use route_context::RouteContext;
+pub use route_network::RouteNetwork;
+pub use rule_set::RuleSet;
+pub use rule_set_config::RuleSetConfig;
+pub use rule_set_type::RuleSetType;
 pub use server_config::ServerConfig;
 pub use server_error::ServerError;
+pub use upstream_proxy_config::UpstreamProxyConfig;
+pub use upstream_proxy_protocol::UpstreamProxyProtocol;
 pub use websocket_request::{ParseWebsocketRequestError, WebsocketRequest};
@@ -1,23 +1,117 @@
-use super::AuthUser;
+use super::{
+    AuthUser, DnsConfig, ForwardOutboundConfig, InboundConfig, InboundProtocol, LocalUser,
+    OutboundConfig, OutboundGroupConfig, UpstreamProxyConfig,
+};
 use serde::Deserialize;
 
+///默认服务端的tag
+const DEFAULT_OUTBOUND_TAG: &str = "default";
+
 #[derive(Deserialize, Debug, Clone)]
 ///客户端配置
 pub struct ClientConfig {
-    ///本地监听地址
-    pub address: String,
+    ///本地监听地址, 与 `port` 一起作为一个mixed监听
+    pub address: Option<String>,
     ///本地监听端口
-    pub port: u16,
+    pub port: Option<u16>,
+    ///额外的本地监听列表
+    pub inbounds: Option<Vec<InboundConfig>>,
     ///授权用户配置
     pub auth_user: AuthUser,
+    ///本地代理的授权用户, 为空时不需要授权
+    pub local_users: Option<Vec<LocalUser>>,
+    ///是否嗅探目标为ip地址的连接中的域名(TLS SNI 或者 HTTP Host), 用于域名路由
+    pub sniffing: Option<bool>,
+    ///是否使用嗅探到的域名作为连接的目标地址
+    pub sniff_override_dest: Option<bool>,
     ///服务端url
     pub server_url: String,
     ///连接池最多空闲连接个数
     pub max_idle_conns: u32,
+    ///每个连接最多同时承载的stream个数,默认为32
+    pub max_streams_per_conn: Option<u32>,
     ///指定ip来建立tcp连接
     pub server_ip: Option<String>,
     ///自定义ssl ca文件路径
     pub ssl_ca_path: Option<String>,
     ///额外的http请求头
     pub extra_http_headers: Option<Vec<[String; 2]>>,
+    ///前置代理, 通过这个代理连接服务端
+    pub upstream_proxy: Option<UpstreamProxyConfig>,
+    ///内置的dns服务
+    pub dns: Option<DnsConfig>,
+    ///额外的上游服务端列表
+    pub outbounds: Option<Vec<OutboundConfig>>,
+    ///服务端分组列表
+    pub outbound_groups: Option<Vec<OutboundGroupConfig>>,
+    ///第三方socks5/http代理出口列表
+    pub forward_outbounds: Option<Vec<ForwardOutboundConfig>>,
+}
+
+impl ClientConfig {
+    ///所有的本地监听, 没有单独设置的选项使用 `[client]` 中的配置
+    pub fn inbound_list(&self) -> Vec<InboundConfig> {
+        let mut inbound_list = Vec::new();
+        if let (Some(address), Some(port)) = (&self.address, self.port) {
+            inbound_list.push(InboundConfig {
+                tag: None,
+                address: address.to_string(),
+                port,
+                protocol: InboundProtocol::Mixed,
+                local_users: None,
+                sniffing: None,
+                sniff_override_dest: None,
+            });
+        }
+        if let Some(inbounds) = &self.inbounds {
+            inbound_list.extend(inbounds.iter().cloned());
+        }
+        for inbound in inbound_list.iter_mut() {
+            if inbound.local_users.is_none() {
+                inbound.local_users = self.local_users.clone();
+            }
+            if inbound.sniffing.is_none() {
+                inbound.sniffing = self.sniffing;
+            }
+            if inbound.sniff_override_dest.is_none() {
+                inbound.sniff_override_dest = self.sniff_override_dest;
+            }
+        }
+        inbound_list
+    }
+
+    ///使用 `[client]` 中的服务端配置作为默认的outbound
+    pub fn default_outbound(&self) -> OutboundConfig {
+        OutboundConfig {
+            tag: DEFAULT_OUTBOUND_TAG.to_string(),
+            server_url: self.server_url.to_string(),
+            auth_user: self.auth_user.to_owned(),
+            server_ip: self.server_ip.to_owned(),
+            ssl_ca_path: self.ssl_ca_path.to_owned(),
+            extra_http_headers: self.extra_http_headers.to_owned(),
+            max_idle_conns: Some(self.max_idle_conns),
+            max_streams_per_conn: self.max_streams_per_conn,
+            upstream_proxy: self.upstream_proxy.to_owned(),
+        }
+    }
+
+    ///额外的outbound列表, 没有单独设置的选项使用 `[client]` 中的配置
+    pub fn outbound_list(&self) -> Vec<OutboundConfig> {
+        let mut outbound_list = self.outbounds.clone().unwrap_or_default();
+        for outbound in outbound_list.iter_mut() {
+            if outbound.extra_http_headers.is_none() {
+                outbound.extra_http_headers = self.extra_http_headers.clone();
+            }
+            if outbound.max_idle_conns.is_none() {
+                outbound.max_idle_conns = Some(self.max_idle_conns);
+            }
+            if outbound.max_streams_per_conn.is_none() {
+                outbound.max_streams_per_conn = self.max_streams_per_conn;
+            }
+            if outbound.upstream_proxy.is_none() {
+                outbound.upstream_proxy = self.upstream_proxy.clone();
+            }
+        }
+        outbound_list
+    }
 }
@@ -1,10 +1,11 @@
-use super::{ConfigError, ParseWebsocketRequestError};
+use super::{ConfigError, ParseRouteConditionError, ParseWebsocketRequestError};
 use crate::services::{
-    geoip::ParseIpSelectionError,
-    geosite::{FromBinaryError, ParseDomainSelectionError},
+    geoip::{LoadGeoIpError, ParseIpSelectionError},
+    geosite::{FromFileError, ParseDomainSelectionError},
+    rule_set::LoadRuleSetError,
 };
 use maxminddb::MaxMindDBError;
-use std::io::Error as IoError;
+use std::{io::Error as IoError, net::AddrParseError};
 use thiserror::Error;
 use tokio_tungstenite::tungstenite::Error as WsError;
 
@@ -13,22 +14,42 @@ use tokio_tungstenite::tungstenite::Error as WsError;
 pub enum ClientError {
     #[error("load {0} failed: {1}")]
     Config(String, ConfigError),
+    #[error("no inbound configured")]
+    NoInbound,
+    #[error("invalid dns upstream {0}: {1}")]
+    DnsUpstream(String, AddrParseError),
+    #[error("invalid fake-ip range {0}")]
+    FakeIpRange(String),
     #[error("bind address {0} failed: {1}")]
     Bind(String, IoError),
     #[error("wait signal failed: {0}")]
     WaitSignal(IoError),
-    #[error("init manger failed: {0}")]
-    InitManger(#[from] ParseWebsocketRequestError),
-    #[error("check server status failed: {0}")]
-    CheckConn(WsError),
+    #[error("init outbound {0} failed: {1}")]
+    InitManger(String, ParseWebsocketRequestError),
+    #[error("duplicate outbound tag {0}")]
+    DuplicateOutbound(String),
+    #[error("outbound {0} not found")]
+    UnknownOutbound(String),
+    #[error("forward outbound {0} not found")]
+    UnknownForward(String),
+    #[error("outbound group {0} is empty")]
+    EmptyOutboundGroup(String),
+    #[error("check server {0} status failed: {1}")]
+    CheckConn(String, Box<WsError>),
     #[error("run http service failed: {0}")]
     HttpService(IoError),
     #[error("load geosite data failed: {0}")]
-    LoadGeoSite(#[from] FromBinaryError),
+    LoadGeoSite(#[from] FromFileError),
     #[error("load mmdb data failed: {0}")]
     LoadMmdb(#[from] MaxMindDBError),
+    #[error("{0}")]
+    LoadGeoIp(#[from] LoadGeoIpError),
+    #[error("load rule-set failed: {0}")]
+    LoadRuleSet(#[from] LoadRuleSetError),
     #[error("parse route domain selection failed: {0}")]
     ParseDomainSelection(#[from] ParseDomainSelectionError),
     #[error("parse route ip selection failed: {0}")]
     ParseIpSelection(#[from] ParseIpSelectionError),
+    #[error("parse route condition failed: {0}")]
+    ParseRouteCondition(#[from] ParseRouteConditionError),
 }
@@ -0,0 +1,51 @@
+use super::FakeIpPool;
+use ipnet::{Ipv4Net, Ipv6Net};
+use serde::Deserialize;
+
+///默认的fake-ip ipv4地址段
+const DEFAULT_FAKE_IP_RANGE: &str = "198.18.0.0/15";
+///默认的fake-ip ipv6地址段
+const DEFAULT_FAKE_IP6_RANGE: &str = "fc00::/18";
+
+#[derive(Deserialize, Debug, Clone)]
+///客户端内置的dns服务配置
+pub struct DnsConfig {
+    ///dns服务监听地址
+    pub address: String,
+    ///dns服务监听端口, 同时监听udp和tcp
+    pub port: u16,
+    ///直连域名使用的上游dns服务器(udp), 例如 `223.5.5.5:53`
+    pub direct_upstream: String,
+    ///代理域名使用的上游dns服务器, 通过服务端使用tcp查询, 例如 `8.8.8.8:53`
+    pub proxy_upstream: String,
+    ///是否对代理的域名返回fake-ip
+    pub fake_ip: Option<bool>,
+    ///fake-ip使用的ipv4地址段, 默认为 `198.18.0.0/15`
+    pub fake_ip_range: Option<String>,
+    ///fake-ip使用的ipv6地址段, 默认为 `fc00::/18`
+    pub fake_ip6_range: Option<String>,
+}
+
+impl DnsConfig {
+    ///开启fake-ip时构造地址池, 出错时返回无效的地址段和原因
+    pub fn fake_ip_pool(&self) -> Result<Option<FakeIpPool>, String> {
+        if !self.fake_ip.unwrap_or_default() {
+            return Ok(None);
+        }
+        let ipv4_range = self
+            .fake_ip_range
+            .as_deref()
+            .unwrap_or(DEFAULT_FAKE_IP_RANGE);
+        let ipv6_range = self
+            .fake_ip6_range
+            .as_deref()
+            .unwrap_or(DEFAULT_FAKE_IP6_RANGE);
+        let ipv4_net = ipv4_range
+            .parse::<Ipv4Net>()
+            .map_err(|e| format!("{ipv4_range}: {e}"))?;
+        let ipv6_net = ipv6_range
+            .parse::<Ipv6Net>()
+            .map_err(|e| format!("{ipv6_range}: {e}"))?;
+        FakeIpPool::try_new(ipv4_net.trunc(), ipv6_net.trunc()).map(Some)
+    }
+}
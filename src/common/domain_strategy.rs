@@ -0,0 +1,16 @@
+use serde::Deserialize;
+
+///域名的路由策略, 决定是否解析域名之后匹配ip规则
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+pub enum DomainStrategy {
+    ///只使用域名规则匹配
+    #[default]
+    #[serde(rename(deserialize = "as_is"))]
+    AsIs,
+    ///没有匹配到域名规则时, 解析域名并使用ip规则匹配
+    #[serde(rename(deserialize = "ip_if_non_match"))]
+    IpIfNonMatch,
+    ///总是解析域名, 先使用ip规则匹配, 没有匹配到时再使用域名规则
+    #[serde(rename(deserialize = "ip_on_demand"))]
+    IpOnDemand,
+}
@@ -0,0 +1,145 @@
+use ipnet::{Ipv4Net, Ipv6Net};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    sync::Mutex,
+};
+
+///地址池的最大容量
+const MAX_POOL_SIZE: u32 = 1 << 20;
+///地址池的最小容量, 也就是ipv4的 `/24` 或者ipv6的 `/120` 去掉网络地址和广播地址,
+///地址太少时很快就会复用, 已经返回给客户端的fake-ip会对应到其他域名
+const MIN_POOL_SIZE: u32 = 254;
+
+///fake-ip地址池, 保存fake-ip和域名的双向映射
+///
+///同一个域名的ipv4和ipv6地址使用相同的序号, 地址用完之后从头开始复用最早分配的地址
+#[derive(Debug)]
+pub struct FakeIpPool {
+    ipv4_net: Ipv4Net,
+    ipv6_net: Ipv6Net,
+    ///可以分配的地址个数, 序号范围为 `1..=size`
+    size: u32,
+    state: Mutex<FakeIpState>,
+}
+
+#[derive(Debug, Default)]
+struct FakeIpState {
+    ///下一个分配的序号
+    next_index: u32,
+    domain_map: HashMap<String, u32>,
+    index_map: HashMap<u32, String>,
+}
+
+impl FakeIpPool {
+    ///使用两个地址段构造地址池, 可以分配的地址少于 [`MIN_POOL_SIZE`] 时返回错误信息
+    pub fn try_new(ipv4_net: Ipv4Net, ipv6_net: Ipv6Net) -> Result<Self, String> {
+        //排除网络地址和广播地址
+        let ipv4_size = (1u64 << (32 - ipv4_net.prefix_len())).saturating_sub(2);
+        let ipv6_size = 1u128
+            .checked_shl(128 - ipv6_net.prefix_len() as u32)
+            .unwrap_or(u128::MAX)
+            .saturating_sub(2);
+        let size = (ipv4_size as u128)
+            .min(ipv6_size)
+            .min(MAX_POOL_SIZE as u128) as u32;
+        if size < MIN_POOL_SIZE {
+            return Err(format!(
+                "{ipv4_net} and {ipv6_net} have {size} usable addresses, at least {MIN_POOL_SIZE} are required"
+            ));
+        }
+        Ok(Self {
+            ipv4_net,
+            ipv6_net,
+            size,
+            state: Mutex::new(FakeIpState {
+                next_index: 1,
+                ..Default::default()
+            }),
+        })
+    }
+
+    ///获取域名对应的ipv4和ipv6地址, 没有分配过时分配新的地址
+    pub fn allocate(&self, domain: &str) -> (Ipv4Addr, Ipv6Addr) {
+        let index = {
+            let mut state = self.state.lock().unwrap();
+            match state.domain_map.get(domain) {
+                Some(s) => *s,
+                None => {
+                    let index = state.next_index;
+                    state.next_index = if index >= self.size { 1 } else { index + 1 };
+                    //回收旧的域名
+                    if let Some(old_domain) = state.index_map.insert(index, domain.to_string()) {
+                        state.domain_map.remove(&old_domain);
+                    }
+                    state.domain_map.insert(domain.to_string(), index);
+                    index
+                }
+            }
+        };
+        let ipv4 = Ipv4Addr::from(u32::from(self.ipv4_net.network()) + index);
+        let ipv6 = Ipv6Addr::from(u128::from(self.ipv6_net.network()) + index as u128);
+        (ipv4, ipv6)
+    }
+
+    ///查找fake-ip对应的域名
+    pub fn lookup(&self, ip: &IpAddr) -> Option<String> {
+        let index = match ip {
+            IpAddr::V4(s) if self.ipv4_net.contains(s) => {
+                u32::from(*s) - u32::from(self.ipv4_net.network())
+            }
+            IpAddr::V6(s) if self.ipv6_net.contains(s) => {
+                let index = u128::from(*s) - u128::from(self.ipv6_net.network());
+                u32::try_from(index).ok()?
+            }
+            _ => return None,
+        };
+        let state = self.state.lock().unwrap();
+        state.index_map.get(&index).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn try_new(ipv4_range: &str, ipv6_range: &str) -> Result<FakeIpPool, String> {
+        FakeIpPool::try_new(ipv4_range.parse().unwrap(), ipv6_range.parse().unwrap())
+    }
+
+    #[test]
+    fn reject_small_range() {
+        for (ipv4_range, ipv6_range) in [
+            ("198.18.0.0/32", "fc00::/18"),
+            ("198.18.0.0/31", "fc00::/18"),
+            ("198.18.0.0/25", "fc00::/18"),
+            ("198.18.0.0/15", "fc00::/128"),
+            ("198.18.0.0/15", "fc00::/127"),
+            ("198.18.0.0/15", "fc00::/121"),
+        ] {
+            assert!(
+                try_new(ipv4_range, ipv6_range).is_err(),
+                "{ipv4_range} {ipv6_range}"
+            );
+        }
+        assert!(try_new("198.18.0.0/24", "fc00::/120").is_ok());
+    }
+
+    #[test]
+    fn allocate_in_range() {
+        let fake_ip_pool = try_new("198.18.0.0/24", "fc00::/120").unwrap();
+        let ipv4_net: Ipv4Net = "198.18.0.0/24".parse().unwrap();
+        let mut first_ip = None;
+        //地址用完之后从头复用, 不会分配网络地址和广播地址
+        for i in 0..(MIN_POOL_SIZE + 1) {
+            let (ipv4, _) = fake_ip_pool.allocate(&format!("{i}.example.com"));
+            assert!(ipv4_net.contains(&ipv4));
+            assert_ne!(ipv4, ipv4_net.network());
+            assert_ne!(ipv4, ipv4_net.broadcast());
+            first_ip.get_or_insert(ipv4);
+        }
+        let first_ip = IpAddr::V4(first_ip.unwrap());
+        let domain = format!("{MIN_POOL_SIZE}.example.com");
+        assert_eq!(fake_ip_pool.lookup(&first_ip), Some(domain));
+    }
+}
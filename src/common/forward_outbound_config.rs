@@ -0,0 +1,11 @@
+use super::UpstreamProxyConfig;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Clone)]
+///第三方socks5/http代理出口, 路由规则中使用 `forward:tag` 选择
+pub struct ForwardOutboundConfig {
+    pub tag: String,
+    ///代理的协议、地址和认证信息
+    #[serde(flatten)]
+    pub proxy: UpstreamProxyConfig,
+}
@@ -1,7 +1,17 @@
+mod country_lookup;
+mod geoip_sets;
+mod ip_matcher;
+mod ip_range_set;
 mod ip_rule_group;
 mod ip_rule_type;
+mod parse_ip_net;
 mod private_cidr;
 
+pub use country_lookup::CountryLookup;
+pub use geoip_sets::GeoIpSets;
+pub use ip_matcher::{IpMatcher, ParseCidrError};
+pub use ip_range_set::IpRangeSet;
 pub use ip_rule_group::IpRuleGroup;
 pub use ip_rule_type::IpRuleType;
+pub use parse_ip_net::parse_ip_net;
 pub use private_cidr::private_cidr_list;
@@ -0,0 +1,56 @@
+use lru::LruCache;
+use maxminddb::{geoip2::Country, Reader};
+use std::{
+    net::IpAddr,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+};
+
+///缓存最近查询的ip个数
+const CACHE_SIZE: usize = 4096;
+
+///使用geoip数据库查询ip所属的国家/地区代码, 缓存最近的查询结果
+pub struct CountryLookup {
+    mmdb_data: Reader<Vec<u8>>,
+    cache: Mutex<LruCache<IpAddr, Option<Arc<str>>>>,
+}
+
+impl From<Reader<Vec<u8>>> for CountryLookup {
+    fn from(mmdb_data: Reader<Vec<u8>>) -> Self {
+        let cache_size = NonZeroUsize::new(CACHE_SIZE).unwrap();
+        Self {
+            mmdb_data,
+            cache: Mutex::new(LruCache::new(cache_size)),
+        }
+    }
+}
+
+impl std::fmt::Debug for CountryLookup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CountryLookup").finish()
+    }
+}
+
+impl CountryLookup {
+    ///查询国家/地区代码, 没有找到时返回 `None`
+    pub fn lookup(&self, address: &IpAddr) -> Option<Arc<str>> {
+        if let Some(country_code) = self.cache.lock().unwrap().get(address) {
+            return country_code.clone();
+        }
+        let country_code = match self.mmdb_data.lookup::<Country>(*address) {
+            Ok(country_info) => country_info
+                .country
+                .and_then(|country| country.iso_code)
+                .map(Arc::from),
+            Err(e) => {
+                log::error!("lookup {address} in mmdb failed: {e}");
+                None
+            }
+        };
+        self.cache
+            .lock()
+            .unwrap()
+            .put(*address, country_code.clone());
+        country_code
+    }
+}
@@ -0,0 +1,42 @@
+use ipnet::IpNet;
+use std::collections::HashMap;
+
+///从 `geoip.dat` 或者cidr列表目录加载的ip地址段, 按照tag(国家/地区代码或者 `telegram` 这样的名称)分组
+///
+///tag不区分大小写
+#[derive(Debug, Default)]
+pub struct GeoIpSets {
+    sets: HashMap<String, Vec<IpNet>>,
+}
+
+impl GeoIpSets {
+    ///添加地址段, tag已经存在时合并
+    pub fn insert(&mut self, tag: &str, net_list: impl IntoIterator<Item = IpNet>) {
+        self.sets
+            .entry(tag.to_lowercase())
+            .or_default()
+            .extend(net_list);
+    }
+
+    ///合并另一组数据
+    pub fn extend(&mut self, other: GeoIpSets) {
+        for (tag, net_list) in other.sets {
+            self.insert(&tag, net_list);
+        }
+    }
+
+    pub fn get(&self, tag: &str) -> Option<&[IpNet]> {
+        self.sets.get(&tag.to_lowercase()).map(Vec::as_slice)
+    }
+
+    ///所有的tag和对应的地址段
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[IpNet])> {
+        self.sets
+            .iter()
+            .map(|(tag, net_list)| (tag.as_str(), net_list.as_slice()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sets.is_empty()
+    }
+}
@@ -0,0 +1,55 @@
+use super::{IpRangeSet, IpRuleGroup};
+use ipnet::IpNet;
+use std::net::IpAddr;
+use thiserror::Error;
+
+///cidr格式错误
+#[derive(Error, Debug)]
+#[error("invalid cidr {0}")]
+pub struct ParseCidrError(pub String);
+
+///由 [`IpRuleGroup`] 编译得到的ip匹配器
+#[derive(Debug)]
+pub struct IpMatcher {
+    ///cidr规则
+    range_set: IpRangeSet,
+    ///国家/地区代码规则
+    country_code_list: Vec<String>,
+}
+
+impl TryFrom<IpRuleGroup> for IpMatcher {
+    type Error = ParseCidrError;
+
+    fn try_from(group: IpRuleGroup) -> Result<Self, Self::Error> {
+        let mut net_list = Vec::with_capacity(group.cidr_list.len());
+        for cidr in group.cidr_list {
+            match cidr.parse::<IpNet>() {
+                Ok(s) => net_list.push(s),
+                Err(_) => return Err(ParseCidrError(cidr)),
+            }
+        }
+        Ok(Self {
+            range_set: net_list.into_iter().collect(),
+            country_code_list: group.country_code_list.into_iter().collect(),
+        })
+    }
+}
+
+impl IpMatcher {
+    ///ip是否在cidr规则中
+    pub fn match_cidr(&self, address: &IpAddr) -> bool {
+        self.range_set.contains(address)
+    }
+
+    ///是否包含国家/地区代码规则, 不包含时不需要查询geoip数据库
+    pub fn has_country_rules(&self) -> bool {
+        !self.country_code_list.is_empty()
+    }
+
+    ///国家/地区代码是否匹配, 不区分大小写
+    pub fn match_country(&self, country_code: &str) -> bool {
+        self.country_code_list
+            .iter()
+            .any(|code| country_code.eq_ignore_ascii_case(code))
+    }
+}
@@ -0,0 +1,62 @@
+use ipnet::IpNet;
+use std::net::IpAddr;
+
+///排序合并之后的ip地址段, 使用二分查找匹配
+#[derive(Debug, Default)]
+pub struct IpRangeSet {
+    ///ipv4地址段的起止地址(包含)
+    ipv4_ranges: Vec<(u32, u32)>,
+    ///ipv6地址段的起止地址(包含)
+    ipv6_ranges: Vec<(u128, u128)>,
+}
+
+impl FromIterator<IpNet> for IpRangeSet {
+    fn from_iter<T: IntoIterator<Item = IpNet>>(iter: T) -> Self {
+        let mut ipv4_ranges = Vec::new();
+        let mut ipv6_ranges = Vec::new();
+        for ip_net in iter {
+            match ip_net {
+                IpNet::V4(s) => ipv4_ranges.push((s.network().into(), s.broadcast().into())),
+                IpNet::V6(s) => ipv6_ranges.push((s.network().into(), s.broadcast().into())),
+            }
+        }
+        Self {
+            ipv4_ranges: merge_ranges(ipv4_ranges),
+            ipv6_ranges: merge_ranges(ipv6_ranges),
+        }
+    }
+}
+
+impl IpRangeSet {
+    ///是否没有任何地址段
+    pub fn is_empty(&self) -> bool {
+        self.ipv4_ranges.is_empty() && self.ipv6_ranges.is_empty()
+    }
+
+    pub fn contains(&self, address: &IpAddr) -> bool {
+        match address {
+            IpAddr::V4(s) => ranges_contains(&self.ipv4_ranges, u32::from(*s)),
+            IpAddr::V6(s) => ranges_contains(&self.ipv6_ranges, u128::from(*s)),
+        }
+    }
+}
+
+///排序并合并重叠的地址段
+fn merge_ranges<T: Ord + Copy>(mut ranges: Vec<(T, T)>) -> Vec<(T, T)> {
+    ranges.sort_unstable();
+    let mut merged_ranges: Vec<(T, T)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged_ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged_ranges.push((start, end)),
+        }
+    }
+    merged_ranges.shrink_to_fit();
+    merged_ranges
+}
+
+///查找最后一个起始地址不大于 `value` 的地址段
+fn ranges_contains<T: Ord + Copy>(ranges: &[(T, T)], value: T) -> bool {
+    let index = ranges.partition_point(|(start, _)| *start <= value);
+    index > 0 && ranges[index - 1].1 >= value
+}
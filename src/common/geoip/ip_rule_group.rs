@@ -1,11 +1,8 @@
-use super::IpRuleType;
-use ipnet::IpNet;
-use maxminddb::geoip2::Country;
-use maxminddb::Reader;
-use std::{collections::HashSet, net::IpAddr};
+use super::{GeoIpSets, IpRuleType};
+use std::collections::HashSet;
 
 ///代表选择的一组匹配规则
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct IpRuleGroup {
     pub cidr_list: HashSet<String>,
     pub country_code_list: HashSet<String>,
@@ -27,40 +24,30 @@ impl IpRuleGroup {
         }
     }
 
+    pub fn add_group(&mut self, group: IpRuleGroup) {
+        self.cidr_list.extend(group.cidr_list);
+        self.country_code_list.extend(group.country_code_list);
+    }
+
+    ///使用 `geoip.dat` 或者cidr列表中的地址段替换对应的国家/地区代码规则
+    ///
+    ///没有找到的代码保留, 匹配时使用mmdb查询
+    pub fn expand_geoip_sets(&mut self, geoip_sets: &GeoIpSets) {
+        let cidr_list = &mut self.cidr_list;
+        self.country_code_list
+            .retain(|country_code| match geoip_sets.get(country_code) {
+                Some(net_list) => {
+                    cidr_list.extend(net_list.iter().map(ToString::to_string));
+                    false
+                }
+                None => true,
+            });
+    }
+
     fn add_private_cidr_list(&mut self) {
         let cidr_list = super::private_cidr_list();
         for s in cidr_list {
             self.cidr_list.insert(s.to_string());
         }
     }
-
-    pub fn match_ip(&self, address: &IpAddr, mmdb_data: &Reader<Vec<u8>>) -> bool {
-        for cidr in &self.cidr_list {
-            let cidr_net: IpNet = cidr.parse().unwrap();
-            if cidr_net.contains(address) {
-                return true;
-            }
-        }
-        let country_info = match mmdb_data.lookup::<Country>(*address) {
-            Ok(s) => s,
-            Err(e) => {
-                log::error!("lookup {address} in mmdb failed: {e}");
-                return false;
-            }
-        };
-        //获取国家代码
-        let mut opt_country_code = None;
-        if let Some(country) = country_info.country {
-            opt_country_code = country.iso_code;
-        }
-        if let Some(country_code) = opt_country_code {
-            for code in &self.country_code_list {
-                //不区分大小写
-                if country_code.eq_ignore_ascii_case(code) {
-                    return true;
-                }
-            }
-        }
-        false
-    }
 }
@@ -0,0 +1,10 @@
+use ipnet::IpNet;
+use std::net::IpAddr;
+
+///解析cidr, 单个ip地址转换为 /32 或者 /128
+pub fn parse_ip_net(s: &str) -> Option<IpNet> {
+    match s.parse::<IpNet>() {
+        Ok(s) => Some(s),
+        Err(_) => s.parse::<IpAddr>().ok().map(IpNet::from),
+    }
+}
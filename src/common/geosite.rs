@@ -1,11 +1,15 @@
+mod domain_matcher;
 mod domain_rule;
 mod domain_rule_attr;
 mod domain_rule_group;
 mod domain_rule_type;
+mod domain_suffix_trie;
 mod geosite_ns;
 
+pub use domain_matcher::DomainMatcher;
 pub use domain_rule::{DomainRule, ParseDomainRuleError};
 pub use domain_rule_attr::DomainRuleAttr;
 pub use domain_rule_group::DomainRuleGroup;
 pub use domain_rule_type::DomainRuleType;
+pub use domain_suffix_trie::DomainSuffixTrie;
 pub use geosite_ns::GeoSite;
@@ -0,0 +1,171 @@
+use super::{DomainRuleGroup, DomainSuffixTrie};
+use aho_corasick::AhoCorasick;
+use regex::{Regex, RegexSet};
+use std::collections::HashSet;
+
+///由 [`DomainRuleGroup`] 编译得到的域名匹配器, 匹配时不需要分配内存
+#[derive(Debug)]
+pub struct DomainMatcher {
+    ///`full:` 规则
+    full_set: HashSet<String>,
+    ///`domain:` 规则
+    suffix_trie: DomainSuffixTrie,
+    ///`keyword:` 规则
+    keyword_matcher: Option<AhoCorasick>,
+    ///`regexp:` 规则
+    regex_set: Option<RegexSet>,
+}
+
+impl TryFrom<DomainRuleGroup> for DomainMatcher {
+    type Error = regex::Error;
+
+    ///编译规则, 无效的正则表达式会被忽略
+    fn try_from(group: DomainRuleGroup) -> Result<Self, Self::Error> {
+        let mut suffix_trie = DomainSuffixTrie::default();
+        for domain in &group.domain_list {
+            suffix_trie.insert(domain);
+        }
+        let keyword_matcher = if group.keyword_list.is_empty() {
+            None
+        } else {
+            Some(AhoCorasick::new(&group.keyword_list))
+        };
+        let regexp_list: Vec<&String> = group
+            .regexp_list
+            .iter()
+            .filter(|s| match Regex::new(s) {
+                Ok(_) => true,
+                Err(e) => {
+                    log::error!("rexp {} error: {e}", s);
+                    false
+                }
+            })
+            .collect();
+        let regex_set = if regexp_list.is_empty() {
+            None
+        } else {
+            Some(RegexSet::new(regexp_list)?)
+        };
+        Ok(Self {
+            full_set: group.full_list,
+            suffix_trie,
+            keyword_matcher,
+            regex_set,
+        })
+    }
+}
+
+impl DomainMatcher {
+    pub fn match_domain(&self, domain: &str) -> bool {
+        //完全匹配
+        if self.full_set.contains(domain) {
+            return true;
+        }
+        //域名匹配
+        if self.suffix_trie.match_domain(domain) {
+            return true;
+        }
+        //关键词
+        if let Some(keyword_matcher) = &self.keyword_matcher {
+            if keyword_matcher.is_match(domain) {
+                return true;
+            }
+        }
+        //正则表达式
+        match &self.regex_set {
+            Some(regex_set) => regex_set.is_match(domain),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{DomainRule, DomainRuleType},
+        *,
+    };
+
+    fn rule_group() -> DomainRuleGroup {
+        let to_set = |list: &[&str]| list.iter().map(|s| s.to_string()).collect();
+        DomainRuleGroup {
+            domain_list: to_set(&["example.com", ".sub.example.org", "cn", "..dots.net"]),
+            keyword_list: to_set(&["google", "ads"]),
+            regexp_list: to_set(&[r"^api\d+\.test\.io$", "(invalid"]),
+            full_list: to_set(&["full.example.net"]),
+        }
+    }
+
+    #[test]
+    fn same_as_linear_match() {
+        let group = rule_group();
+        let matcher = DomainMatcher::try_from(group.clone()).unwrap();
+        //命令行查询使用的单条规则
+        let rule_list: Vec<DomainRule> = [
+            (DomainRuleType::Domain, &group.domain_list),
+            (DomainRuleType::Keyword, &group.keyword_list),
+            (DomainRuleType::Regexp, &group.regexp_list),
+            (DomainRuleType::Full, &group.full_list),
+        ]
+        .into_iter()
+        .flat_map(|(rule_type, list)| {
+            list.iter()
+                .map(move |s| DomainRule::new(rule_type.clone(), s.to_string()))
+        })
+        .collect();
+        let domain_list = [
+            "example.com",
+            "www.example.com",
+            "a.b.example.com",
+            "notexample.com",
+            "example.com.",
+            ".example.com",
+            "sub.example.org",
+            "a.sub.example.org",
+            "a.b.sub.example.org",
+            ".sub.example.org",
+            "xsub.example.org",
+            "example.org",
+            "cn",
+            "baidu.cn",
+            "cn.com",
+            "dots.net",
+            ".dots.net",
+            "a.dots.net",
+            "a..dots.net",
+            "www.google.com",
+            "googleapis.com",
+            "myads.org",
+            "api1.test.io",
+            "api.test.io",
+            "x.api1.test.io",
+            "full.example.net",
+            "www.full.example.net",
+            "example.net",
+            "",
+            ".",
+        ];
+        for domain in domain_list {
+            assert_eq!(
+                matcher.match_domain(domain),
+                group.match_domain(domain),
+                "{domain}"
+            );
+            assert_eq!(
+                matcher.match_domain(domain),
+                rule_list.iter().any(|rule| rule.match_domain(domain)),
+                "{domain}"
+            );
+        }
+    }
+
+    #[test]
+    fn leading_dot_only_match_subdomain() {
+        let mut group = DomainRuleGroup::default();
+        group.domain_list.insert(".example.com".to_string());
+        let matcher = DomainMatcher::try_from(group).unwrap();
+        assert!(!matcher.match_domain("example.com"));
+        assert!(matcher.match_domain("www.example.com"));
+        assert!(matcher.match_domain("a.b.example.com"));
+    }
+}
@@ -1,6 +1,7 @@
 use super::{domain_rule_attr::DomainRuleAttr, DomainRuleType};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashSet, str::FromStr};
+use std::{collections::HashSet, fmt, str::FromStr};
 use thiserror::Error;
 
 ///域名匹配规则
@@ -55,6 +56,33 @@ impl DomainRule {
         false
     }
 
+    ///单条规则是否匹配域名
+    ///
+    ///正则表达式每次都会重新编译, 只用于命令行查询, 路由使用 [`super::DomainMatcher`]
+    pub fn match_domain(&self, domain: &str) -> bool {
+        match self.rule_type {
+            DomainRuleType::Include => false,
+            //以 `.` 开头的规则只匹配子域名, 与 [`super::DomainMatcher`] 相同
+            DomainRuleType::Domain => match self.value.strip_prefix('.') {
+                Some(value) => domain
+                    .strip_suffix(value)
+                    .is_some_and(|prefix| prefix.ends_with('.')),
+                None => domain
+                    .strip_suffix(&self.value)
+                    .is_some_and(|prefix| prefix.is_empty() || prefix.ends_with('.')),
+            },
+            DomainRuleType::Keyword => domain.contains(&self.value),
+            DomainRuleType::Regexp => match Regex::new(&self.value) {
+                Ok(re) => re.is_match(domain),
+                Err(e) => {
+                    log::error!("rexp {} error: {e}", self.value);
+                    false
+                }
+            },
+            DomainRuleType::Full => domain == self.value,
+        }
+    }
+
     fn contains_attr(&self, name: &str) -> bool {
         if let Some(self_attrs) = &self.attrs {
             for node_attr in self_attrs {
@@ -81,6 +109,30 @@ impl DomainRule {
     }
 }
 
+///与源文件相同的格式, 例如 `domain:google.com @ads`
+impl fmt::Display for DomainRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.rule_type, self.value)?;
+        let mut attr_names: Vec<String> = self
+            .attrs
+            .iter()
+            .flatten()
+            .map(|attr| {
+                if attr.enabled {
+                    attr.name.to_string()
+                } else {
+                    format!("!{}", attr.name)
+                }
+            })
+            .collect();
+        attr_names.sort();
+        for attr_name in attr_names {
+            write!(f, " @{attr_name}")?;
+        }
+        Ok(())
+    }
+}
+
 ///解析域名匹配规则的错误
 #[derive(Error, Debug)]
 pub enum ParseDomainRuleError {
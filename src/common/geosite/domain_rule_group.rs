@@ -5,7 +5,7 @@ use regex::Regex;
 use super::{DomainRule, DomainRuleType};
 
 ///代表选择的一组匹配规则
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct DomainRuleGroup {
     pub domain_list: HashSet<String>,
     pub keyword_list: HashSet<String>,
@@ -47,6 +47,9 @@ impl DomainRuleGroup {
         }
     }
 
+    ///逐条匹配所有的规则
+    ///
+    ///路由使用编译之后的 [`super::DomainMatcher`] , 这里只用于对比测试
     pub fn match_domain(&self, domain: &str) -> bool {
         //完全匹配
         for s in &self.full_list {
@@ -1,7 +1,8 @@
 use serde_repr::{Deserialize_repr, Serialize_repr};
+use std::fmt;
 
 ///域名匹配规则类型
-#[derive(Debug, PartialEq, Eq, Clone, Serialize_repr, Deserialize_repr)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize_repr, Deserialize_repr)]
 #[repr(u8)]
 pub enum DomainRuleType {
     Include,
@@ -10,3 +11,16 @@ pub enum DomainRuleType {
     Regexp,
     Full,
 }
+
+impl fmt::Display for DomainRuleType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let type_name = match self {
+            Self::Include => "include",
+            Self::Domain => "domain",
+            Self::Keyword => "keyword",
+            Self::Regexp => "regexp",
+            Self::Full => "full",
+        };
+        write!(f, "{type_name}")
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+///按照域名的label倒序保存的后缀树, 用于 `domain:` 规则
+///
+///规则 `example.com` 匹配 `example.com` 和它的所有子域名,
+///以 `.` 开头的规则 `.example.com` 只匹配子域名
+#[derive(Debug)]
+pub struct DomainSuffixTrie {
+    ///节点列表, 第一个为根节点
+    nodes: Vec<TrieNode>,
+}
+
+#[derive(Debug, Default)]
+struct TrieNode {
+    ///label => 子节点的序号
+    children: HashMap<Box<str>, usize>,
+    ///从根节点到这里是一条完整的规则
+    terminal: bool,
+    ///从根节点到这里是一条以 `.` 开头的规则, 只匹配子域名
+    subdomain_only: bool,
+}
+
+impl Default for DomainSuffixTrie {
+    fn default() -> Self {
+        Self {
+            nodes: vec![TrieNode::default()],
+        }
+    }
+}
+
+impl DomainSuffixTrie {
+    ///添加一条规则, 以 `.` 开头的规则只匹配子域名
+    pub fn insert(&mut self, domain: &str) {
+        let (domain, subdomain_only) = match domain.strip_prefix('.') {
+            Some(s) => (s, true),
+            None => (domain, false),
+        };
+        if domain.is_empty() {
+            return;
+        }
+        let mut index = 0;
+        for label in domain.rsplit('.') {
+            index = match self.nodes[index].children.get(label) {
+                Some(s) => *s,
+                None => {
+                    let child_index = self.nodes.len();
+                    self.nodes.push(TrieNode::default());
+                    self.nodes[index]
+                        .children
+                        .insert(Box::from(label), child_index);
+                    child_index
+                }
+            };
+        }
+        if subdomain_only {
+            self.nodes[index].subdomain_only = true;
+        } else {
+            self.nodes[index].terminal = true;
+        }
+    }
+
+    ///是否没有任何规则
+    pub fn is_empty(&self) -> bool {
+        self.nodes.len() == 1
+    }
+
+    ///域名是否等于某条规则或者是它的子域名
+    pub fn match_domain(&self, domain: &str) -> bool {
+        let mut index = 0;
+        let mut labels = domain.rsplit('.').peekable();
+        while let Some(label) = labels.next() {
+            index = match self.nodes[index].children.get(label) {
+                Some(s) => *s,
+                None => return false,
+            };
+            let node = &self.nodes[index];
+            //只匹配子域名的规则后面至少还要有一个label
+            if node.terminal || (node.subdomain_only && labels.peek().is_some()) {
+                return true;
+            }
+        }
+        false
+    }
+}
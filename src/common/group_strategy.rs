@@ -0,0 +1,18 @@
+use serde::Deserialize;
+
+///outbound分组选择服务端的策略
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum GroupStrategy {
+    ///选择延迟最低的服务端
+    #[serde(rename(deserialize = "url_test"))]
+    UrlTest,
+    ///按顺序选择第一个可用的服务端
+    #[serde(rename(deserialize = "fallback"))]
+    Fallback,
+    ///轮流使用可用的服务端
+    #[serde(rename(deserialize = "round_robin"))]
+    RoundRobin,
+    ///根据目标地址的hash选择, 同一个目标总是使用相同的服务端
+    #[serde(rename(deserialize = "consistent_hash"))]
+    ConsistentHash,
+}
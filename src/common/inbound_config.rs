@@ -0,0 +1,38 @@
+use super::{InboundProtocol, LocalUser};
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Clone)]
+///本地代理的监听配置
+pub struct InboundConfig {
+    ///监听的tag, 用于路由规则匹配
+    pub tag: Option<String>,
+    ///本地监听地址
+    pub address: String,
+    ///本地监听端口
+    pub port: u16,
+    ///代理协议
+    pub protocol: InboundProtocol,
+    ///授权用户, 不设置时使用 `[client]` 中的 `local_users`
+    pub local_users: Option<Vec<LocalUser>>,
+    ///是否嗅探目标为ip地址的连接中的域名, 不设置时使用 `[client]` 中的 `sniffing`
+    pub sniffing: Option<bool>,
+    ///是否使用嗅探到的域名作为连接的目标地址, 不设置时使用 `[client]` 中的 `sniff_override_dest`
+    pub sniff_override_dest: Option<bool>,
+}
+
+impl InboundConfig {
+    ///授权用户列表
+    pub fn local_users(&self) -> &[LocalUser] {
+        self.local_users.as_deref().unwrap_or_default()
+    }
+
+    ///是否开启嗅探
+    pub fn sniffing(&self) -> bool {
+        self.sniffing.unwrap_or_default()
+    }
+
+    ///是否使用嗅探到的域名连接
+    pub fn sniff_override_dest(&self) -> bool {
+        self.sniff_override_dest.unwrap_or_default()
+    }
+}
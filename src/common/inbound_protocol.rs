@@ -0,0 +1,21 @@
+use serde::Deserialize;
+
+///本地代理的协议
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum InboundProtocol {
+    ///只接受socks5
+    #[serde(rename(deserialize = "socks5"))]
+    Socks5,
+    ///只接受http
+    #[serde(rename(deserialize = "http"))]
+    Http,
+    ///根据第一个字节判断是socks5还是http
+    #[serde(rename(deserialize = "mixed"))]
+    Mixed,
+    ///透明代理, 接受iptables REDIRECT转发的连接
+    #[serde(rename(deserialize = "redirect"))]
+    Redirect,
+    ///透明代理, 接受iptables TPROXY转发的连接
+    #[serde(rename(deserialize = "tproxy"))]
+    Tproxy,
+}
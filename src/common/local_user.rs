@@ -0,0 +1,19 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Clone)]
+///本地代理的授权用户
+pub struct LocalUser {
+    ///用户名
+    pub user: String,
+    ///密码
+    pub password: String,
+}
+
+impl LocalUser {
+    ///在用户列表中检查用户名和密码
+    pub fn check(local_users: &[Self], user: &str, password: &str) -> bool {
+        local_users
+            .iter()
+            .any(|item| item.user == user && item.password == password)
+    }
+}
@@ -1,14 +1,23 @@
 ///客户端产生的消息子类
 pub mod client;
 mod client_message;
+mod datagram;
+mod frame;
 ///服务端产生的消息子类
 pub mod server;
 mod server_message;
+mod window_update;
 
+use super::socks5::ParseConnDestError;
 pub use client_message::ClientMessage;
+pub use datagram::Datagram;
 pub use server_message::ServerMessage;
 use std::str::Utf8Error;
 use thiserror::Error;
+pub use window_update::WindowUpdate;
+
+///每个stream的初始窗口大小, 发送方在收到窗口更新之前最多发送这么多数据
+pub const INITIAL_WINDOW_SIZE: u32 = 256 * 1024;
 
 ///解析消息的错误定义
 #[derive(Error, Debug)]
@@ -22,6 +31,10 @@ pub enum ParseMessageError {
     InvalidResponseStatus(u8),
     #[error("invalid conn status {0}")]
     InvalidConnStatus(u8),
+    #[error("invalid bind status {0}")]
+    InvalidBindStatus(u8),
     #[error("invalid message type {0}")]
     InvalidMsgType(u8),
+    #[error("invalid address, {0}")]
+    InvalidAddr(#[from] ParseConnDestError),
 }
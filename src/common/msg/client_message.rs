@@ -1,30 +1,49 @@
 pub use super::client::{Connect, ProxyRequest};
-use super::ParseMessageError;
-use bytes::{Buf, Bytes};
+use super::frame::{decode_frame, encode_frame};
+use super::{Datagram, ParseMessageError, WindowUpdate};
+use bytes::Bytes;
 //消息类型定义
 const MESSAGE_TYPE_CONN: u8 = 0;
 const MESSAGE_TYPE_DIS_CONN: u8 = 1;
 const MESSAGE_TYPE_REQUEST: u8 = 2;
+const MESSAGE_TYPE_WINDOW_UPDATE: u8 = 3;
+const MESSAGE_TYPE_UDP_ASSOCIATE: u8 = 4;
+const MESSAGE_TYPE_DATAGRAM: u8 = 5;
+const MESSAGE_TYPE_BIND: u8 = 6;
 
 ///客户端消息
+///
+///每条消息的第一个字段为 stream id, 同一个websocket连接上可以同时承载多个stream
 #[derive(Debug)]
 pub enum ClientMessage {
     ///连接远端
-    Conn(Connect),
+    Conn(u32, Connect),
     ///断开远端
-    DisConn,
+    DisConn(u32),
     ///写入请求
-    Request(ProxyRequest),
+    Request(u32, ProxyRequest),
+    ///已经处理了服务端发来的数据, 服务端可以继续发送
+    WindowUpdate(u32, WindowUpdate),
+    ///建立udp转发
+    UdpAssociate(u32),
+    ///发送udp数据报
+    Datagram(u32, Datagram),
+    ///在服务端监听端口, 等待远端连接, `Connect` 中为预期的远端地址
+    Bind(u32, Connect),
 }
 
-impl From<Connect> for ClientMessage {
-    fn from(item: Connect) -> Self {
-        Self::Conn(item)
-    }
-}
-impl From<ProxyRequest> for ClientMessage {
-    fn from(item: ProxyRequest) -> Self {
-        Self::Request(item)
+impl ClientMessage {
+    ///消息所属的stream id
+    pub fn stream_id(&self) -> u32 {
+        match self {
+            Self::Conn(stream_id, _) => *stream_id,
+            Self::DisConn(stream_id) => *stream_id,
+            Self::Request(stream_id, _) => *stream_id,
+            Self::WindowUpdate(stream_id, _) => *stream_id,
+            Self::UdpAssociate(stream_id) => *stream_id,
+            Self::Datagram(stream_id, _) => *stream_id,
+            Self::Bind(stream_id, _) => *stream_id,
+        }
     }
 }
 
@@ -32,16 +51,26 @@ impl From<ClientMessage> for Bytes {
     ///序列化
     fn from(item: ClientMessage) -> Self {
         match item {
-            ClientMessage::Conn(data) => {
-                let data_bytes: Bytes = data.into();
-                let mut buff = Bytes::from_static(&[MESSAGE_TYPE_CONN]).chain(data_bytes);
-                buff.copy_to_bytes(buff.remaining())
+            ClientMessage::Conn(stream_id, data) => {
+                encode_frame(MESSAGE_TYPE_CONN, stream_id, data.into())
+            }
+            ClientMessage::DisConn(stream_id) => {
+                encode_frame(MESSAGE_TYPE_DIS_CONN, stream_id, Bytes::new())
+            }
+            ClientMessage::Request(stream_id, data) => {
+                encode_frame(MESSAGE_TYPE_REQUEST, stream_id, data.into())
+            }
+            ClientMessage::WindowUpdate(stream_id, data) => {
+                encode_frame(MESSAGE_TYPE_WINDOW_UPDATE, stream_id, data.into())
+            }
+            ClientMessage::UdpAssociate(stream_id) => {
+                encode_frame(MESSAGE_TYPE_UDP_ASSOCIATE, stream_id, Bytes::new())
             }
-            ClientMessage::DisConn => Bytes::from_static(&[MESSAGE_TYPE_DIS_CONN]),
-            ClientMessage::Request(data) => {
-                let data_bytes: Bytes = data.into();
-                let mut buff = Bytes::from_static(&[MESSAGE_TYPE_REQUEST]).chain(data_bytes);
-                buff.copy_to_bytes(buff.remaining())
+            ClientMessage::Datagram(stream_id, data) => {
+                encode_frame(MESSAGE_TYPE_DATAGRAM, stream_id, data.into())
+            }
+            ClientMessage::Bind(stream_id, data) => {
+                encode_frame(MESSAGE_TYPE_BIND, stream_id, data.into())
             }
         }
     }
@@ -52,23 +81,112 @@ impl TryFrom<Bytes> for ClientMessage {
 
     ///解析
     fn try_from(value: Bytes) -> Result<Self, Self::Error> {
-        if value.is_empty() {
-            return Err(ParseMessageError::Incomplete);
-        }
-        let msg_type = *value.first().unwrap();
+        let (msg_type, stream_id, data_bytes) = decode_frame(value)?;
         let message = if msg_type == MESSAGE_TYPE_CONN {
-            let data_bytes = value.slice(1..);
             let sub_message = data_bytes.try_into()?;
-            Self::Conn(sub_message)
+            Self::Conn(stream_id, sub_message)
         } else if msg_type == MESSAGE_TYPE_DIS_CONN {
-            Self::DisConn
+            Self::DisConn(stream_id)
         } else if msg_type == MESSAGE_TYPE_REQUEST {
-            let data_bytes = value.slice(1..);
             let sub_message = data_bytes.try_into()?;
-            Self::Request(sub_message)
+            Self::Request(stream_id, sub_message)
+        } else if msg_type == MESSAGE_TYPE_WINDOW_UPDATE {
+            let sub_message = data_bytes.try_into()?;
+            Self::WindowUpdate(stream_id, sub_message)
+        } else if msg_type == MESSAGE_TYPE_UDP_ASSOCIATE {
+            Self::UdpAssociate(stream_id)
+        } else if msg_type == MESSAGE_TYPE_DATAGRAM {
+            let sub_message = data_bytes.try_into()?;
+            Self::Datagram(stream_id, sub_message)
+        } else if msg_type == MESSAGE_TYPE_BIND {
+            let sub_message = data_bytes.try_into()?;
+            Self::Bind(stream_id, sub_message)
         } else {
             return Err(ParseMessageError::InvalidMsgType(msg_type));
         };
         Ok(message)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::socks5::{ConnDest, ConnDestAddr};
+
+    ///每种消息各一条, 调用两次得到相同的消息用于比较
+    fn all_messages() -> Vec<ClientMessage> {
+        vec![
+            ClientMessage::Conn(1, Connect("example.com:443".to_string())),
+            ClientMessage::DisConn(2),
+            ClientMessage::Request(3, ProxyRequest(Bytes::from_static(b"GET / HTTP/1.1\r\n"))),
+            ClientMessage::WindowUpdate(4, WindowUpdate(128 * 1024)),
+            ClientMessage::UdpAssociate(5),
+            ClientMessage::Datagram(
+                6,
+                Datagram {
+                    dest: ConnDest {
+                        addr: ConnDestAddr::Domain("example.com".to_string()),
+                        port: 53,
+                    },
+                    data: Bytes::from_static(b"\x12\x34"),
+                },
+            ),
+            ClientMessage::Datagram(
+                7,
+                Datagram {
+                    dest: "[::1]:5353".parse::<std::net::SocketAddr>().unwrap().into(),
+                    data: Bytes::new(),
+                },
+            ),
+            ClientMessage::Bind(u32::MAX, Connect("1.2.3.4:0".to_string())),
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        for (message, expected) in all_messages().into_iter().zip(all_messages()) {
+            let data = Bytes::from(message);
+            let decoded = ClientMessage::try_from(data).unwrap();
+            assert_eq!(decoded.stream_id(), expected.stream_id());
+            assert_eq!(format!("{decoded:?}"), format!("{expected:?}"));
+        }
+    }
+
+    #[test]
+    fn truncated_message() {
+        for message in all_messages() {
+            let data = Bytes::from(message);
+            //截断的消息可能解析失败, 但是不能panic
+            for len in 0..data.len() {
+                let _ = ClientMessage::try_from(data.slice(..len));
+            }
+        }
+        for data in [
+            //短于消息头
+            &b"\x00\x00\x00"[..],
+            //conn和request没有内容
+            b"\x00\x00\x00\x00\x01",
+            b"\x02\x00\x00\x00\x01",
+            //窗口更新不足4字节
+            b"\x03\x00\x00\x00\x01\x00\x01",
+        ] {
+            assert!(matches!(
+                ClientMessage::try_from(Bytes::from_static(data)),
+                Err(ParseMessageError::Incomplete)
+            ));
+        }
+        //udp地址不完整
+        assert!(matches!(
+            ClientMessage::try_from(Bytes::from_static(b"\x05\x00\x00\x00\x01\x01\x7f\x00")),
+            Err(ParseMessageError::InvalidAddr(_))
+        ));
+    }
+
+    #[test]
+    fn unknown_type() {
+        assert!(matches!(
+            ClientMessage::try_from(Bytes::from_static(b"\xff\x00\x00\x00\x01abc")),
+            Err(ParseMessageError::InvalidMsgType(0xff))
+        ));
+    }
+}
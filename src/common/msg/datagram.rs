@@ -0,0 +1,34 @@
+use super::ParseMessageError;
+use crate::common::socks5::ConnDest;
+use bytes::{BufMut, Bytes, BytesMut};
+
+///udp数据报
+///
+///客户端发出时 `dest` 为目标地址, 服务端发出时 `dest` 为数据报的来源地址
+#[derive(Debug)]
+pub struct Datagram {
+    pub dest: ConnDest,
+    pub data: Bytes,
+}
+
+impl From<Datagram> for Bytes {
+    ///序列化, 地址部分与socks5的地址格式相同
+    fn from(item: Datagram) -> Self {
+        let dest_data = item.dest.to_raw_data();
+        let mut buff = BytesMut::with_capacity(dest_data.len() + item.data.len());
+        buff.put_slice(&dest_data);
+        buff.put_slice(&item.data);
+        buff.into()
+    }
+}
+
+impl TryFrom<Bytes> for Datagram {
+    type Error = ParseMessageError;
+
+    ///解析
+    fn try_from(value: Bytes) -> Result<Self, Self::Error> {
+        let dest = ConnDest::try_from_bytes(&value)?;
+        let data = value.slice(dest.raw_data_len()..);
+        Ok(Self { dest, data })
+    }
+}
@@ -0,0 +1,63 @@
+use super::ParseMessageError;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+///消息头长度: 消息类型(1字节) + stream id(4字节)
+const HEADER_SIZE: usize = 5;
+
+///序列化一帧消息
+///
+/// ```text
+/// +----------+-----------+----------+
+/// | MSG_TYPE | STREAM_ID | PAYLOAD  |
+/// +----------+-----------+----------+
+/// |    1     |     4     | Variable |
+/// +----------+-----------+----------+
+/// ```
+pub fn encode_frame(msg_type: u8, stream_id: u32, payload: Bytes) -> Bytes {
+    let mut buff = BytesMut::with_capacity(HEADER_SIZE + payload.len());
+    buff.put_u8(msg_type);
+    buff.put_u32(stream_id);
+    buff.put_slice(&payload);
+    buff.into()
+}
+
+///解析一帧消息,得到消息类型、stream id 和剩余的数据
+pub fn decode_frame(mut value: Bytes) -> Result<(u8, u32, Bytes), ParseMessageError> {
+    if value.len() < HEADER_SIZE {
+        return Err(ParseMessageError::Incomplete);
+    }
+    let msg_type = value.get_u8();
+    let stream_id = value.get_u32();
+    Ok((msg_type, stream_id, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_and_decode() {
+        let frame = encode_frame(7, 0x0102_0304, Bytes::from_static(b"abc"));
+        assert_eq!(&frame[..], b"\x07\x01\x02\x03\x04abc");
+        let (msg_type, stream_id, payload) = decode_frame(frame).unwrap();
+        assert_eq!(
+            (msg_type, stream_id, &payload[..]),
+            (7, 0x0102_0304, &b"abc"[..])
+        );
+        //没有payload
+        let frame = encode_frame(1, u32::MAX, Bytes::new());
+        let (msg_type, stream_id, payload) = decode_frame(frame).unwrap();
+        assert_eq!((msg_type, stream_id, payload.len()), (1, u32::MAX, 0));
+    }
+
+    #[test]
+    fn short_header() {
+        let frame = encode_frame(1, 2, Bytes::new());
+        for len in 0..HEADER_SIZE {
+            assert!(matches!(
+                decode_frame(frame.slice(..len)),
+                Err(ParseMessageError::Incomplete)
+            ));
+        }
+    }
+}
@@ -1,7 +1,9 @@
+mod bind_result;
 mod connect_result;
 mod proxy_response_result;
 mod request_fail;
 
+pub use bind_result::BindResult;
 pub use connect_result::ConnectResult;
 pub use proxy_response_result::ProxyResponseResult;
 pub use request_fail::RequestFail;
@@ -0,0 +1,63 @@
+use super::super::ParseMessageError;
+use crate::common::socks5::ConnDest;
+use bytes::{BufMut, Bytes, BytesMut};
+//状态定义
+const STATUS_LISTENING: u8 = 0;
+const STATUS_ACCEPTED: u8 = 1;
+const STATUS_ERR: u8 = 2;
+const STATUS_TIMEOUT: u8 = 3;
+
+///bind命令的结果, 服务端会依次发送 `Listening` 和 `Accepted`
+#[derive(Debug)]
+pub enum BindResult {
+    ///已经开始监听, 附带监听的地址
+    Listening(ConnDest),
+    ///收到了远端的连接, 附带远端的地址
+    Accepted(ConnDest),
+    ///io出错
+    Err(String),
+    ///等待远端连接超时
+    Timeout,
+}
+
+impl From<BindResult> for Bytes {
+    ///序列化
+    fn from(item: BindResult) -> Self {
+        let (bind_status, payload) = match item {
+            BindResult::Listening(addr) => (STATUS_LISTENING, addr.to_raw_data()),
+            BindResult::Accepted(addr) => (STATUS_ACCEPTED, addr.to_raw_data()),
+            BindResult::Err(message_str) => (STATUS_ERR, Bytes::from(message_str)),
+            BindResult::Timeout => (STATUS_TIMEOUT, Bytes::new()),
+        };
+        let mut buff = BytesMut::with_capacity(1 + payload.len());
+        buff.put_u8(bind_status);
+        buff.put_slice(&payload);
+        buff.into()
+    }
+}
+
+impl TryFrom<Bytes> for BindResult {
+    type Error = ParseMessageError;
+
+    ///解析
+    fn try_from(value: Bytes) -> Result<Self, Self::Error> {
+        if value.is_empty() {
+            return Err(ParseMessageError::Incomplete);
+        }
+        let bind_status = *value.first().unwrap();
+        let payload = value.slice(1..);
+        let item = if bind_status == STATUS_LISTENING {
+            Self::Listening(ConnDest::try_from_bytes(&payload)?)
+        } else if bind_status == STATUS_ACCEPTED {
+            Self::Accepted(ConnDest::try_from_bytes(&payload)?)
+        } else if bind_status == STATUS_ERR {
+            let message_str = std::str::from_utf8(&payload)?;
+            Self::Err(message_str.to_string())
+        } else if bind_status == STATUS_TIMEOUT {
+            Self::Timeout
+        } else {
+            return Err(ParseMessageError::InvalidBindStatus(bind_status));
+        };
+        Ok(item)
+    }
+}
@@ -1,41 +1,69 @@
-pub use super::server::{ConnectResult, ProxyResponseResult, RequestFail};
-use super::ParseMessageError;
-use bytes::{Buf, Bytes};
+use super::frame::{decode_frame, encode_frame};
+pub use super::server::{BindResult, ConnectResult, ProxyResponseResult, RequestFail};
+use super::{Datagram, ParseMessageError, WindowUpdate};
+use bytes::Bytes;
 //消息类型定义
 const MESSAGE_TYPE_CONN_RESULT: u8 = 0;
 const MESSAGE_TYPE_RESPONSE_RESULT: u8 = 1;
 const MESSAGE_TYPE_REQUEST_FAIL: u8 = 2;
+const MESSAGE_TYPE_WINDOW_UPDATE: u8 = 3;
+const MESSAGE_TYPE_DATAGRAM: u8 = 4;
+const MESSAGE_TYPE_BIND_RESULT: u8 = 5;
 
 ///服务端消息
+///
+///每条消息的第一个字段为 stream id, 与客户端消息中的 stream id 对应
 #[derive(Debug)]
 pub enum ServerMessage {
     ///连接远端的结果
-    ConnResult(ConnectResult),
+    ConnResult(u32, ConnectResult),
     ///响应结果
-    ResponseResult(ProxyResponseResult),
+    ResponseResult(u32, ProxyResponseResult),
     ///发送请求失败
-    RequestFail(RequestFail),
+    RequestFail(u32, RequestFail),
+    ///已经把客户端发来的数据写入远端, 客户端可以继续发送
+    WindowUpdate(u32, WindowUpdate),
+    ///收到的udp数据报
+    Datagram(u32, Datagram),
+    ///bind命令的结果
+    BindResult(u32, BindResult),
+}
+
+impl ServerMessage {
+    ///消息所属的stream id
+    pub fn stream_id(&self) -> u32 {
+        match self {
+            Self::ConnResult(stream_id, _) => *stream_id,
+            Self::ResponseResult(stream_id, _) => *stream_id,
+            Self::RequestFail(stream_id, _) => *stream_id,
+            Self::WindowUpdate(stream_id, _) => *stream_id,
+            Self::Datagram(stream_id, _) => *stream_id,
+            Self::BindResult(stream_id, _) => *stream_id,
+        }
+    }
 }
 
 impl From<ServerMessage> for Bytes {
     ///序列化
     fn from(item: ServerMessage) -> Self {
         match item {
-            ServerMessage::ConnResult(data) => {
-                let data_bytes: Bytes = data.into();
-                let mut buff = Bytes::from_static(&[MESSAGE_TYPE_CONN_RESULT]).chain(data_bytes);
-                buff.copy_to_bytes(buff.remaining())
+            ServerMessage::ConnResult(stream_id, data) => {
+                encode_frame(MESSAGE_TYPE_CONN_RESULT, stream_id, data.into())
+            }
+            ServerMessage::ResponseResult(stream_id, data) => {
+                encode_frame(MESSAGE_TYPE_RESPONSE_RESULT, stream_id, data.into())
+            }
+            ServerMessage::RequestFail(stream_id, data) => {
+                encode_frame(MESSAGE_TYPE_REQUEST_FAIL, stream_id, data.into())
             }
-            ServerMessage::ResponseResult(data) => {
-                let data_bytes: Bytes = data.into();
-                let mut buff =
-                    Bytes::from_static(&[MESSAGE_TYPE_RESPONSE_RESULT]).chain(data_bytes);
-                buff.copy_to_bytes(buff.remaining())
+            ServerMessage::WindowUpdate(stream_id, data) => {
+                encode_frame(MESSAGE_TYPE_WINDOW_UPDATE, stream_id, data.into())
             }
-            ServerMessage::RequestFail(data) => {
-                let data_bytes: Bytes = data.into();
-                let mut buff = Bytes::from_static(&[MESSAGE_TYPE_REQUEST_FAIL]).chain(data_bytes);
-                buff.copy_to_bytes(buff.remaining())
+            ServerMessage::Datagram(stream_id, data) => {
+                encode_frame(MESSAGE_TYPE_DATAGRAM, stream_id, data.into())
+            }
+            ServerMessage::BindResult(stream_id, data) => {
+                encode_frame(MESSAGE_TYPE_BIND_RESULT, stream_id, data.into())
             }
         }
     }
@@ -46,22 +74,25 @@ impl TryFrom<Bytes> for ServerMessage {
 
     ///解析
     fn try_from(value: Bytes) -> Result<Self, Self::Error> {
-        if value.is_empty() {
-            return Err(ParseMessageError::Incomplete);
-        }
-        let msg_type = *value.first().unwrap();
+        let (msg_type, stream_id, data_bytes) = decode_frame(value)?;
         let message = if msg_type == MESSAGE_TYPE_CONN_RESULT {
-            let data_bytes = value.slice(1..);
             let sub_message = data_bytes.try_into()?;
-            Self::ConnResult(sub_message)
+            Self::ConnResult(stream_id, sub_message)
         } else if msg_type == MESSAGE_TYPE_RESPONSE_RESULT {
-            let data_bytes = value.slice(1..);
             let sub_message = data_bytes.try_into()?;
-            Self::ResponseResult(sub_message)
+            Self::ResponseResult(stream_id, sub_message)
         } else if msg_type == MESSAGE_TYPE_REQUEST_FAIL {
-            let data_bytes = value.slice(1..);
             let sub_message = data_bytes.try_into()?;
-            Self::RequestFail(sub_message)
+            Self::RequestFail(stream_id, sub_message)
+        } else if msg_type == MESSAGE_TYPE_WINDOW_UPDATE {
+            let sub_message = data_bytes.try_into()?;
+            Self::WindowUpdate(stream_id, sub_message)
+        } else if msg_type == MESSAGE_TYPE_DATAGRAM {
+            let sub_message = data_bytes.try_into()?;
+            Self::Datagram(stream_id, sub_message)
+        } else if msg_type == MESSAGE_TYPE_BIND_RESULT {
+            let sub_message = data_bytes.try_into()?;
+            Self::BindResult(stream_id, sub_message)
         } else {
             return Err(ParseMessageError::InvalidMsgType(msg_type));
         };
@@ -69,18 +100,102 @@ impl TryFrom<Bytes> for ServerMessage {
     }
 }
 
-impl From<ConnectResult> for ServerMessage {
-    fn from(item: ConnectResult) -> Self {
-        Self::ConnResult(item)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::socks5::{ConnDest, ConnDestAddr};
+    use std::net::SocketAddr;
+
+    ///每种消息各一条, 调用两次得到相同的消息用于比较
+    fn all_messages() -> Vec<ServerMessage> {
+        let addr: SocketAddr = "127.0.0.1:1080".parse().unwrap();
+        vec![
+            ServerMessage::ConnResult(1, ConnectResult::Ok),
+            ServerMessage::ConnResult(1, ConnectResult::Err("refused".to_string())),
+            ServerMessage::ConnResult(1, ConnectResult::Timeout),
+            ServerMessage::ResponseResult(2, ProxyResponseResult::Ok(Bytes::from_static(b"data"))),
+            ServerMessage::ResponseResult(2, ProxyResponseResult::Err("reset".to_string())),
+            ServerMessage::ResponseResult(2, ProxyResponseResult::Closed),
+            ServerMessage::RequestFail(3, RequestFail("broken pipe".to_string())),
+            ServerMessage::WindowUpdate(4, WindowUpdate(u32::MAX)),
+            ServerMessage::Datagram(
+                5,
+                Datagram {
+                    dest: ConnDest {
+                        addr: ConnDestAddr::Domain("example.com".to_string()),
+                        port: 53,
+                    },
+                    data: Bytes::from_static(b"\x12\x34"),
+                },
+            ),
+            ServerMessage::BindResult(6, BindResult::Listening(addr.into())),
+            ServerMessage::BindResult(
+                6,
+                BindResult::Accepted("[::1]:2000".parse::<SocketAddr>().unwrap().into()),
+            ),
+            ServerMessage::BindResult(6, BindResult::Err("addr in use".to_string())),
+            ServerMessage::BindResult(u32::MAX, BindResult::Timeout),
+        ]
     }
-}
-impl From<ProxyResponseResult> for ServerMessage {
-    fn from(item: ProxyResponseResult) -> Self {
-        Self::ResponseResult(item)
+
+    #[test]
+    fn round_trip() {
+        for (message, expected) in all_messages().into_iter().zip(all_messages()) {
+            let data = Bytes::from(message);
+            let decoded = ServerMessage::try_from(data).unwrap();
+            assert_eq!(decoded.stream_id(), expected.stream_id());
+            assert_eq!(format!("{decoded:?}"), format!("{expected:?}"));
+        }
     }
-}
-impl From<RequestFail> for ServerMessage {
-    fn from(item: RequestFail) -> Self {
-        Self::RequestFail(item)
+
+    #[test]
+    fn truncated_message() {
+        for message in all_messages() {
+            let data = Bytes::from(message);
+            //截断的消息可能解析失败, 但是不能panic
+            for len in 0..data.len() {
+                let _ = ServerMessage::try_from(data.slice(..len));
+            }
+        }
+        for data in [
+            //短于消息头
+            &b"\x00\x00"[..],
+            //连接结果、响应结果、bind结果没有状态
+            b"\x00\x00\x00\x00\x01",
+            b"\x01\x00\x00\x00\x01",
+            b"\x05\x00\x00\x00\x01",
+            //窗口更新不足4字节
+            b"\x03\x00\x00\x00\x01\x00",
+        ] {
+            assert!(matches!(
+                ServerMessage::try_from(Bytes::from_static(data)),
+                Err(ParseMessageError::Incomplete)
+            ));
+        }
+        //bind监听地址不完整
+        assert!(matches!(
+            ServerMessage::try_from(Bytes::from_static(b"\x05\x00\x00\x00\x01\x00\x01\x7f")),
+            Err(ParseMessageError::InvalidAddr(_))
+        ));
+    }
+
+    #[test]
+    fn unknown_type_or_status() {
+        assert!(matches!(
+            ServerMessage::try_from(Bytes::from_static(b"\x10\x00\x00\x00\x01")),
+            Err(ParseMessageError::InvalidMsgType(0x10))
+        ));
+        assert!(matches!(
+            ServerMessage::try_from(Bytes::from_static(b"\x00\x00\x00\x00\x01\x09")),
+            Err(ParseMessageError::InvalidConnStatus(9))
+        ));
+        assert!(matches!(
+            ServerMessage::try_from(Bytes::from_static(b"\x01\x00\x00\x00\x01\x09")),
+            Err(ParseMessageError::InvalidResponseStatus(9))
+        ));
+        assert!(matches!(
+            ServerMessage::try_from(Bytes::from_static(b"\x05\x00\x00\x00\x01\x09")),
+            Err(ParseMessageError::InvalidBindStatus(9))
+        ));
     }
 }
@@ -0,0 +1,27 @@
+use super::ParseMessageError;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+///窗口更新, 通知对方可以继续发送的字节数
+#[derive(Debug)]
+pub struct WindowUpdate(pub u32);
+
+impl From<WindowUpdate> for Bytes {
+    ///序列化
+    fn from(item: WindowUpdate) -> Self {
+        let mut buff = BytesMut::with_capacity(4);
+        buff.put_u32(item.0);
+        buff.into()
+    }
+}
+
+impl TryFrom<Bytes> for WindowUpdate {
+    type Error = ParseMessageError;
+
+    ///解析
+    fn try_from(mut value: Bytes) -> Result<Self, Self::Error> {
+        if value.len() < 4 {
+            return Err(ParseMessageError::Incomplete);
+        }
+        Ok(Self(value.get_u32()))
+    }
+}
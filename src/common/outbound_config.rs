@@ -0,0 +1,25 @@
+use super::{AuthUser, UpstreamProxyConfig};
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Clone)]
+///一个上游服务端的配置
+pub struct OutboundConfig {
+    ///路由规则中使用 `proxy:tag` 选择这个服务端
+    pub tag: String,
+    ///服务端url
+    pub server_url: String,
+    ///授权用户配置
+    pub auth_user: AuthUser,
+    ///指定ip来建立tcp连接
+    pub server_ip: Option<String>,
+    ///自定义ssl ca文件路径
+    pub ssl_ca_path: Option<String>,
+    ///额外的http请求头, 不设置时使用 `[client]` 中的 `extra_http_headers`
+    pub extra_http_headers: Option<Vec<[String; 2]>>,
+    ///连接池最多空闲连接个数, 不设置时使用 `[client]` 中的 `max_idle_conns`
+    pub max_idle_conns: Option<u32>,
+    ///每个连接最多同时承载的stream个数, 不设置时使用 `[client]` 中的 `max_streams_per_conn`
+    pub max_streams_per_conn: Option<u32>,
+    ///前置代理, 不设置时使用 `[client]` 中的 `upstream_proxy`
+    pub upstream_proxy: Option<UpstreamProxyConfig>,
+}
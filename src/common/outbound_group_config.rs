@@ -0,0 +1,17 @@
+use super::GroupStrategy;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Clone)]
+///一组服务端的配置, 按照策略从中选择一个服务端
+pub struct OutboundGroupConfig {
+    ///路由规则中使用 `proxy:tag` 选择这个分组
+    pub tag: String,
+    ///选择策略
+    pub strategy: GroupStrategy,
+    ///分组中的outbound的tag, `default` 表示 `[client]` 中的服务端
+    pub outbounds: Vec<String>,
+    ///健康检查的间隔秒数, 默认为60
+    pub check_interval: Option<u64>,
+    ///健康检查时通过服务端连接的目标地址(host:port), 不设置时只检查与服务端建立连接的延迟
+    pub probe_dest: Option<String>,
+}
@@ -0,0 +1,16 @@
+use super::RouteConfigCom;
+use futures_util::future::BoxFuture;
+use std::{error::Error, fmt::Debug, net::IpAddr};
+
+///解析域名出错
+pub type ResolveDomainError = Box<dyn Error + Send + Sync>;
+
+///`domain_strategy` 使用的域名解析
+pub trait ResolveDomain: Debug + Send + Sync {
+    ///解析域名得到所有的ip地址, `route_config` 为当前连接使用的路由配置
+    fn resolve_domain<'a>(
+        &'a self,
+        domain: &'a str,
+        route_config: &'a RouteConfigCom,
+    ) -> BoxFuture<'a, Result<Vec<IpAddr>, ResolveDomainError>>;
+}
@@ -0,0 +1,114 @@
+use super::{RouteConfigRule, RouteContext, RouteNetwork};
+use ipnet::IpNet;
+use std::net::IpAddr;
+use thiserror::Error;
+
+///解析路由规则的附加条件出错
+#[derive(Error, Debug)]
+pub enum ParseRouteConditionError {
+    #[error("invalid port range {0}")]
+    Port(String),
+    #[error("invalid source address {0}")]
+    Source(String),
+}
+
+///路由规则中除了域名和ip之外的附加条件, 所有条件都满足时规则才匹配
+///
+///没有设置的条件不参与匹配
+#[derive(Debug, Default)]
+pub struct RouteConditions {
+    ///目标端口范围
+    port_ranges: Vec<(u16, u16)>,
+    ///网络类型
+    network: Option<RouteNetwork>,
+    ///本地监听的tag
+    inbound_tags: Vec<String>,
+    ///客户端地址段
+    source_nets: Vec<IpNet>,
+}
+
+impl RouteConditions {
+    ///检查目标端口和连接信息是否满足所有的条件, 缺少条件需要的信息时不匹配
+    pub fn matches(&self, port: Option<u16>, context: &RouteContext) -> bool {
+        if !self.port_ranges.is_empty() {
+            match port {
+                Some(port) => {
+                    if !self
+                        .port_ranges
+                        .iter()
+                        .any(|(start, end)| (*start..=*end).contains(&port))
+                    {
+                        return false;
+                    }
+                }
+                None => return false,
+            }
+        }
+        if self.network.is_some() && self.network != context.network {
+            return false;
+        }
+        if !self.inbound_tags.is_empty() {
+            match &context.inbound_tag {
+                Some(tag) if self.inbound_tags.contains(tag) => (),
+                _ => return false,
+            }
+        }
+        if !self.source_nets.is_empty() {
+            match &context.source {
+                Some(source) if self.source_nets.iter().any(|net| net.contains(source)) => (),
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    ///解析端口范围, 例如 `22` 或者 `6881-6889`
+    fn parse_port_range(value: &str) -> Result<(u16, u16), ParseRouteConditionError> {
+        let parse_port = |s: &str| {
+            s.trim()
+                .parse::<u16>()
+                .map_err(|_| ParseRouteConditionError::Port(value.to_string()))
+        };
+        let (start, end) = match value.split_once('-') {
+            Some((start, end)) => (parse_port(start)?, parse_port(end)?),
+            None => {
+                let port = parse_port(value)?;
+                (port, port)
+            }
+        };
+        if start > end {
+            return Err(ParseRouteConditionError::Port(value.to_string()));
+        }
+        Ok((start, end))
+    }
+
+    ///解析客户端地址段, 单个ip地址作为只包含自己的地址段
+    fn parse_source(value: &str) -> Result<IpNet, ParseRouteConditionError> {
+        if let Ok(ip_net) = value.parse::<IpNet>() {
+            return Ok(ip_net.trunc());
+        }
+        value
+            .parse::<IpAddr>()
+            .map(IpNet::from)
+            .map_err(|_| ParseRouteConditionError::Source(value.to_string()))
+    }
+}
+
+impl TryFrom<&RouteConfigRule> for RouteConditions {
+    type Error = ParseRouteConditionError;
+
+    fn try_from(rule: &RouteConfigRule) -> Result<Self, Self::Error> {
+        let mut conditions = Self {
+            network: rule.network,
+            inbound_tags: rule.inbound.clone().unwrap_or_default(),
+            ..Default::default()
+        };
+        for value in rule.port.iter().flatten() {
+            conditions.port_ranges.push(Self::parse_port_range(value)?);
+        }
+        for value in rule.source.iter().flatten() {
+            conditions.source_nets.push(Self::parse_source(value)?);
+        }
+        Ok(conditions)
+    }
+}
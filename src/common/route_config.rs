@@ -1,8 +1,12 @@
+use super::{DomainStrategy, RouteNetwork, RuleSetConfig};
 use serde::Deserialize;
+use std::fmt;
 
 ///路由配置
 #[derive(Deserialize, Debug)]
 pub struct RouteConfig {
+    ///优先匹配的规则列表, 只使用端口、网络类型、本地监听和客户端地址匹配
+    pub rules: Option<Vec<RouteConfigRule>>,
     ///域名默认路由行为
     pub default_domain_action: RouteConfigAction,
     ///ip默认路由行为
@@ -11,28 +15,70 @@ pub struct RouteConfig {
     pub domain_rules: Vec<RouteConfigRule>,
     ///ip规则列表
     pub ip_rules: Vec<RouteConfigRule>,
+    ///域名的路由策略, 默认为 `as_is`
+    pub domain_strategy: Option<DomainStrategy>,
+    ///规则集列表, 在 `selection` 中使用 `ruleset:tag` 引用
+    pub rule_sets: Option<Vec<RuleSetConfig>>,
 }
 
 ///路由行为
-#[derive(Deserialize, Debug, Clone, Copy)]
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(try_from = "String")]
 pub enum RouteConfigAction {
     ///直连
-    #[serde(rename(deserialize = "direct"))]
     Direct,
-    ///代理
-    #[serde(rename(deserialize = "proxy"))]
-    Proxy,
+    ///代理, 可以使用 `proxy:tag` 指定outbound, 不指定时使用默认的服务端
+    Proxy(Option<String>),
+    ///使用 `forward:tag` 指定的第三方socks5/http代理连接
+    Forward(String),
     ///阻断
-    #[serde(rename(deserialize = "block"))]
     Block,
 }
 
+impl TryFrom<String> for RouteConfigAction {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let t_action = match value.as_str() {
+            "direct" => Self::Direct,
+            "proxy" => Self::Proxy(None),
+            "block" => Self::Block,
+            _ => match value.split_once(':') {
+                Some(("proxy", tag)) if !tag.is_empty() => Self::Proxy(Some(tag.to_string())),
+                Some(("forward", tag)) if !tag.is_empty() => Self::Forward(tag.to_string()),
+                _ => return Err(format!("invalid route action {value}")),
+            },
+        };
+        Ok(t_action)
+    }
+}
+
+impl fmt::Display for RouteConfigAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Direct => write!(f, "Direct"),
+            Self::Proxy(None) => write!(f, "Proxy"),
+            Self::Proxy(Some(tag)) => write!(f, "Proxy:{tag}"),
+            Self::Forward(tag) => write!(f, "Forward:{tag}"),
+            Self::Block => write!(f, "Block"),
+        }
+    }
+}
+
 ///路由规则配置
 #[derive(Deserialize, Debug)]
 pub struct RouteConfigRule {
     ///路由行为
     #[serde(rename(deserialize = "action"))]
     pub t_action: RouteConfigAction,
-    ///一系列的域名匹配,或者IP匹配
-    pub selection: Vec<String>,
+    ///一系列的域名匹配,或者IP匹配, 不设置时匹配所有的域名或者IP
+    pub selection: Option<Vec<String>>,
+    ///目标端口, 例如 `22` 或者 `6881-6889`
+    pub port: Option<Vec<String>>,
+    ///网络类型
+    pub network: Option<RouteNetwork>,
+    ///本地监听的tag
+    pub inbound: Option<Vec<String>>,
+    ///客户端地址, 例如 `192.168.1.0/24`
+    pub source: Option<Vec<String>>,
 }
@@ -1,11 +1,24 @@
-use super::{geoip::IpRuleGroup, geosite::DomainRuleGroup, route_config::RouteConfigAction};
-use maxminddb::Reader;
-use regex::Regex;
-use std::net::IpAddr;
+use super::{
+    geoip::{CountryLookup, IpMatcher},
+    geosite::DomainMatcher,
+    route_config::RouteConfigAction,
+    DomainStrategy, FakeIpPool, ResolveDomain, RouteConditions, RouteContext, RuleSetConfig,
+};
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::time;
+
+///`domain_strategy` 解析域名的超时时间
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(3);
 
 ///解析处理过的路由配置
 #[derive(Debug)]
 pub struct RouteConfigCom {
+    ///优先匹配的规则列表
+    pub rules: Vec<RouteConfigRuleCom>,
     ///域名默认路由行为
     pub default_domain_action: RouteConfigAction,
     ///ip默认路由行为
@@ -14,8 +27,25 @@ pub struct RouteConfigCom {
     pub domain_rules: Vec<RouteConfigDomainRuleCom>,
     ///ip规则列表
     pub ip_rules: Vec<RouteConfigIpRuleCom>,
-    ///geoip数据库对象
-    pub mmdb_data: Option<Reader<Vec<u8>>>,
+    ///geoip数据库查询
+    pub country_lookup: Option<CountryLookup>,
+    ///域名的路由策略
+    pub domain_strategy: DomainStrategy,
+    ///fake-ip地址池, 开启fake-ip dns时才有
+    pub fake_ip_pool: Option<Arc<FakeIpPool>>,
+    ///`domain_strategy` 使用的域名解析, 没有时不解析域名
+    pub domain_resolver: Option<Arc<dyn ResolveDomain>>,
+    ///规则集配置, 用于监视和更新规则文件
+    pub rule_sets: Vec<RuleSetConfig>,
+}
+
+///预处理后的不区分域名和IP的路由规则配置
+#[derive(Debug)]
+pub struct RouteConfigRuleCom {
+    ///路由行为
+    pub t_action: RouteConfigAction,
+    ///附加条件
+    pub conditions: RouteConditions,
 }
 
 ///预处理后的域名路由规则配置
@@ -23,8 +53,10 @@ pub struct RouteConfigCom {
 pub struct RouteConfigDomainRuleCom {
     ///路由行为
     pub t_action: RouteConfigAction,
-    ///一系列的域名匹配
-    pub selection: DomainRuleGroup,
+    ///一系列的域名匹配, `None` 表示匹配所有的域名
+    pub selection: Option<DomainMatcher>,
+    ///附加条件
+    pub conditions: RouteConditions,
 }
 
 ///预处理后的IP路由规则配置
@@ -32,27 +64,48 @@ pub struct RouteConfigDomainRuleCom {
 pub struct RouteConfigIpRuleCom {
     ///路由行为
     pub t_action: RouteConfigAction,
-    ///一系列的IP匹配
-    pub selection: IpRuleGroup,
+    ///一系列的IP匹配, `None` 表示匹配所有的IP
+    pub selection: Option<IpMatcher>,
+    ///附加条件
+    pub conditions: RouteConditions,
 }
 
 impl Default for RouteConfigCom {
     fn default() -> Self {
         Self {
-            default_domain_action: RouteConfigAction::Proxy,
-            default_ip_action: RouteConfigAction::Proxy,
+            rules: Vec::default(),
+            default_domain_action: RouteConfigAction::Proxy(None),
+            default_ip_action: RouteConfigAction::Proxy(None),
             domain_rules: Vec::default(),
             ip_rules: Vec::default(),
-            mmdb_data: None,
+            country_lookup: None,
+            domain_strategy: DomainStrategy::default(),
+            fake_ip_pool: None,
+            domain_resolver: None,
+            rule_sets: Vec::default(),
         }
     }
 }
 
 impl RouteConfigCom {
     ///匹配目标地址(host:ip)得到路由行为
-    pub fn match_action(&self, conn_dest: &str) -> RouteConfigAction {
-        let pos = conn_dest.rfind(':').unwrap();
-        let host = &conn_dest[..pos];
+    ///
+    ///先匹配 `rules` , 再根据目标地址的类型匹配域名规则或者ip规则, 每个列表中第一个匹配的规则生效.
+    ///目标是域名时, 根据 `domain_strategy` 决定是否解析域名之后匹配ip规则
+    pub async fn match_action(&self, conn_dest: &str, context: &RouteContext) -> RouteConfigAction {
+        //没有端口时整个作为host, 带有端口条件的规则不会匹配
+        let (host, port) = match conn_dest.rsplit_once(':') {
+            //不带中括号的ipv6地址没有端口
+            Some((host, port)) if !host.contains(':') || host.starts_with('[') => {
+                (host, port.parse().ok())
+            }
+            _ => (conn_dest, None),
+        };
+        //ipv6地址可能带有中括号
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if let Some(t_action) = self.match_rules(port, context) {
+            return t_action;
+        }
         let is_domain = {
             if host.contains(':') {
                 //ipv6
@@ -60,45 +113,224 @@ impl RouteConfigCom {
             } else {
                 match host.rfind('.') {
                     Some(pos) => {
+                        //最后一段全部是数字时为ipv4地址
                         let root_domain = &host[pos + 1..];
-                        let num_rexp = Regex::new("^\\d+$").unwrap();
-                        !num_rexp.is_match(root_domain)
+                        root_domain.is_empty() || !root_domain.bytes().all(|b| b.is_ascii_digit())
                     }
                     None => true,
                 }
             }
         };
-        if is_domain {
-            self.match_domain(host)
-        } else {
-            self.match_ip(host)
+        if !is_domain {
+            return self.match_ip(host, port, context);
+        }
+        let domain_action = || {
+            self.match_domain_rules(host, port, context)
+                .unwrap_or_else(|| self.default_domain_action.clone())
+        };
+        match self.domain_strategy {
+            DomainStrategy::AsIs => domain_action(),
+            DomainStrategy::IpIfNonMatch => match self.match_domain_rules(host, port, context) {
+                Some(t_action) => t_action,
+                None => self
+                    .match_resolved_ip(host, port, context)
+                    .await
+                    .unwrap_or_else(|| self.default_domain_action.clone()),
+            },
+            DomainStrategy::IpOnDemand => match self.match_resolved_ip(host, port, context).await {
+                Some(t_action) => t_action,
+                None => domain_action(),
+            },
         }
     }
 
-    fn match_domain(&self, domain: &str) -> RouteConfigAction {
-        for rule in &self.domain_rules {
-            if rule.selection.match_domain(domain) {
-                return rule.t_action;
-            }
-        }
-        self.default_domain_action
+    ///路由规则中使用的所有路由行为
+    fn all_actions(&self) -> impl Iterator<Item = &RouteConfigAction> {
+        self.rules
+            .iter()
+            .map(|rule| &rule.t_action)
+            .chain(self.domain_rules.iter().map(|rule| &rule.t_action))
+            .chain(self.ip_rules.iter().map(|rule| &rule.t_action))
+            .chain([&self.default_domain_action, &self.default_ip_action])
+    }
+
+    ///路由规则中使用的所有outbound的tag
+    pub fn outbound_tags(&self) -> impl Iterator<Item = &str> {
+        self.all_actions().filter_map(|t_action| match t_action {
+            RouteConfigAction::Proxy(Some(tag)) => Some(tag.as_str()),
+            _ => None,
+        })
+    }
+
+    ///路由规则中使用的所有第三方代理出口的tag
+    pub fn forward_tags(&self) -> impl Iterator<Item = &str> {
+        self.all_actions().filter_map(|t_action| match t_action {
+            RouteConfigAction::Forward(tag) => Some(tag.as_str()),
+            _ => None,
+        })
+    }
+
+    ///目标地址(host:port)是fake-ip时, 返回对应的域名目标地址(domain:port)
+    pub fn resolve_fake_dest(&self, conn_dest: &str) -> Option<String> {
+        let fake_ip_pool = self.fake_ip_pool.as_ref()?;
+        let dest_addr: SocketAddr = conn_dest.parse().ok()?;
+        let domain = fake_ip_pool.lookup(&dest_addr.ip())?;
+        Some(format!("{domain}:{}", dest_addr.port()))
+    }
+
+    ///只使用域名匹配得到路由行为, 没有端口和连接信息, 带有这些条件的规则不会匹配
+    pub fn match_domain(&self, domain: &str) -> RouteConfigAction {
+        let context = RouteContext::default();
+        self.match_rules(None, &context)
+            .or_else(|| self.match_domain_rules(domain, None, &context))
+            .unwrap_or_else(|| self.default_domain_action.clone())
+    }
+
+    ///使用 `rules` 匹配
+    fn match_rules(&self, port: Option<u16>, context: &RouteContext) -> Option<RouteConfigAction> {
+        self.rules
+            .iter()
+            .find(|rule| rule.conditions.matches(port, context))
+            .map(|rule| rule.t_action.clone())
+    }
+
+    ///使用域名规则匹配, 没有匹配的规则时返回 `None`
+    fn match_domain_rules(
+        &self,
+        domain: &str,
+        port: Option<u16>,
+        context: &RouteContext,
+    ) -> Option<RouteConfigAction> {
+        self.domain_rules
+            .iter()
+            .find(|rule| {
+                rule.conditions.matches(port, context)
+                    && match &rule.selection {
+                        Some(selection) => selection.match_domain(domain),
+                        None => true,
+                    }
+            })
+            .map(|rule| rule.t_action.clone())
     }
 
-    fn match_ip(&self, ip_str: &str) -> RouteConfigAction {
+    fn match_ip(
+        &self,
+        ip_str: &str,
+        port: Option<u16>,
+        context: &RouteContext,
+    ) -> RouteConfigAction {
         let ip_addr: IpAddr = match ip_str.parse() {
             Ok(s) => s,
             Err(e) => {
                 log::error!("parse ip {ip_str} failed: {e}");
-                return self.default_ip_action;
+                return self.default_ip_action.clone();
             }
         };
-        if let Some(mmdb_data) = &self.mmdb_data {
-            for rule in &self.ip_rules {
-                if rule.selection.match_ip(&ip_addr, mmdb_data) {
-                    return rule.t_action;
+        self.match_ip_rules(&[ip_addr], port, context)
+            .unwrap_or_else(|| self.default_ip_action.clone())
+    }
+
+    ///解析域名, 使用解析到的ip匹配ip规则, 解析失败、超时或者没有匹配的规则时返回 `None`
+    async fn match_resolved_ip(
+        &self,
+        domain: &str,
+        port: Option<u16>,
+        context: &RouteContext,
+    ) -> Option<RouteConfigAction> {
+        //没有ip规则时不需要解析
+        if self.ip_rules.is_empty() {
+            return None;
+        }
+        let domain_resolver = self.domain_resolver.as_ref()?;
+        let resolve_future = domain_resolver.resolve_domain(domain, self);
+        let ip_list = match time::timeout(RESOLVE_TIMEOUT, resolve_future).await {
+            Ok(Ok(s)) => s,
+            Ok(Err(e)) => {
+                log::warn!("resolve {domain} failed: {e}");
+                return None;
+            }
+            Err(_) => {
+                log::warn!("resolve {domain} timeout");
+                return None;
+            }
+        };
+        self.match_ip_rules(&ip_list, port, context)
+    }
+
+    ///使用ip规则匹配, 任意一个ip匹配时规则生效
+    ///
+    ///每个ip最多查询一次geoip数据库
+    fn match_ip_rules(
+        &self,
+        ip_list: &[IpAddr],
+        port: Option<u16>,
+        context: &RouteContext,
+    ) -> Option<RouteConfigAction> {
+        //每个ip的国家/地区代码, 第一次用到时查询
+        let mut country_list: Vec<Option<Option<Arc<str>>>> = vec![None; ip_list.len()];
+        for rule in &self.ip_rules {
+            if !rule.conditions.matches(port, context) {
+                continue;
+            }
+            let selection = match &rule.selection {
+                Some(s) => s,
+                None => return Some(rule.t_action.clone()),
+            };
+            for (ip_addr, country_code) in ip_list.iter().zip(country_list.iter_mut()) {
+                if selection.match_cidr(ip_addr) {
+                    return Some(rule.t_action.clone());
+                }
+                if !selection.has_country_rules() {
+                    continue;
+                }
+                let country_lookup = match &self.country_lookup {
+                    Some(s) => s,
+                    None => continue,
+                };
+                let country_code =
+                    country_code.get_or_insert_with(|| country_lookup.lookup(ip_addr));
+                if let Some(country_code) = country_code {
+                    if selection.match_country(country_code) {
+                        return Some(rule.t_action.clone());
+                    }
                 }
             }
         }
-        self.default_ip_action
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn match_dest_type() {
+        let route_config = RouteConfigCom {
+            default_ip_action: RouteConfigAction::Direct,
+            ..Default::default()
+        };
+        let context = RouteContext::default();
+        let domain_action = RouteConfigAction::Proxy(None);
+        let ip_action = RouteConfigAction::Direct;
+        for (conn_dest, t_action) in [
+            ("example.com:443", &domain_action),
+            ("example.123a:443", &domain_action),
+            ("example.:443", &domain_action),
+            ("localhost:80", &domain_action),
+            ("1.2.3.4:80", &ip_action),
+            ("[2001:db8::1]:80", &ip_action),
+            //没有端口
+            ("example.com", &domain_action),
+            ("1.2.3.4", &ip_action),
+            ("2001:db8::1", &ip_action),
+            ("::1", &ip_action),
+        ] {
+            assert_eq!(
+                &route_config.match_action(conn_dest, &context).await,
+                t_action,
+                "{conn_dest}"
+            );
+        }
     }
 }
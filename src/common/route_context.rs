@@ -0,0 +1,23 @@
+use super::{InboundConfig, RouteNetwork};
+use std::net::{IpAddr, SocketAddr};
+
+///匹配路由时目标地址之外的连接信息
+#[derive(Debug, Clone, Default)]
+pub struct RouteContext {
+    ///网络类型
+    pub network: Option<RouteNetwork>,
+    ///连接来自的本地监听的tag
+    pub inbound_tag: Option<String>,
+    ///客户端的地址
+    pub source: Option<IpAddr>,
+}
+
+impl RouteContext {
+    pub fn new(network: RouteNetwork, inbound: &InboundConfig, source: SocketAddr) -> Self {
+        Self {
+            network: Some(network),
+            inbound_tag: inbound.tag.clone(),
+            source: Some(source.ip()),
+        }
+    }
+}
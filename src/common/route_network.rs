@@ -0,0 +1,10 @@
+use serde::Deserialize;
+
+///路由规则匹配的网络类型
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteNetwork {
+    #[serde(rename(deserialize = "tcp"))]
+    Tcp,
+    #[serde(rename(deserialize = "udp"))]
+    Udp,
+}
@@ -0,0 +1,10 @@
+use super::{geoip::IpRuleGroup, geosite::DomainRuleGroup};
+
+///从规则文件加载的规则集
+#[derive(Debug, Clone)]
+pub enum RuleSet {
+    ///域名规则
+    Domain(DomainRuleGroup),
+    ///ip规则
+    Ip(IpRuleGroup),
+}
@@ -0,0 +1,28 @@
+use super::RuleSetType;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+///规则集配置, 在 `selection` 中使用 `ruleset:tag` 引用
+#[derive(Deserialize, Debug, Clone)]
+pub struct RuleSetConfig {
+    pub tag: String,
+    ///规则集的类型
+    #[serde(rename(deserialize = "type"))]
+    pub rule_type: RuleSetType,
+    ///本地规则文件路径, 设置了 `url` 时作为下载文件的保存路径
+    pub path: Option<String>,
+    ///下载规则文件的http(s)地址, 按照匹配的路由行为直连、通过服务端或者第三方代理下载
+    pub url: Option<String>,
+    ///更新 `url` 规则文件的间隔(秒), 默认为86400
+    pub interval: Option<u64>,
+}
+
+impl RuleSetConfig {
+    ///规则文件的路径, 只设置了 `url` 时保存在 `{data_dir}/rulesets/{tag}.txt`
+    pub fn file_path(&self, data_dir: &str) -> PathBuf {
+        match &self.path {
+            Some(path) => PathBuf::from(path),
+            None => PathBuf::from(format!("{data_dir}/rulesets/{}.txt", self.tag)),
+        }
+    }
+}
@@ -0,0 +1,12 @@
+use serde::Deserialize;
+
+///规则集的类型
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleSetType {
+    ///域名规则, 每行一条 `domain:/full:/keyword:/regexp:` 规则
+    #[serde(rename(deserialize = "domain"))]
+    Domain,
+    ///ip规则, 每行一个cidr/ip或者 `geoip:` 规则
+    #[serde(rename(deserialize = "ip"))]
+    Ip,
+}
@@ -1,3 +1,4 @@
+use super::ConfigError;
 use hyper::Error as HyperError;
 use std::io::Error as IoError;
 use thiserror::Error;
@@ -5,6 +6,8 @@ use thiserror::Error;
 ///启动服务端的错误
 #[derive(Error, Debug)]
 pub enum ServerError {
+    #[error("load {0} failed: {1}")]
+    Config(String, ConfigError),
     #[error("ssl cert path not set")]
     ConfigSSlCertNone,
     #[error("ssl key path not set")]
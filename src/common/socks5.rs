@@ -1,7 +1,9 @@
 mod address_type;
+mod command;
 mod conn_dest;
 
 pub use address_type::{AddressType, ParseAddressTypeError};
-pub use conn_dest::{ConnDest, ParseConnDestError};
+pub use command::{Command, ParseCommandError};
+pub use conn_dest::{ConnDest, ConnDestAddr, ParseConnDestError};
 ///版本
 pub const VERSION: u8 = 5;
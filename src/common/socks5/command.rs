@@ -0,0 +1,42 @@
+use thiserror::Error;
+
+///socks5请求的命令
+pub enum Command {
+    ///建立tcp连接
+    Connect,
+    ///监听端口,等待远端连接
+    Bind,
+    ///udp转发
+    UdpAssociate,
+}
+
+///命令类型错误
+#[derive(Error, Debug)]
+#[error("invalid command {0}")]
+pub struct ParseCommandError(pub u8);
+
+impl From<Command> for u8 {
+    fn from(value: Command) -> Self {
+        match value {
+            Command::Connect => 1,
+            Command::Bind => 2,
+            Command::UdpAssociate => 3,
+        }
+    }
+}
+
+impl TryFrom<u8> for Command {
+    type Error = ParseCommandError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        if value == 1 {
+            Ok(Self::Connect)
+        } else if value == 2 {
+            Ok(Self::Bind)
+        } else if value == 3 {
+            Ok(Self::UdpAssociate)
+        } else {
+            Err(ParseCommandError(value))
+        }
+    }
+}
@@ -3,7 +3,7 @@ use bytes::{BufMut, Bytes, BytesMut};
 use std::{
     array::TryFromSliceError,
     fmt,
-    net::{IpAddr, Ipv4Addr},
+    net::{IpAddr, Ipv4Addr, SocketAddr},
     ops::Range,
     str::Utf8Error,
 };
@@ -11,6 +11,7 @@ use thiserror::Error;
 use tokio::io::{AsyncRead, AsyncReadExt};
 
 ///连接目标地址
+#[derive(Debug, Clone)]
 pub enum ConnDestAddr {
     ///IP地址
     Ip(IpAddr),
@@ -18,6 +19,7 @@ pub enum ConnDestAddr {
     Domain(String),
 }
 ///目标地址和端口信息
+#[derive(Debug, Clone)]
 pub struct ConnDest {
     pub addr: ConnDestAddr,
     pub port: u16,
@@ -34,6 +36,15 @@ impl Default for ConnDest {
     }
 }
 
+impl From<SocketAddr> for ConnDest {
+    fn from(value: SocketAddr) -> Self {
+        Self {
+            addr: ConnDestAddr::Ip(value.ip()),
+            port: value.port(),
+        }
+    }
+}
+
 ///解析目标地址和端口出错
 #[derive(Error, Debug)]
 
@@ -121,7 +132,7 @@ impl ConnDest {
 
     /// 解析客户端传入的目标地址和端口
     ///
-    /// ```
+    /// ```text
     /// +------+----------+----------+
     /// | ATYP | DST.ADDR | DST.PORT |
     /// +------+----------+----------+
@@ -175,6 +186,16 @@ impl ConnDest {
         Ok(Self { addr, port })
     }
 
+    ///序列化之后的长度
+    pub fn raw_data_len(&self) -> usize {
+        let addr_length = match &self.addr {
+            ConnDestAddr::Ip(IpAddr::V4(_)) => 4,
+            ConnDestAddr::Ip(IpAddr::V6(_)) => 16,
+            ConnDestAddr::Domain(s) => s.len() + 1,
+        };
+        addr_length + 3
+    }
+
     pub async fn try_from_stream<T>(stream: &mut T) -> Result<Self, ParseConnDestError>
     where
         T: AsyncRead + Unpin,
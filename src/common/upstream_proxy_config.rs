@@ -0,0 +1,17 @@
+use super::UpstreamProxyProtocol;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Clone)]
+///前置代理配置, 客户端先连接这个代理, 再通过它连接服务端
+pub struct UpstreamProxyConfig {
+    ///代理协议
+    pub protocol: UpstreamProxyProtocol,
+    ///代理地址
+    pub address: String,
+    ///代理端口
+    pub port: u16,
+    ///用户名, 不设置时不进行认证
+    pub user: Option<String>,
+    ///密码
+    pub password: Option<String>,
+}
@@ -0,0 +1,12 @@
+use serde::Deserialize;
+
+///前置代理的协议
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum UpstreamProxyProtocol {
+    ///http代理, 使用CONNECT方法建立隧道
+    #[serde(rename(deserialize = "http"))]
+    Http,
+    ///socks5代理
+    #[serde(rename(deserialize = "socks5"))]
+    Socks5,
+}
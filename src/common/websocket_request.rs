@@ -1,4 +1,4 @@
-use super::{AuthUser, ClientConfig};
+use super::{AuthUser, OutboundConfig, UpstreamProxyConfig};
 use bytes::{BufMut, BytesMut};
 use rustls::{ClientConfig as SslClientConfig, OwnedTrustAnchor, RootCertStore};
 use std::fs;
@@ -48,8 +48,14 @@ pub enum ParseWebsocketRequestError {
 pub struct WebsocketRequest {
     server_uri: Uri,
     auth_user: AuthUser,
-    ///服务端ip地址+端口
-    pub server_addr: SocketAddr,
+    ///建立tcp连接使用的host(配置了 `server_ip` 时为这个ip)
+    pub server_host: String,
+    ///服务端端口
+    pub server_port: u16,
+    ///服务端ip地址+端口, 使用前置代理时不在本地解析, 为 `None`
+    pub server_addr: Option<SocketAddr>,
+    ///前置代理
+    pub upstream_proxy: Option<UpstreamProxyConfig>,
     pub ssl_connector: Option<Connector>,
     ///额外的http请求头
     pub extra_http_headers: Vec<(HeaderName, HeaderValue)>,
@@ -97,6 +103,28 @@ impl WebsocketRequest {
         Ok(ca_list)
     }
 
+    ///解析服务端的ip地址, 有多个地址时ipv4优先
+    fn resolve_server_addr(
+        server_host: &str,
+        server_port: u16,
+    ) -> Result<SocketAddr, ParseWebsocketRequestError> {
+        let addrs_iter = (server_host, server_port)
+            .to_socket_addrs()
+            .map_err(ParseWebsocketRequestError::ParseAddrErr)?;
+        let mut tmp_addr = None;
+        for sock_addr in addrs_iter {
+            //ipv4优先
+            if sock_addr.is_ipv4() {
+                tmp_addr = Some(sock_addr);
+                break;
+            }
+            if tmp_addr.is_none() {
+                tmp_addr = Some(sock_addr);
+            }
+        }
+        tmp_addr.ok_or(ParseWebsocketRequestError::ResolveErr)
+    }
+
     ///根据ca文件,构造ssl连接配置
     fn build_ssl_config(ca_path: &str) -> Result<SslClientConfig, ParseWebsocketRequestError> {
         let mut root_store = RootCertStore::empty();
@@ -124,16 +152,17 @@ impl IntoClientRequest for &WebsocketRequest {
     }
 }
 
-impl TryFrom<&ClientConfig> for WebsocketRequest {
+impl TryFrom<&OutboundConfig> for WebsocketRequest {
     type Error = ParseWebsocketRequestError;
 
-    fn try_from(value: &ClientConfig) -> Result<Self, Self::Error> {
+    fn try_from(value: &OutboundConfig) -> Result<Self, Self::Error> {
         let server_uri: Uri = value.server_url.parse()?;
         //获取host/ip
         let server_host = match &value.server_ip {
-            Some(ip_value) if !ip_value.is_empty() => ip_value.as_str(),
+            Some(ip_value) if !ip_value.is_empty() => ip_value.to_string(),
             _ => match server_uri.host() {
-                Some(s) => s,
+                //ipv6地址去掉中括号
+                Some(s) => s.trim_start_matches('[').trim_end_matches(']').to_string(),
                 None => return Err(ParseWebsocketRequestError::UriNoHostErr),
             },
         };
@@ -146,27 +175,10 @@ impl TryFrom<&ClientConfig> for WebsocketRequest {
                 _ => return Err(ParseWebsocketRequestError::InvalidScheme),
             },
         };
-        //解析ip地址
-        let addrs_iter = (server_host, server_port)
-            .to_socket_addrs()
-            .map_err(ParseWebsocketRequestError::ParseAddrErr)?;
-        //可能会有多个地址
-        let server_addr = {
-            let mut tmp_addr = None;
-            for sock_addr in addrs_iter {
-                //ipv4优先
-                if sock_addr.is_ipv4() {
-                    tmp_addr = Some(sock_addr);
-                    break;
-                }
-                if tmp_addr.is_none() {
-                    tmp_addr = Some(sock_addr);
-                }
-            }
-            match tmp_addr {
-                Some(s) => s,
-                None => return Err(ParseWebsocketRequestError::ResolveErr),
-            }
+        //解析ip地址, 使用前置代理时由代理解析
+        let server_addr = match &value.upstream_proxy {
+            Some(_) => None,
+            None => Some(Self::resolve_server_addr(&server_host, server_port)?),
         };
         //headers
         let extra_http_headers = match &value.extra_http_headers {
@@ -192,7 +204,10 @@ impl TryFrom<&ClientConfig> for WebsocketRequest {
         Ok(Self {
             server_uri,
             auth_user: value.auth_user.to_owned(),
+            server_host,
+            server_port,
             server_addr,
+            upstream_proxy: value.upstream_proxy.to_owned(),
             ssl_connector,
             extra_http_headers,
         })
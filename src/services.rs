@@ -1,11 +1,19 @@
+mod file_state;
+mod flow_window;
+mod hangup_signal;
 mod load_config_ns;
 ///客户端模块
 pub mod proxy_client;
 ///服务端模块
 pub mod proxy_server;
 mod read_raw_data;
+mod relay_socket;
+pub use file_state::{files_state, FileState};
+pub use hangup_signal::HangupSignal;
 pub use load_config_ns::{load_config, load_config_sync};
 ///ip匹配相关功能
 pub mod geoip;
 ///域名匹配相关功能
 pub mod geosite;
+///规则集相关功能
+pub mod rule_set;
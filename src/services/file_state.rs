@@ -0,0 +1,18 @@
+use std::{path::PathBuf, time::SystemTime};
+use tokio::fs;
+
+///文件的路径、修改时间和大小, 用于判断文件是否被修改
+pub type FileState = (PathBuf, Option<SystemTime>, u64);
+
+///一组文件的状态, 文件不存在时修改时间为 `None`
+pub async fn files_state(paths: Vec<PathBuf>) -> Vec<FileState> {
+    let mut state_list = Vec::with_capacity(paths.len());
+    for path in paths {
+        let (modified, len) = match fs::metadata(&path).await {
+            Ok(metadata) => (metadata.modified().ok(), metadata.len()),
+            Err(_) => (None, 0),
+        };
+        state_list.push((path, modified, len));
+    }
+    state_list
+}
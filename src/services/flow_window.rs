@@ -0,0 +1,196 @@
+use crate::common::msg::INITIAL_WINDOW_SIZE;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use thiserror::Error;
+use tokio::sync::Semaphore;
+
+///发送窗口的最大额度, 超过时说明对方发送了错误的窗口更新
+pub const MAX_SEND_WINDOW_SIZE: usize = INITIAL_WINDOW_SIZE as usize * 4;
+
+///发送窗口已关闭, stream或者会话已经结束
+#[derive(Error, Debug)]
+#[error("send window closed")]
+pub struct WindowClosedError;
+
+///窗口更新使发送额度超过 [`MAX_SEND_WINDOW_SIZE`]
+#[derive(Error, Debug)]
+#[error("send window overflow")]
+pub struct WindowOverflowError;
+
+///发送窗口
+///
+///记录对方还能接收的字节数, 额度用完后发送方需要等待对方的窗口更新
+pub struct SendWindow {
+    credit: Semaphore,
+}
+
+impl Default for SendWindow {
+    fn default() -> Self {
+        Self {
+            credit: Semaphore::new(INITIAL_WINDOW_SIZE as usize),
+        }
+    }
+}
+
+impl SendWindow {
+    ///等待并占用 `size` 字节的发送额度, 窗口关闭后返回错误
+    pub async fn acquire(&self, size: usize) -> Result<(), WindowClosedError> {
+        if size == 0 {
+            return Ok(());
+        }
+        self.credit
+            .acquire_many(size as u32)
+            .await
+            .map_err(|_| WindowClosedError)?
+            .forget();
+        Ok(())
+    }
+
+    ///收到窗口更新, 增加发送额度
+    ///
+    ///窗口更新由对方控制, 额度超过上限时不增加并返回错误, 调用方需要重置stream
+    pub fn release(&self, size: u32) -> Result<(), WindowOverflowError> {
+        let size = size as usize;
+        //只有收到窗口更新时才会增加额度, 检查和增加之间额度只会减少
+        if self.credit.available_permits().saturating_add(size) > MAX_SEND_WINDOW_SIZE {
+            return Err(WindowOverflowError);
+        }
+        self.credit.add_permits(size);
+        Ok(())
+    }
+
+    ///stream或者会话结束时关闭窗口, 唤醒所有等待额度的发送方
+    pub fn close(&self) {
+        self.credit.close();
+    }
+}
+
+///接收窗口
+///
+///累计已经处理的字节数, 超过窗口的一半时才通知对方, 以减少窗口更新消息的数量
+#[derive(Default)]
+pub struct RecvWindow {
+    consumed: u32,
+}
+
+impl RecvWindow {
+    ///处理了 `size` 字节的数据, 返回需要通知对方的窗口更新大小
+    pub fn consume(&mut self, size: usize) -> Option<u32> {
+        self.consumed += size as u32;
+        if self.consumed >= INITIAL_WINDOW_SIZE / 2 {
+            let update_size = self.consumed;
+            self.consumed = 0;
+            Some(update_size)
+        } else {
+            None
+        }
+    }
+}
+
+///接收窗口中还没有处理的数据
+///
+///对方遵守流量控制时, 未处理的数据不会超过窗口大小
+#[derive(Default)]
+pub struct RecvPending {
+    size: AtomicUsize,
+}
+
+impl RecvPending {
+    ///收到 `size` 字节的数据, 超出窗口大小时不计入并返回 `false`
+    pub fn push(&self, size: usize) -> bool {
+        self.size
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |pending| {
+                pending
+                    .checked_add(size)
+                    .filter(|s| *s <= INITIAL_WINDOW_SIZE as usize)
+            })
+            .is_ok()
+    }
+
+    ///处理完 `size` 字节的数据
+    pub fn pop(&self, size: usize) {
+        self.size.fetch_sub(size, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::time;
+
+    const WAIT_TIME: Duration = Duration::from_millis(50);
+
+    #[tokio::test]
+    async fn acquire_wait_for_release() {
+        let send_window = SendWindow::default();
+        send_window
+            .acquire(INITIAL_WINDOW_SIZE as usize)
+            .await
+            .unwrap();
+        //额度用完之后等待
+        assert!(time::timeout(WAIT_TIME, send_window.acquire(1))
+            .await
+            .is_err());
+        //部分额度不够时继续等待
+        send_window.release(10).unwrap();
+        assert!(time::timeout(WAIT_TIME, send_window.acquire(100))
+            .await
+            .is_err());
+        send_window.release(90).unwrap();
+        time::timeout(WAIT_TIME, send_window.acquire(100))
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn close_wake_acquire() {
+        let send_window = std::sync::Arc::new(SendWindow::default());
+        send_window
+            .acquire(INITIAL_WINDOW_SIZE as usize)
+            .await
+            .unwrap();
+        let acquire_task = tokio::spawn({
+            let send_window = send_window.clone();
+            async move { send_window.acquire(1).await }
+        });
+        time::sleep(WAIT_TIME).await;
+        send_window.close();
+        assert!(acquire_task.await.unwrap().is_err());
+    }
+
+    #[test]
+    fn release_overflow() {
+        let send_window = SendWindow::default();
+        let max_update = (MAX_SEND_WINDOW_SIZE - INITIAL_WINDOW_SIZE as usize) as u32;
+        assert!(send_window.release(u32::MAX).is_err());
+        assert!(send_window.release(max_update + 1).is_err());
+        send_window.release(max_update).unwrap();
+        assert!(send_window.release(1).is_err());
+    }
+
+    #[test]
+    fn update_at_half_window() {
+        let mut recv_window = RecvWindow::default();
+        let half_size = (INITIAL_WINDOW_SIZE / 2) as usize;
+        assert_eq!(recv_window.consume(half_size - 1), None);
+        assert_eq!(recv_window.consume(1), Some(half_size as u32));
+        //通知之后重新累计
+        assert_eq!(recv_window.consume(half_size - 1), None);
+        assert_eq!(recv_window.consume(10), Some(half_size as u32 + 9));
+    }
+
+    #[test]
+    fn pending_overrun() {
+        let recv_pending = RecvPending::default();
+        let window_size = INITIAL_WINDOW_SIZE as usize;
+        assert!(recv_pending.push(window_size - 1));
+        assert!(recv_pending.push(1));
+        //超出窗口的数据不计入
+        assert!(!recv_pending.push(1));
+        recv_pending.pop(100);
+        assert!(!recv_pending.push(101));
+        assert!(recv_pending.push(100));
+        assert!(!RecvPending::default().push(window_size + 1));
+    }
+}
@@ -1,5 +1,12 @@
+mod dat_proto;
+mod load;
 mod load_mmdb_ns;
 mod parse;
 
+pub use load::{
+    from_cidr_dir::{from_cidr_dir, FromCidrDirError},
+    from_dat_file::{from_dat_file, FromDatError},
+    from_data_dir::{cidr_dir_path, from_data_dir, LoadGeoIpError},
+};
 pub use load_mmdb_ns::load_mmdb;
 pub use parse::parse_ip_selection::{parse_ip_selection, ParseIpSelectionError};
@@ -0,0 +1,33 @@
+//v2fly `geoip.dat` 使用的protobuf消息定义, 对应 `v2ray-core/app/router/config.proto`
+
+use prost::Message;
+
+///geoip.dat文件的内容
+#[derive(Clone, PartialEq, Message)]
+pub struct GeoIpList {
+    #[prost(message, repeated, tag = "1")]
+    pub entry: Vec<GeoIpEntry>,
+}
+
+///一个国家/地区(或者 `telegram` 这样的分类)的地址段
+#[derive(Clone, PartialEq, Message)]
+pub struct GeoIpEntry {
+    ///国家/地区代码, 一般为大写
+    #[prost(string, tag = "1")]
+    pub country_code: String,
+    #[prost(message, repeated, tag = "2")]
+    pub cidr: Vec<DatCidr>,
+    ///反向匹配, 不支持, 加载时跳过这样的条目
+    #[prost(bool, tag = "3")]
+    pub reverse_match: bool,
+}
+
+///一个地址段
+#[derive(Clone, PartialEq, Message)]
+pub struct DatCidr {
+    ///ipv4为4个字节, ipv6为16个字节
+    #[prost(bytes = "vec", tag = "1")]
+    pub ip: Vec<u8>,
+    #[prost(uint32, tag = "2")]
+    pub prefix: u32,
+}
@@ -0,0 +1,3 @@
+pub mod from_cidr_dir;
+pub mod from_dat_file;
+pub mod from_data_dir;
@@ -0,0 +1,67 @@
+use crate::common::geoip::{self, GeoIpSets};
+use std::{ffi::OsStr, io::Error as IoError, path::Path};
+use thiserror::Error;
+use tokio::fs;
+
+///从cidr列表目录加载的错误
+#[derive(Error, Debug)]
+pub enum FromCidrDirError {
+    #[error("read dir {0} failed: {1}")]
+    ReadDir(String, IoError),
+    #[error("read file {0} failed: {1}")]
+    ReadFile(String, IoError),
+    #[error("invalid cidr {2} in {0} line {1}")]
+    InvalidCidr(String, usize, String),
+}
+
+///从目录中的 `cn.txt` 这样的cidr列表加载地址段, 文件名(不含扩展名)为tag
+///
+///每行一个cidr或者ip地址, 忽略空行和 `#` 之后的注释
+pub async fn from_cidr_dir(dir: &Path) -> Result<GeoIpSets, FromCidrDirError> {
+    let dir_name = dir.display().to_string();
+    let mut entries = fs::read_dir(dir)
+        .await
+        .map_err(|e| FromCidrDirError::ReadDir(dir_name.to_string(), e))?;
+    let mut geoip_sets = GeoIpSets::default();
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|e| FromCidrDirError::ReadDir(dir_name.to_string(), e))?
+    {
+        let path = entry.path();
+        if path.extension() != Some(OsStr::new("txt")) {
+            continue;
+        }
+        let tag = match path.file_stem().and_then(OsStr::to_str) {
+            Some(s) => s,
+            None => continue,
+        };
+        let file_name = path.display().to_string();
+        let text = fs::read_to_string(&path)
+            .await
+            .map_err(|e| FromCidrDirError::ReadFile(file_name.to_string(), e))?;
+        let mut net_list = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = match line.find('#') {
+                Some(pos) => &line[..pos],
+                None => line,
+            }
+            .trim();
+            if line.is_empty() {
+                continue;
+            }
+            match geoip::parse_ip_net(line) {
+                Some(s) => net_list.push(s),
+                None => {
+                    return Err(FromCidrDirError::InvalidCidr(
+                        file_name,
+                        index + 1,
+                        line.to_string(),
+                    ))
+                }
+            }
+        }
+        geoip_sets.insert(tag, net_list);
+    }
+    Ok(geoip_sets)
+}
@@ -0,0 +1,91 @@
+use super::super::dat_proto::{DatCidr, GeoIpList};
+use crate::common::geoip::GeoIpSets;
+use ipnet::IpNet;
+use prost::{DecodeError, Message};
+use std::{
+    io::Error as IoError,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    path::Path,
+};
+use thiserror::Error;
+use tokio::fs;
+
+///从v2fly的geoip.dat文件加载的错误
+#[derive(Error, Debug)]
+pub enum FromDatError {
+    #[error("read file failed: {0}")]
+    ReadFile(#[from] IoError),
+    #[error("decode protobuf failed: {0}")]
+    Decode(#[from] DecodeError),
+    #[error("invalid cidr in {0}")]
+    InvalidCidr(String),
+}
+
+///从v2fly的geoip.dat(protobuf格式)加载所有的地址段
+pub async fn from_dat_file(path: &Path) -> Result<GeoIpSets, FromDatError> {
+    let file_data = fs::read(path).await?;
+    let geoip_list = GeoIpList::decode(file_data.as_slice())?;
+    let mut geoip_sets = GeoIpSets::default();
+    for entry in geoip_list.entry {
+        //反向匹配的地址段按正向加载会得到相反的结果, 直接跳过
+        if entry.reverse_match {
+            log::warn!(
+                "geoip:{} reverse match is not supported, skipped",
+                entry.country_code
+            );
+            continue;
+        }
+        let mut net_list = Vec::with_capacity(entry.cidr.len());
+        for cidr in &entry.cidr {
+            match to_ip_net(cidr) {
+                Some(s) => net_list.push(s),
+                None => return Err(FromDatError::InvalidCidr(entry.country_code)),
+            }
+        }
+        geoip_sets.insert(&entry.country_code, net_list);
+    }
+    Ok(geoip_sets)
+}
+
+fn to_ip_net(cidr: &DatCidr) -> Option<IpNet> {
+    let ip_addr = match cidr.ip.len() {
+        4 => IpAddr::V4(Ipv4Addr::from(
+            <[u8; 4]>::try_from(cidr.ip.as_slice()).ok()?,
+        )),
+        16 => IpAddr::V6(Ipv6Addr::from(
+            <[u8; 16]>::try_from(cidr.ip.as_slice()).ok()?,
+        )),
+        _ => return None,
+    };
+    let prefix = u8::try_from(cidr.prefix).ok()?;
+    IpNet::new(ip_addr, prefix).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::super::dat_proto::GeoIpEntry, *};
+    use std::{env, process};
+
+    #[tokio::test]
+    async fn skip_reverse_match() {
+        let entry = |country_code: &str, reverse_match| GeoIpEntry {
+            country_code: country_code.to_string(),
+            cidr: vec![DatCidr {
+                ip: vec![10, 0, 0, 0],
+                prefix: 8,
+            }],
+            reverse_match,
+        };
+        let geoip_list = GeoIpList {
+            entry: vec![entry("PRIVATE", false), entry("NOT-PRIVATE", true)],
+        };
+        let path = env::temp_dir().join(format!("liu-proxy-geoip-{}.dat", process::id()));
+        fs::write(&path, geoip_list.encode_to_vec()).await.unwrap();
+        let geoip_sets = from_dat_file(&path).await;
+        let _ = fs::remove_file(&path).await;
+        let geoip_sets = geoip_sets.unwrap();
+        let net: IpNet = "10.0.0.0/8".parse().unwrap();
+        assert_eq!(geoip_sets.get("private"), Some(&[net][..]));
+        assert_eq!(geoip_sets.get("not-private"), None);
+    }
+}
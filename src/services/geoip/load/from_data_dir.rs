@@ -0,0 +1,38 @@
+use super::{
+    from_cidr_dir::{from_cidr_dir, FromCidrDirError},
+    from_dat_file::{from_dat_file, FromDatError},
+};
+use crate::common::geoip::GeoIpSets;
+use std::path::PathBuf;
+use thiserror::Error;
+
+///从数据目录加载ip地址段的错误
+#[derive(Error, Debug)]
+pub enum LoadGeoIpError {
+    #[error("load geoip.dat failed: {0}")]
+    Dat(#[from] FromDatError),
+    #[error("load cidr list failed: {0}")]
+    CidrDir(#[from] FromCidrDirError),
+}
+
+///数据目录中cidr列表所在的目录
+pub fn cidr_dir_path(data_dir: &str) -> PathBuf {
+    PathBuf::from(format!("{data_dir}/geoip"))
+}
+
+///加载数据目录中的 `geoip.dat` 和 `geoip` 目录中的cidr列表, 两者都存在时合并, 都不存在时返回 `None`
+pub async fn from_data_dir(data_dir: &str) -> Result<Option<GeoIpSets>, LoadGeoIpError> {
+    let dat_path = PathBuf::from(format!("{data_dir}/geoip.dat"));
+    let cidr_dir = cidr_dir_path(data_dir);
+    if !dat_path.exists() && !cidr_dir.is_dir() {
+        return Ok(None);
+    }
+    let mut geoip_sets = GeoIpSets::default();
+    if dat_path.exists() {
+        geoip_sets.extend(from_dat_file(&dat_path).await?);
+    }
+    if cidr_dir.is_dir() {
+        geoip_sets.extend(from_cidr_dir(&cidr_dir).await?);
+    }
+    Ok(Some(geoip_sets))
+}
@@ -1,25 +1,55 @@
-use crate::common::geoip::{IpRuleGroup, IpRuleType};
+use crate::common::{
+    geoip::{GeoIpSets, IpMatcher, IpRuleGroup, IpRuleType, ParseCidrError},
+    RuleSet,
+};
+use ipnet::IpNet;
+use std::collections::HashMap;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum ParseIpSelectionError {
     #[error("invalid type {0}")]
     InvalidType(String),
+    #[error("ip rule-set {0} not found")]
+    RuleSetNotFound(String),
+    #[error("{0}")]
+    Cidr(#[from] ParseCidrError),
 }
 
-///解析路由配置中的selection字段选择的ip规则
-pub fn parse_ip_selection(selection_list: &[String]) -> Result<IpRuleGroup, ParseIpSelectionError> {
+///解析路由配置中的selection字段选择的ip规则, 并编译为匹配器
+///
+///`geoip_sets` 中有的国家/地区代码直接编译为地址段, 其余的在匹配时查询mmdb
+pub fn parse_ip_selection(
+    selection_list: &[String],
+    rule_sets: &HashMap<String, RuleSet>,
+    geoip_sets: Option<&GeoIpSets>,
+) -> Result<IpMatcher, ParseIpSelectionError> {
     let mut rule_group = IpRuleGroup::default();
     for selection_node in selection_list {
+        if let Some(tag) = selection_node.trim().strip_prefix("ruleset:") {
+            match rule_sets.get(tag.trim()) {
+                Some(RuleSet::Ip(group)) => rule_group.add_group(group.clone()),
+                _ => return Err(ParseIpSelectionError::RuleSetNotFound(tag.to_string())),
+            }
+            continue;
+        }
         let (rule_type, value) = parse_route_selection_node(selection_node)?;
         rule_group.add_rule(rule_type, value);
     }
-    Ok(rule_group)
+    if let Some(geoip_sets) = geoip_sets {
+        rule_group.expand_geoip_sets(geoip_sets);
+    }
+    let matcher = IpMatcher::try_from(rule_group)?;
+    Ok(matcher)
 }
 
 fn parse_route_selection_node(
     selection_node: &str,
 ) -> Result<(IpRuleType, String), ParseIpSelectionError> {
+    //ipv6 cidr中包含 `:`
+    if selection_node.trim().parse::<IpNet>().is_ok() {
+        return Ok((IpRuleType::Cidr, selection_node.trim().to_string()));
+    }
     let (t_type, s_text) = match selection_node.find(':') {
         Some(s) => {
             let t_type_text = selection_node[..s].trim();
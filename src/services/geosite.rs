@@ -1,10 +1,18 @@
+mod dat_proto;
 mod load;
 mod parse;
 mod save;
 
 pub use load::{
     from_binary_file::{from_binary_file, FromBinaryError},
+    from_dat_file::{from_dat_file, FromDatError},
+    from_file::{data_file_path, from_data_dir, from_file, FromFileError},
     from_source_dir::{from_source_dir, FromSourceError},
 };
-pub use parse::parse_domain_selection::{parse_domain_selection, ParseDomainSelectionError};
-pub use save::{save_as_binary, SaveBinaryError};
+pub use parse::parse_domain_selection::{
+    parse_domain_rule_group, parse_domain_selection, ParseDomainSelectionError,
+};
+pub use save::{
+    save_as_binary::{save_as_binary, SaveBinaryError},
+    save_as_dat::{save_as_dat, SaveDatError},
+};
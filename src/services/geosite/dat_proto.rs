@@ -0,0 +1,63 @@
+//v2fly `geosite.dat` 使用的protobuf消息定义, 对应 `v2ray-core/app/router/config.proto`
+
+use prost::{Enumeration, Message, Oneof};
+
+///geosite.dat文件的内容
+#[derive(Clone, PartialEq, Message)]
+pub struct GeoSiteList {
+    #[prost(message, repeated, tag = "1")]
+    pub entry: Vec<GeoSiteEntry>,
+}
+
+///一个分类(对应源文件夹中的一个文件)
+#[derive(Clone, PartialEq, Message)]
+pub struct GeoSiteEntry {
+    ///分类名称, 一般为大写
+    #[prost(string, tag = "1")]
+    pub country_code: String,
+    #[prost(message, repeated, tag = "2")]
+    pub domain: Vec<DatDomain>,
+}
+
+///一条域名规则
+#[derive(Clone, PartialEq, Message)]
+pub struct DatDomain {
+    #[prost(enumeration = "DatDomainType", tag = "1")]
+    pub r#type: i32,
+    #[prost(string, tag = "2")]
+    pub value: String,
+    #[prost(message, repeated, tag = "3")]
+    pub attribute: Vec<DatDomainAttribute>,
+}
+
+///域名规则类型
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+#[repr(i32)]
+pub enum DatDomainType {
+    ///关键词
+    Plain = 0,
+    ///正则表达式
+    Regex = 1,
+    ///域名及其子域名
+    Domain = 2,
+    ///完全匹配
+    Full = 3,
+}
+
+///域名规则的属性
+#[derive(Clone, PartialEq, Message)]
+pub struct DatDomainAttribute {
+    #[prost(string, tag = "1")]
+    pub key: String,
+    #[prost(oneof = "DatAttributeValue", tags = "2, 3")]
+    pub typed_value: Option<DatAttributeValue>,
+}
+
+///属性值
+#[derive(Clone, PartialEq, Oneof)]
+pub enum DatAttributeValue {
+    #[prost(bool, tag = "2")]
+    BoolValue(bool),
+    #[prost(int64, tag = "3")]
+    IntValue(i64),
+}
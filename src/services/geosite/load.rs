@@ -1,2 +1,4 @@
 pub mod from_binary_file;
+pub mod from_dat_file;
+pub mod from_file;
 pub mod from_source_dir;
@@ -0,0 +1,135 @@
+use super::super::dat_proto::{DatAttributeValue, DatDomainType, GeoSiteList};
+use crate::common::geosite::{DomainRule, DomainRuleType, GeoSite};
+use prost::{DecodeError, Message};
+use std::{
+    collections::{HashMap, HashSet},
+    io::Error as IoError,
+    path::Path,
+};
+use thiserror::Error;
+use tokio::fs;
+
+///从v2fly的geosite.dat文件加载的错误
+#[derive(Error, Debug)]
+pub enum FromDatError {
+    #[error("read file failed: {0}")]
+    ReadFile(#[from] IoError),
+    #[error("decode protobuf failed: {0}")]
+    Decode(#[from] DecodeError),
+    #[error("invalid domain type {0} in {1}")]
+    InvalidType(i32, String),
+}
+
+///从v2fly的geosite.dat(protobuf格式)加载GeoSite, 分类名称转换为小写
+pub async fn from_dat_file(path: &Path) -> Result<GeoSite, FromDatError> {
+    let file_data = fs::read(path).await?;
+    let geosite_list = GeoSiteList::decode(file_data.as_slice())?;
+    let mut all_rules = Vec::new();
+    //相同的规则只保存一次
+    let mut rule_ids = HashMap::new();
+    let mut file_rules = HashMap::with_capacity(geosite_list.entry.len());
+    for entry in geosite_list.entry {
+        let file_name = entry.country_code.to_lowercase();
+        let mut file_rules_set = HashSet::with_capacity(entry.domain.len());
+        for domain in entry.domain {
+            let rule_type = match DatDomainType::from_i32(domain.r#type) {
+                Some(DatDomainType::Plain) => DomainRuleType::Keyword,
+                Some(DatDomainType::Regex) => DomainRuleType::Regexp,
+                Some(DatDomainType::Domain) => DomainRuleType::Domain,
+                Some(DatDomainType::Full) => DomainRuleType::Full,
+                None => return Err(FromDatError::InvalidType(domain.r#type, file_name)),
+            };
+            let mut rule = DomainRule::new(rule_type, domain.value);
+            let mut attr_names = Vec::new();
+            for attr in domain.attribute {
+                //源文件中的属性都是 `@name` 形式, 对应true
+                let enabled = match attr.typed_value {
+                    Some(DatAttributeValue::BoolValue(s)) => s,
+                    Some(DatAttributeValue::IntValue(s)) => s != 0,
+                    None => true,
+                };
+                if enabled {
+                    attr_names.push(attr.key.to_string());
+                    rule.add_attr(attr.key, true);
+                }
+            }
+            attr_names.sort();
+            let rule_key = (rule.rule_type.clone(), rule.value.to_string(), attr_names);
+            let rule_id = *rule_ids.entry(rule_key).or_insert_with(|| {
+                all_rules.push(rule);
+                all_rules.len() - 1
+            });
+            file_rules_set.insert(rule_id);
+        }
+        file_rules
+            .entry(file_name)
+            .or_insert_with(HashSet::new)
+            .extend(file_rules_set);
+    }
+    Ok(GeoSite {
+        all_rules,
+        file_rules,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::super::save::save_as_dat::save_as_dat, *};
+    use std::{env, process};
+
+    ///每个分类的规则, 与规则的序号无关
+    fn category_rules(geosite_data: &GeoSite) -> HashMap<String, Vec<String>> {
+        geosite_data
+            .file_rules
+            .iter()
+            .map(|(file_name, rule_ids)| {
+                let mut rules: Vec<String> = rule_ids
+                    .iter()
+                    .map(|rule_id| geosite_data.all_rules[*rule_id].to_string())
+                    .collect();
+                rules.sort();
+                (file_name.to_string(), rules)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn save_and_load_dat() {
+        let mut all_rules = Vec::new();
+        let mut add_rule = |rule_type, value: &str, attrs: &[&str]| {
+            let mut rule = DomainRule::new(rule_type, value.to_string());
+            for attr in attrs {
+                rule.add_attr(attr.to_string(), true);
+            }
+            all_rules.push(rule);
+            all_rules.len() - 1
+        };
+        let google = add_rule(DomainRuleType::Domain, "google.com", &[]);
+        let google_cn = add_rule(DomainRuleType::Domain, "google.cn", &["cn"]);
+        let ads = add_rule(DomainRuleType::Keyword, "ads", &["ads", "cn"]);
+        let api = add_rule(DomainRuleType::Regexp, r"^api\d+\.example\.com$", &[]);
+        let full = add_rule(DomainRuleType::Full, "www.example.com", &["ads"]);
+        let geosite_data = GeoSite {
+            all_rules,
+            file_rules: HashMap::from([
+                (
+                    "google".to_string(),
+                    HashSet::from([google, google_cn, ads]),
+                ),
+                ("category-ads".to_string(), HashSet::from([ads, full])),
+                ("example".to_string(), HashSet::from([api, full])),
+            ]),
+        };
+        let path = env::temp_dir().join(format!("liu-proxy-geosite-{}.dat", process::id()));
+        save_as_dat(path.to_str().unwrap(), &geosite_data)
+            .await
+            .unwrap();
+        let loaded = from_dat_file(&path).await;
+        let _ = fs::remove_file(&path).await;
+        let loaded = loaded.unwrap();
+        //分类名称保存时转换为大写, 加载时转换回小写
+        assert_eq!(category_rules(&loaded), category_rules(&geosite_data));
+        //多个分类中相同的规则只保存一次
+        assert_eq!(loaded.all_rules.len(), geosite_data.all_rules.len());
+    }
+}
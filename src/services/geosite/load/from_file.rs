@@ -0,0 +1,47 @@
+use super::{
+    from_binary_file::{from_binary_file, FromBinaryError},
+    from_dat_file::{from_dat_file, FromDatError},
+};
+use crate::common::geosite::GeoSite;
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+///加载geosite数据文件的错误
+#[derive(Error, Debug)]
+pub enum FromFileError {
+    #[error("{0}")]
+    Binary(#[from] FromBinaryError),
+    #[error("{0}")]
+    Dat(#[from] FromDatError),
+    #[error("{0}/geosite.pak or {0}/geosite.dat not found")]
+    NotFound(String),
+}
+
+///数据目录中的geosite数据文件, 优先使用 `geosite.pak` , 其次是v2fly的 `geosite.dat`
+pub fn data_file_path(data_dir: &str) -> Option<PathBuf> {
+    ["geosite.pak", "geosite.dat"]
+        .into_iter()
+        .map(|file_name| PathBuf::from(format!("{data_dir}/{file_name}")))
+        .find(|path| path.exists())
+}
+
+///加载geosite数据文件, 扩展名为 `.dat` 时使用protobuf格式, 否则使用二进制格式
+pub async fn from_file(path: &Path) -> Result<GeoSite, FromFileError> {
+    let geosite_data = if path.extension() == Some(OsStr::new("dat")) {
+        from_dat_file(path).await?
+    } else {
+        from_binary_file(path).await?
+    };
+    Ok(geosite_data)
+}
+
+///加载数据目录中的geosite数据文件
+pub async fn from_data_dir(data_dir: &str) -> Result<GeoSite, FromFileError> {
+    match data_file_path(data_dir) {
+        Some(path) => from_file(&path).await,
+        None => Err(FromFileError::NotFound(data_dir.to_string())),
+    }
+}
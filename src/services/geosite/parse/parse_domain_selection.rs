@@ -1,7 +1,10 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-use crate::common::geosite::{
-    DomainRule, DomainRuleAttr, DomainRuleGroup, DomainRuleType, GeoSite,
+use crate::common::{
+    geosite::{
+        DomainMatcher, DomainRule, DomainRuleAttr, DomainRuleGroup, DomainRuleType, GeoSite,
+    },
+    RuleSet,
 };
 use futures_util::future::Either;
 use thiserror::Error;
@@ -12,16 +15,32 @@ pub enum ParseDomainSelectionError {
     InvalidType(String),
     #[error("geosite:{0} not found")]
     GeoSiteNotFound(String),
+    #[error("domain rule-set {0} not found")]
+    RuleSetNotFound(String),
+    #[error("build regex set failed: {0}")]
+    RegexSet(#[from] regex::Error),
 }
 
-///解析路由配置中的selection字段选择的域名
+///解析路由配置中的selection字段选择的域名, 并编译为匹配器
 pub fn parse_domain_selection(
     selection_list: &[String],
     geosite_data: &GeoSite,
+    rule_sets: &HashMap<String, RuleSet>,
+) -> Result<DomainMatcher, ParseDomainSelectionError> {
+    let rule_group = parse_domain_rule_group(selection_list, geosite_data, rule_sets)?;
+    let matcher = DomainMatcher::try_from(rule_group)?;
+    Ok(matcher)
+}
+
+///解析selection字段选择的所有域名规则
+pub fn parse_domain_rule_group(
+    selection_list: &[String],
+    geosite_data: &GeoSite,
+    rule_sets: &HashMap<String, RuleSet>,
 ) -> Result<DomainRuleGroup, ParseDomainSelectionError> {
     let mut rule_group = DomainRuleGroup::default();
     for selection_node in selection_list {
-        let rules = parse_route_selection_node(selection_node, geosite_data)?;
+        let rules = parse_route_selection_node(selection_node, geosite_data, rule_sets)?;
         match rules {
             Either::Left(s) => rule_group.add_rule(s),
             Either::Right(group) => rule_group.add_group(group),
@@ -33,6 +52,7 @@ pub fn parse_domain_selection(
 fn parse_route_selection_node(
     selection_node: &str,
     geosite_data: &GeoSite,
+    rule_sets: &HashMap<String, RuleSet>,
 ) -> Result<Either<DomainRule, DomainRuleGroup>, ParseDomainSelectionError> {
     let (t_type, s_text) = match selection_node.find(':') {
         Some(s) => {
@@ -49,6 +69,13 @@ fn parse_route_selection_node(
             } else if t_type_text == "geosite" {
                 let group = parse_geosite(s_text, geosite_data)?;
                 return Ok(Either::Right(group));
+            } else if t_type_text == "ruleset" {
+                return match rule_sets.get(s_text) {
+                    Some(RuleSet::Domain(group)) => Ok(Either::Right(group.clone())),
+                    _ => Err(ParseDomainSelectionError::RuleSetNotFound(
+                        s_text.to_string(),
+                    )),
+                };
             } else {
                 return Err(ParseDomainSelectionError::InvalidType(
                     t_type_text.to_string(),
@@ -0,0 +1,88 @@
+use super::super::dat_proto::{
+    DatAttributeValue, DatDomain, DatDomainAttribute, DatDomainType, GeoSiteEntry, GeoSiteList,
+};
+use crate::common::geosite::{DomainRuleType, GeoSite};
+use prost::Message;
+use std::{io::Error as IoError, path::Path};
+use thiserror::Error;
+use tokio::fs;
+
+///保存为v2fly的geosite.dat文件的错误
+#[derive(Error, Debug)]
+pub enum SaveDatError {
+    #[error("save file failed: {0}")]
+    SaveFileErr(IoError),
+    #[error("create dir failed: {0}")]
+    CreateDirErr(IoError),
+}
+
+///保存GeoSite为v2fly的geosite.dat(protobuf格式)文件, 分类名称转换为大写
+pub async fn save_as_dat(path: &str, geosite_data: &GeoSite) -> Result<(), SaveDatError> {
+    let path = Path::new(path);
+    //目录不存在,创建目录
+    if let Some(dir_path) = path.parent() {
+        if !dir_path.as_os_str().is_empty() && !dir_path.exists() {
+            fs::create_dir_all(dir_path)
+                .await
+                .map_err(SaveDatError::CreateDirErr)?;
+        }
+    }
+    let data = to_geosite_list(geosite_data).encode_to_vec();
+    fs::write(path, &data)
+        .await
+        .map_err(SaveDatError::SaveFileErr)?;
+    Ok(())
+}
+
+///转换为protobuf消息, 分类和规则按顺序排列, 使输出的文件内容固定
+fn to_geosite_list(geosite_data: &GeoSite) -> GeoSiteList {
+    let mut file_names: Vec<&String> = geosite_data.file_rules.keys().collect();
+    file_names.sort();
+    let entry = file_names
+        .into_iter()
+        .map(|file_name| {
+            let mut rule_ids: Vec<usize> =
+                geosite_data.file_rules[file_name].iter().copied().collect();
+            rule_ids.sort_unstable();
+            let domain = rule_ids
+                .into_iter()
+                .filter_map(|rule_id| {
+                    let rule = &geosite_data.all_rules[rule_id];
+                    let domain_type = match rule.rule_type {
+                        //include在加载源文件时已经展开
+                        DomainRuleType::Include => return None,
+                        DomainRuleType::Domain => DatDomainType::Domain,
+                        DomainRuleType::Keyword => DatDomainType::Plain,
+                        DomainRuleType::Regexp => DatDomainType::Regex,
+                        DomainRuleType::Full => DatDomainType::Full,
+                    };
+                    let mut attr_names: Vec<&String> = rule
+                        .attrs
+                        .iter()
+                        .flatten()
+                        .filter(|attr| attr.enabled)
+                        .map(|attr| &attr.name)
+                        .collect();
+                    attr_names.sort();
+                    let attribute = attr_names
+                        .into_iter()
+                        .map(|name| DatDomainAttribute {
+                            key: name.to_string(),
+                            typed_value: Some(DatAttributeValue::BoolValue(true)),
+                        })
+                        .collect();
+                    Some(DatDomain {
+                        r#type: domain_type as i32,
+                        value: rule.value.to_string(),
+                        attribute,
+                    })
+                })
+                .collect();
+            GeoSiteEntry {
+                country_code: file_name.to_uppercase(),
+                domain,
+            }
+        })
+        .collect();
+    GeoSiteList { entry }
+}
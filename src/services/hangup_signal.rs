@@ -0,0 +1,37 @@
+///SIGHUP信号, 用于通知重新加载配置
+///
+///不支持或者监听失败时 `recv` 一直等待
+pub struct HangupSignal {
+    #[cfg(unix)]
+    signal: Option<tokio::signal::unix::Signal>,
+}
+
+impl HangupSignal {
+    #[cfg(unix)]
+    pub fn listen() -> Self {
+        use tokio::signal::unix::{signal, SignalKind};
+        let signal = match signal(SignalKind::hangup()) {
+            Ok(s) => Some(s),
+            Err(e) => {
+                log::warn!("listen SIGHUP failed: {e}");
+                None
+            }
+        };
+        Self { signal }
+    }
+
+    #[cfg(not(unix))]
+    pub fn listen() -> Self {
+        Self {}
+    }
+
+    ///等待下一个信号
+    pub async fn recv(&mut self) {
+        #[cfg(unix)]
+        if let Some(s) = &mut self.signal {
+            s.recv().await;
+            return;
+        }
+        futures_util::future::pending().await
+    }
+}
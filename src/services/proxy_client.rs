@@ -1,19 +1,32 @@
 mod check_server_conn;
 mod connection;
+mod dns;
 mod handle_connection;
 mod http;
 mod load_route_config;
-mod poll_message;
+mod mux;
+mod outbound_group;
 mod proxy_error;
 mod proxy_tcp;
+mod rule_set_provider;
 mod run_proxy_tcp_loop;
-mod send_message;
 mod server_conn_manger;
+mod server_conn_pool;
+mod shared_route_config;
+mod sniff;
 mod socks5;
+mod transparent;
+mod upstream_proxy;
+mod watch_route_config;
 
-use crate::common::{ClientConfig, ClientError, RouteConfigCom};
+use crate::common::{
+    ClientConfig, ClientError, DnsConfig, InboundConfig, InboundProtocol, RouteConfigCom,
+};
 use crate::services;
+use futures_util::future;
 use server_conn_manger::ServerConnManger;
+use shared_route_config::SharedRouteConfig;
+use std::io::{Error as IoError, ErrorKind};
 use std::sync::Arc;
 use tokio::net::TcpListener;
 use tokio::signal;
@@ -27,24 +40,47 @@ pub async fn execute(
     let config: ClientConfig = services::load_config(config_file, "client")
         .await
         .map_err(|e| ClientError::Config(config_file.to_string(), e))?;
-    let route_config = load_route_config::load_route_config(route_file, data_dir).await?;
-    //dbg!(&route_config);
+    let inbound_list = config.inbound_list();
+    if inbound_list.is_empty() {
+        return Err(ClientError::NoInbound);
+    }
+    let mut route_config = load_route_config::load_route_config(route_file, data_dir).await?;
     let conn_manger = ServerConnManger::try_init(&config)?;
+    //配置了dns服务时, 域名路由策略也使用dns服务的上游解析域名
+    let dns_resolver = match &config.dns {
+        Some(dns_config) => {
+            route_config.fake_ip_pool = dns_config
+                .fake_ip_pool()
+                .map_err(ClientError::FakeIpRange)?
+                .map(Arc::new);
+            let resolver = dns::DnsResolver::try_new(dns_config, conn_manger.clone())
+                .map_err(|e| ClientError::DnsUpstream(dns_config.direct_upstream.to_string(), e))?;
+            Some(Arc::new(resolver))
+        }
+        None => None,
+    };
+    route_config.domain_resolver = match &dns_resolver {
+        Some(s) => Some(s.clone()),
+        None => Some(Arc::new(dns::SystemResolver::default())),
+    };
+    let route_config = Arc::new(route_config);
+    //dbg!(&route_config);
+    check_route_outbounds(&route_config, &conn_manger)?;
+    let route_config = SharedRouteConfig::new(route_config);
     //连接服务端,测试连通性
     log::info!("check server status ...");
-    let conn_pair = conn_manger
-        .get_conn_pair()
-        .await
-        .map_err(ClientError::CheckConn)?;
+    conn_manger.check_server_status().await?;
     log::info!("server status ok");
-    //把连接放回连接池
-    conn_manger.push_back_conn(conn_pair).await;
     tokio::select! {
-        output1 = run_accept_loop(conn_manger.clone(), config,route_config) =>output1?,
+        output1 = run_inbounds(conn_manger.clone(), inbound_list, route_config.clone()) =>output1?,
         output2 = signal::ctrl_c() =>{
             output2.map_err(ClientError::WaitSignal)?;
         },
         _ = conn_manger.scan_conn_pool() => (),
+        _ = conn_manger.check_groups() => (),
+        output3 = run_dns(config.dns.as_ref(), dns_resolver, route_config.clone()) =>output3?,
+        _ = watch_route_config::watch_route_config(route_file, data_dir, route_config.clone(), conn_manger.clone()) => (),
+        _ = rule_set_provider::refresh_rule_sets(data_dir, route_config.clone(), conn_manger.clone()) => (),
     };
     log::info!("clear pool conns...");
     conn_manger.clear_conns().await;
@@ -52,18 +88,75 @@ pub async fn execute(
     Ok(())
 }
 
+///路由规则中使用的outbound和第三方代理出口必须存在
+fn check_route_outbounds(
+    route_config: &RouteConfigCom,
+    conn_manger: &ServerConnManger,
+) -> Result<(), ClientError> {
+    if let Some(tag) = route_config
+        .outbound_tags()
+        .find(|tag| !conn_manger.contains_outbound(tag))
+    {
+        return Err(ClientError::UnknownOutbound(tag.to_string()));
+    }
+    if let Some(tag) = route_config
+        .forward_tags()
+        .find(|tag| conn_manger.get_forward(tag).is_none())
+    {
+        return Err(ClientError::UnknownForward(tag.to_string()));
+    }
+    Ok(())
+}
+
+///运行所有的本地监听, 任意一个出错时返回
+async fn run_inbounds(
+    conn_manger: ServerConnManger,
+    inbound_list: Vec<InboundConfig>,
+    route_config: SharedRouteConfig,
+) -> Result<(), ClientError> {
+    let accept_loops = inbound_list
+        .into_iter()
+        .map(|inbound| run_accept_loop(conn_manger.clone(), inbound, route_config.clone()));
+    future::try_join_all(accept_loops).await?;
+    Ok(())
+}
+
+///运行内置的dns服务, 没有配置时一直等待
+async fn run_dns(
+    dns_config: Option<&DnsConfig>,
+    resolver: Option<Arc<dns::DnsResolver>>,
+    route_config: SharedRouteConfig,
+) -> Result<(), ClientError> {
+    match (dns_config, resolver) {
+        (Some(dns_config), Some(resolver)) => {
+            dns::run_dns_server(dns_config, resolver, route_config).await
+        }
+        _ => future::pending().await,
+    }
+}
+
 //accept tcp 连接循环
 async fn run_accept_loop(
     conn_manger: ServerConnManger,
-    config: ClientConfig,
-    route_config: RouteConfigCom,
+    inbound: InboundConfig,
+    route_config: SharedRouteConfig,
 ) -> Result<(), ClientError> {
-    let addr = format!("{}:{}", &config.address, config.port);
-    let listener = TcpListener::bind(&addr)
-        .await
+    let addr = format!("{}:{}", &inbound.address, inbound.port);
+    let protocol = inbound.protocol;
+    let bind_result = if matches!(protocol, InboundProtocol::Tproxy) {
+        match addr.parse() {
+            Ok(socket_addr) => transparent::bind_tproxy(socket_addr),
+            Err(e) => Err(IoError::new(ErrorKind::InvalidInput, e)),
+        }
+    } else {
+        TcpListener::bind(&addr).await
+    };
+    let listener = bind_result.map_err(|e| ClientError::Bind(addr.to_string(), e))?;
+    log::info!("[{protocol:?}]proxy listening on: {addr}");
+    let listen_addr = listener
+        .local_addr()
         .map_err(|e| ClientError::Bind(addr.to_string(), e))?;
-    log::info!("proxy listening on: {addr}");
-    let route_config = Arc::new(route_config);
+    let inbound = Arc::new(inbound);
     loop {
         let (stream, addr) = match listener.accept().await {
             Ok(s) => s,
@@ -72,12 +165,29 @@ async fn run_accept_loop(
                 continue;
             }
         };
+        //每个连接使用当时的路由配置
+        let route_config = route_config.load();
         //spawn
+        if matches!(
+            protocol,
+            InboundProtocol::Redirect | InboundProtocol::Tproxy
+        ) {
+            tokio::spawn(transparent::handle_connection::handle_connection(
+                stream,
+                addr,
+                inbound.to_owned(),
+                listen_addr,
+                conn_manger.to_owned(),
+                route_config,
+            ));
+            continue;
+        }
         tokio::spawn(handle_connection::handle_connection(
             stream,
             addr,
+            inbound.to_owned(),
             conn_manger.to_owned(),
-            route_config.to_owned(),
+            route_config,
         ));
     }
 }
@@ -1,19 +1,25 @@
 use std::fmt::Display;
 
-use super::poll_message::PollMessageError;
-use super::{poll_message, server_conn_manger::ConnPair};
-use crate::common::{msg::client::Connect, msg::server::ConnectResult, msg::ServerMessage};
+use super::mux::{MuxStream, SessionClosedError};
+use crate::common::{
+    msg::client::Connect,
+    msg::server::{BindResult, ConnectResult},
+    msg::ClientMessage,
+    msg::ServerMessage,
+    socks5::ConnDest,
+};
 use thiserror::Error;
-use tokio_tungstenite::tungstenite::Error as WsError;
 
 #[derive(Error, Debug)]
 pub enum ConnectError {
     #[error("send conn msg failed: {0}")]
-    Send(#[from] WsError),
-    #[error("poll conn message failed: {0}")]
-    PollMessage(#[from] PollMessageError),
+    Send(#[from] SessionClosedError),
+    #[error("server closed connection")]
+    Closed,
     #[error("not connection message")]
     NotConnMessage,
+    #[error("not bind message")]
+    NotBindMessage,
     //服务端返回的连接失败信息
     #[error("{0}")]
     ConnErr(String),
@@ -23,14 +29,60 @@ pub enum ConnectError {
 }
 
 pub async fn check_server_conn<T: Display>(
-    ws_conn_pair: &mut ConnPair,
+    mux_stream: &mut MuxStream,
     conn_dest: T,
 ) -> Result<(), ConnectError> {
     let conn_msg = Connect(conn_dest.to_string());
-    super::send_message::send_message(&mut ws_conn_pair.0, conn_msg).await?;
-    let message = poll_message::poll_message(&mut ws_conn_pair.1).await?;
+    mux_stream
+        .send_message(ClientMessage::Conn(mux_stream.stream_id(), conn_msg))
+        .await?;
+    wait_conn_result(mux_stream).await
+}
+
+///请求服务端建立udp转发
+pub async fn check_server_udp_associate(mux_stream: &mut MuxStream) -> Result<(), ConnectError> {
+    mux_stream
+        .send_message(ClientMessage::UdpAssociate(mux_stream.stream_id()))
+        .await?;
+    wait_conn_result(mux_stream).await
+}
+
+///请求服务端监听端口, 返回服务端监听的地址
+pub async fn check_server_bind<T: Display>(
+    mux_stream: &mut MuxStream,
+    expect_dest: T,
+) -> Result<ConnDest, ConnectError> {
+    let bind_msg = Connect(expect_dest.to_string());
+    mux_stream
+        .send_message(ClientMessage::Bind(mux_stream.stream_id(), bind_msg))
+        .await?;
+    wait_bind_result(mux_stream).await
+}
+
+///等待服务端返回的bind结果, 返回结果中的地址
+pub async fn wait_bind_result(mux_stream: &mut MuxStream) -> Result<ConnDest, ConnectError> {
+    let message = match mux_stream.recv().await {
+        Some(s) => s,
+        None => return Err(ConnectError::Closed),
+    };
+    match message {
+        ServerMessage::BindResult(_, bind_result) => match bind_result {
+            BindResult::Listening(addr) | BindResult::Accepted(addr) => Ok(addr),
+            BindResult::Err(e) => Err(ConnectError::ConnErr(e)),
+            BindResult::Timeout => Err(ConnectError::Timeout),
+        },
+        _ => Err(ConnectError::NotBindMessage),
+    }
+}
+
+///等待服务端返回的连接结果
+async fn wait_conn_result(mux_stream: &mut MuxStream) -> Result<(), ConnectError> {
+    let message = match mux_stream.recv().await {
+        Some(s) => s,
+        None => return Err(ConnectError::Closed),
+    };
     match message {
-        ServerMessage::ConnResult(conn_result) => match conn_result {
+        ServerMessage::ConnResult(_, conn_result) => match conn_result {
             ConnectResult::Ok => Ok(()),
             ConnectResult::Err(e) => Err(ConnectError::ConnErr(e)),
             ConnectResult::Timeout => Err(ConnectError::Timeout),
@@ -38,13 +90,3 @@ pub async fn check_server_conn<T: Display>(
         _ => Err(ConnectError::NotConnMessage),
     }
 }
-
-impl ConnectError {
-    pub fn is_ws_error(&self) -> bool {
-        match self {
-            ConnectError::Send(_) => true,
-            ConnectError::PollMessage(e) => e.is_ws_error(),
-            _ => false,
-        }
-    }
-}
@@ -1,10 +1,7 @@
-use super::{super::server_conn_manger::ConnPairReader, ConnectionError};
+use super::ConnectionError;
 use crate::{
     common::msg::{server::ProxyResponseResult, ServerMessage},
-    services::{
-        proxy_client::poll_message::{self, PollMessageError},
-        read_raw_data,
-    },
+    services::{proxy_client::mux::MuxStreamReader, read_raw_data},
 };
 use bytes::Bytes;
 use futures_util::future::Either;
@@ -12,11 +9,11 @@ use std::io::ErrorKind;
 use tokio::net::tcp::ReadHalf;
 
 pub struct ConnReader<'a> {
-    pub inner_reader: Either<ReadHalf<'a>, ConnPairReader>,
+    pub inner_reader: Either<ReadHalf<'a>, MuxStreamReader<'a>>,
 }
 
 impl<'a> ConnReader<'a> {
-    pub fn new(inner_reader: Either<ReadHalf<'a>, ConnPairReader>) -> Self {
+    pub fn new(inner_reader: Either<ReadHalf<'a>, MuxStreamReader<'a>>) -> Self {
         Self { inner_reader }
     }
     pub async fn read_data(&mut self) -> Result<Bytes, ConnectionError> {
@@ -40,19 +37,19 @@ impl<'a> ConnReader<'a> {
             }
         }
     }
-    async fn ws_read_data(conn: &mut ConnPairReader) -> Result<Bytes, ConnectionError> {
-        let message = match poll_message::poll_message(conn).await {
-            Ok(s) => s,
-            Err(e) => match e {
-                PollMessageError::Closed => return Err(ConnectionError::ConnClosed),
-                PollMessageError::Protocol(e1) => return Err(ConnectionError::WsRead(e1)),
-                PollMessageError::Parse(e2) => return Err(ConnectionError::WsParseMsg(e2)),
-            },
+    async fn ws_read_data(conn: &mut MuxStreamReader<'_>) -> Result<Bytes, ConnectionError> {
+        let message = match conn.recv().await.map_err(ConnectionError::WsWrite)? {
+            Some(s) => s,
+            //websocket连接被断开
+            None => return Err(ConnectionError::ConnClosed),
         };
         match message {
             //类型错误, 此时不应该收到这种消息
-            ServerMessage::ConnResult(_) => Err(ConnectionError::WsInvalidServerMessage),
-            ServerMessage::ResponseResult(response_result) => match response_result {
+            ServerMessage::ConnResult(..)
+            | ServerMessage::WindowUpdate(..)
+            | ServerMessage::Datagram(..)
+            | ServerMessage::BindResult(..) => Err(ConnectionError::WsInvalidServerMessage),
+            ServerMessage::ResponseResult(_, response_result) => match response_result {
                 //得到response
                 ProxyResponseResult::Ok(response_data) => Ok(response_data),
                 //server读取response失败
@@ -61,7 +58,7 @@ impl<'a> ConnReader<'a> {
                 ProxyResponseResult::Closed => Err(ConnectionError::WsRemoteClosed),
             },
             //server发送request到远端失败
-            ServerMessage::RequestFail(e) => Err(ConnectionError::WsServerRequest(e.0)),
+            ServerMessage::RequestFail(_, e) => Err(ConnectionError::WsServerRequest(e.0)),
         }
     }
 }
@@ -1,18 +1,15 @@
-use super::{super::server_conn_manger::ConnPairWriter, ConnectionError};
-use crate::{
-    common::msg::{client::ProxyRequest, ClientMessage},
-    services::proxy_client::send_message,
-};
+use super::ConnectionError;
+use crate::services::proxy_client::mux::MuxStreamWriter;
 use bytes::Bytes;
 use futures_util::future::Either;
 use tokio::{io::AsyncWriteExt, net::tcp::WriteHalf};
 
 pub struct ConnWriter<'a> {
-    pub inner_writer: Either<WriteHalf<'a>, ConnPairWriter>,
+    pub inner_writer: Either<WriteHalf<'a>, MuxStreamWriter<'a>>,
 }
 
 impl<'a> ConnWriter<'a> {
-    pub fn new(inner_writer: Either<WriteHalf<'a>, ConnPairWriter>) -> Self {
+    pub fn new(inner_writer: Either<WriteHalf<'a>, MuxStreamWriter<'a>>) -> Self {
         Self { inner_writer }
     }
     pub async fn write_data(&mut self, mut data: Bytes) -> Result<(), ConnectionError> {
@@ -24,8 +21,8 @@ impl<'a> ConnWriter<'a> {
                     .map_err(ConnectionError::TcpWrite)?;
             }
             Either::Right(ws_writer) => {
-                let request_msg = ProxyRequest(data);
-                send_message::send_message(ws_writer, request_msg)
+                ws_writer
+                    .write_data(data)
                     .await
                     .map_err(ConnectionError::WsWrite)?;
             }
@@ -40,8 +37,8 @@ impl<'a> ConnWriter<'a> {
             }
             Either::Right(ws_writer) => {
                 //proxy被断开,通知服务端断开remote
-                let disconn_msg = ClientMessage::DisConn;
-                send_message::send_message(ws_writer, disconn_msg)
+                ws_writer
+                    .disconn()
                     .await
                     .map_err(ConnectionError::WsWrite)?;
             }
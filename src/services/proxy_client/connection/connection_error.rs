@@ -1,5 +1,7 @@
-use super::super::check_server_conn::ConnectError as ServerConnectError;
-use crate::common::msg::ParseMessageError;
+use super::super::{
+    check_server_conn::ConnectError as ServerConnectError, mux::SessionClosedError,
+    upstream_proxy::UpstreamProxyError,
+};
 use std::io::Error as IoError;
 use thiserror::Error;
 use tokio_tungstenite::tungstenite::Error as WsError;
@@ -15,16 +17,21 @@ pub enum ConnectionError {
     ServerConn(ServerConnectError),
     #[error("{0}")]
     TcpConn(IoError),
+    ///没有找到tag对应的第三方代理出口
+    #[error("forward outbound {0} not found")]
+    UnknownForward(String),
+    ///通过第三方代理连接失败
+    #[error("{0}")]
+    Forward(UpstreamProxyError),
+    ///第三方代理出口不支持的操作(bind, udp)
+    #[error("forward outbound does not support {0}")]
+    ForwardUnsupported(&'static str),
     #[error("tcp write failed, {0}")]
     TcpWrite(IoError),
     #[error("ws write failed, {0}")]
-    WsWrite(WsError),
+    WsWrite(SessionClosedError),
     #[error("tcp read failed, {0}")]
     TcpRead(IoError),
-    #[error("ws read failed, {0}")]
-    WsRead(WsError),
-    #[error("parse server message failed, {0}")]
-    WsParseMsg(ParseMessageError),
     ///与服务端之间的连接或者直连连接被断开
     #[error("remote connection closed")]
     ConnClosed,
@@ -39,10 +46,6 @@ pub enum ConnectionError {
     WsServerResponse(String),
     #[error("invalid server message")]
     WsInvalidServerMessage,
-}
-
-impl ConnectionError {
-    pub fn is_ws_error(&self) -> bool {
-        matches!(self, Self::WsConn(_) | Self::WsRead(_) | Self::WsWrite(_))
-    }
+    #[error("health check timeout")]
+    CheckTimeout,
 }
@@ -1,8 +1,7 @@
 use crate::{
-    common::{RouteConfigAction, RouteConfigCom},
+    common::{RouteConfigAction, RouteConfigCom, RouteContext},
     services::proxy_client::{
-        check_server_conn,
-        server_conn_manger::{ConnPair, ServerConnManger},
+        check_server_conn, mux::MuxStream, server_conn_manger::ServerConnManger, upstream_proxy,
     },
 };
 use futures_util::future::Either;
@@ -10,55 +9,113 @@ use tokio::net::TcpStream;
 
 use super::{conn_reader::ConnReader, conn_writer::ConnWriter, ConnectionError};
 
-///代表一个连接server的连接,或者是直连的连接
+///代表一个连接server的stream,或者是直连(包括通过第三方代理)的连接
 pub struct RemoteConnection {
-    conn: Either<TcpStream, Option<ConnPair>>,
+    conn: Either<TcpStream, MuxStream>,
 }
 
 impl RemoteConnection {
     pub async fn connect(
         conn_dest: &str,
+        route_context: &RouteContext,
         conn_manger: &ServerConnManger,
         route_config: &RouteConfigCom,
     ) -> Result<Self, ConnectionError> {
-        let t_action = route_config.match_action(conn_dest);
-        log::info!("[{t_action:?}]{conn_dest}");
+        Self::connect_with_route(
+            conn_dest,
+            conn_dest,
+            route_context,
+            conn_manger,
+            route_config,
+        )
+        .await
+    }
+
+    ///使用 `route_dest` 匹配路由, 连接 `conn_dest`
+    pub async fn connect_with_route(
+        conn_dest: &str,
+        route_dest: &str,
+        route_context: &RouteContext,
+        conn_manger: &ServerConnManger,
+        route_config: &RouteConfigCom,
+    ) -> Result<Self, ConnectionError> {
+        //fake-ip使用对应的域名路由和连接
+        let fake_dest = route_config.resolve_fake_dest(conn_dest);
+        let (conn_dest, route_dest) = match &fake_dest {
+            Some(s) => (s.as_str(), s.as_str()),
+            None => (conn_dest, route_dest),
+        };
+        let t_action = route_config.match_action(route_dest, route_context).await;
+        if route_dest == conn_dest {
+            log::info!("[{t_action}]{conn_dest}");
+        } else {
+            log::info!("[{t_action}]{conn_dest} ({route_dest})");
+        }
         let conn = match t_action {
             RouteConfigAction::Direct => Either::Left(Self::conn_direct(conn_dest).await?),
-            RouteConfigAction::Proxy => {
-                let ws_conn = Self::conn_server(conn_dest, conn_manger).await?;
-                Either::Right(Some(ws_conn))
+            RouteConfigAction::Proxy(outbound) => {
+                let mux_stream =
+                    Self::conn_server(conn_dest, outbound.as_deref(), conn_manger).await?;
+                Either::Right(mux_stream)
+            }
+            RouteConfigAction::Forward(tag) => {
+                Either::Left(Self::conn_forward(conn_dest, &tag, conn_manger).await?)
             }
             RouteConfigAction::Block => return Err(ConnectionError::RouteBlocked),
         };
         Ok(Self { conn })
     }
 
+    ///不匹配路由, 总是通过服务端连接, `outbound` 为 `None` 时使用默认的服务端
+    pub async fn connect_server(
+        conn_dest: &str,
+        outbound: Option<&str>,
+        conn_manger: &ServerConnManger,
+    ) -> Result<Self, ConnectionError> {
+        let mux_stream = Self::conn_server(conn_dest, outbound, conn_manger).await?;
+        Ok(Self::from_server(mux_stream))
+    }
+
+    ///不匹配路由, 总是通过 `tag` 指定的第三方代理连接
+    pub async fn connect_forward(
+        conn_dest: &str,
+        tag: &str,
+        conn_manger: &ServerConnManger,
+    ) -> Result<Self, ConnectionError> {
+        let tcp_conn = Self::conn_forward(conn_dest, tag, conn_manger).await?;
+        Ok(Self::from_direct(tcp_conn))
+    }
+
     ///连接websocket server
     async fn conn_server(
         conn_dest: &str,
+        outbound: Option<&str>,
         conn_manger: &ServerConnManger,
-    ) -> Result<ConnPair, ConnectionError> {
-        let mut ws_conn_pair = conn_manger
-            .get_conn_pair()
+    ) -> Result<MuxStream, ConnectionError> {
+        let mut mux_stream = conn_manger
+            .open_stream(outbound, conn_dest)
             .await
             .map_err(ConnectionError::WsConn)?;
         //把目标地址端口发给server,并检测server连接结果
-        match check_server_conn::check_server_conn(&mut ws_conn_pair, &conn_dest).await {
-            Ok(_) => {
-                //log::info!("[Proxy]{conn_dest} conn ok");
-                Ok(ws_conn_pair)
-            }
-            Err(e) => {
-                //log::info!("[Proxy]{conn_dest} conn failed: {e}");
-                //回收连接
-                if !e.is_ws_error() {
-                    conn_manger.push_back_conn(ws_conn_pair).await;
-                }
-                Err(ConnectionError::ServerConn(e))
-            }
-        }
+        check_server_conn::check_server_conn(&mut mux_stream, &conn_dest)
+            .await
+            .map_err(ConnectionError::ServerConn)?;
+        Ok(mux_stream)
+    }
+    ///通过第三方代理连接, 隧道建立之后与直连一样使用
+    async fn conn_forward(
+        conn_dest: &str,
+        tag: &str,
+        conn_manger: &ServerConnManger,
+    ) -> Result<TcpStream, ConnectionError> {
+        let proxy_config = conn_manger
+            .get_forward(tag)
+            .ok_or_else(|| ConnectionError::UnknownForward(tag.to_string()))?;
+        upstream_proxy::connect_upstream_proxy_dest(proxy_config, conn_dest)
+            .await
+            .map_err(ConnectionError::Forward)
     }
+
     ///直连
     async fn conn_direct(conn_dest: &str) -> Result<TcpStream, ConnectionError> {
         TcpStream::connect(conn_dest)
@@ -66,8 +123,22 @@ impl RemoteConnection {
             .map_err(ConnectionError::TcpConn)
     }
 
+    ///使用已经建立的直连连接
+    pub fn from_direct(tcp_conn: TcpStream) -> Self {
+        Self {
+            conn: Either::Left(tcp_conn),
+        }
+    }
+
+    ///使用已经建立的服务端stream
+    pub fn from_server(mux_stream: MuxStream) -> Self {
+        Self {
+            conn: Either::Right(mux_stream),
+        }
+    }
+
     ///分割为writer和reader
-    pub fn split(&mut self) -> (ConnWriter, ConnReader) {
+    pub fn split(&mut self) -> (ConnWriter<'_>, ConnReader<'_>) {
         match &mut self.conn {
             Either::Left(tcp_conn) => {
                 let (reader, writer) = tcp_conn.split();
@@ -76,8 +147,8 @@ impl RemoteConnection {
                     ConnReader::new(Either::Left(reader)),
                 )
             }
-            Either::Right(option_ws_conn) => {
-                let (writer, reader) = option_ws_conn.take().unwrap();
+            Either::Right(mux_stream) => {
+                let (writer, reader) = mux_stream.split();
                 (
                     ConnWriter::new(Either::Right(writer)),
                     ConnReader::new(Either::Right(reader)),
@@ -85,9 +156,4 @@ impl RemoteConnection {
             }
         }
     }
-    pub async fn push_back_conn(self, conn_manger: &ServerConnManger) {
-        if let Either::Right(Some(ws_conn)) = self.conn {
-            conn_manger.push_back_conn(ws_conn).await;
-        }
-    }
 }
@@ -0,0 +1,13 @@
+mod dns_cache;
+mod dns_error;
+mod dns_query;
+mod dns_resolver;
+mod dns_response;
+mod run_dns_server;
+mod system_resolver;
+
+pub use dns_error::DnsError;
+pub use dns_query::DnsQuery;
+pub use dns_resolver::DnsResolver;
+pub use run_dns_server::run_dns_server;
+pub use system_resolver::SystemResolver;
@@ -0,0 +1,114 @@
+use super::{dns_response::DnsResponse, DnsQuery};
+use crate::common::RouteConfigAction;
+use bytes::Bytes;
+use lru::LruCache;
+use std::{num::NonZeroUsize, sync::Mutex, time::Instant};
+
+///缓存的最大条数
+const CACHE_SIZE: usize = 4096;
+///缓存的最长时间(秒), 避免上游返回过大的ttl
+const MAX_CACHE_TTL: u32 = 3600;
+///NOERROR
+const RCODE_NOERROR: u16 = 0;
+///NXDOMAIN
+const RCODE_NXDOMAIN: u16 = 3;
+
+///一条缓存的响应
+struct CacheEntry {
+    response: DnsResponse,
+    ///缓存的时间
+    cached_at: Instant,
+    ///缓存的有效秒数
+    ttl: u32,
+}
+
+///缓存的key: `(域名, 记录类型, 查询时的路由行为)`
+///
+///路由配置重新加载之后, 域名匹配到不同的路由行为或者outbound时不会使用原来上游的响应
+type CacheKey = (String, u16, RouteConfigAction);
+
+///上游响应的缓存
+///
+///按照响应中最小的ttl过期, 取出时记录的ttl会减去已经缓存的时间
+pub struct DnsCache {
+    entries: Mutex<LruCache<CacheKey, CacheEntry>>,
+}
+
+impl Default for DnsCache {
+    fn default() -> Self {
+        let cache_size = NonZeroUsize::new(CACHE_SIZE).unwrap();
+        Self {
+            entries: Mutex::new(LruCache::new(cache_size)),
+        }
+    }
+}
+
+impl DnsCache {
+    ///查找使用 `t_action` 查询得到的响应, 使用请求的id
+    pub fn get(&self, query: &DnsQuery, t_action: &RouteConfigAction) -> Option<Bytes> {
+        let key = (query.name.to_string(), query.qtype, t_action.clone());
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.get(&key)?;
+        let elapsed_secs = entry.cached_at.elapsed().as_secs();
+        if elapsed_secs >= entry.ttl as u64 {
+            entries.pop(&key);
+            return None;
+        }
+        Some(
+            entry
+                .response
+                .to_response(query.id, query.question(), elapsed_secs as u32),
+        )
+    }
+
+    ///缓存上游的响应
+    ///
+    ///只缓存没有截断的NOERROR和NXDOMAIN响应, 没有任何记录的响应无法确定ttl, 不缓存
+    pub fn insert(&self, query: &DnsQuery, t_action: RouteConfigAction, response_data: &Bytes) {
+        let response = match DnsResponse::try_from(response_data.clone()) {
+            Ok(s) => s,
+            Err(e) => {
+                log::warn!("parse dns response of {} failed: {e}", query.name);
+                return;
+            }
+        };
+        if response.truncated || !matches!(response.rcode, RCODE_NOERROR | RCODE_NXDOMAIN) {
+            return;
+        }
+        let ttl = match response.min_ttl() {
+            Some(s) if s > 0 => s.min(MAX_CACHE_TTL),
+            _ => return,
+        };
+        let entry = CacheEntry {
+            response,
+            cached_at: Instant::now(),
+            ttl,
+        };
+        self.entries
+            .lock()
+            .unwrap()
+            .put((query.name.to_string(), query.qtype, t_action), entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::proxy_client::dns::dns_query::QTYPE_A;
+
+    #[test]
+    fn separate_route_action() {
+        let cache = DnsCache::default();
+        let query = DnsQuery::new(1, "example.com", QTYPE_A).unwrap();
+        let response = query.answer_response("1.1.1.1".parse().unwrap(), 60);
+        cache.insert(&query, RouteConfigAction::Direct, &response);
+        let query = DnsQuery::new(2, "example.com", QTYPE_A).unwrap();
+        let cached = cache.get(&query, &RouteConfigAction::Direct).unwrap();
+        //使用新请求的id
+        assert_eq!(cached[..2], 2u16.to_be_bytes());
+        //路由行为或者outbound改变之后不使用原来的响应
+        assert!(cache.get(&query, &RouteConfigAction::Proxy(None)).is_none());
+        let proxy_us = RouteConfigAction::Proxy(Some("us".to_string()));
+        assert!(cache.get(&query, &proxy_us).is_none());
+    }
+}
@@ -0,0 +1,18 @@
+use super::{super::connection::ConnectionError, dns_query::ParseDnsQueryError};
+use std::io::Error as IoError;
+use thiserror::Error;
+
+///dns查询出错
+#[derive(Error, Debug)]
+pub enum DnsError {
+    #[error("query upstream failed: {0}")]
+    Upstream(#[from] IoError),
+    #[error("query upstream timeout")]
+    Timeout,
+    #[error("query through server failed: {0}")]
+    Server(#[from] ConnectionError),
+    #[error("invalid upstream response")]
+    InvalidResponse,
+    #[error("invalid query: {0}")]
+    InvalidQuery(#[from] ParseDnsQueryError),
+}
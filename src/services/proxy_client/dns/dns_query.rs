@@ -0,0 +1,198 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use std::net::IpAddr;
+use thiserror::Error;
+
+///dns消息头的长度
+const HEADER_LEN: usize = 12;
+///域名的最大长度
+const MAX_NAME_LEN: usize = 253;
+///响应标志位
+const FLAG_QR: u16 = 0x8000;
+///递归可用标志位
+const FLAG_RA: u16 = 0x0080;
+///保留客户端请求中的 opcode 和 RD 标志位
+const FLAG_REQUEST_MASK: u16 = 0x7900;
+///服务器错误
+const RCODE_SERVFAIL: u16 = 2;
+///域名不存在
+const RCODE_NXDOMAIN: u16 = 3;
+///A记录
+pub const QTYPE_A: u16 = 1;
+///AAAA记录
+pub const QTYPE_AAAA: u16 = 28;
+///指向问题中域名的压缩指针
+const NAME_POINTER: u16 = 0xC00C;
+///IN类
+const CLASS_IN: u16 = 1;
+///期望递归查询标志位
+const FLAG_RD: u16 = 0x0100;
+
+///解析dns请求出错
+#[derive(Error, Debug)]
+pub enum ParseDnsQueryError {
+    #[error("dns message too short")]
+    Incomplete,
+    #[error("dns message is not a query")]
+    NotQuery,
+    #[error("dns query has no question")]
+    NoQuestion,
+    #[error("invalid dns name")]
+    InvalidName,
+}
+
+///客户端发来的dns请求
+#[derive(Debug)]
+pub struct DnsQuery {
+    ///请求id
+    pub id: u16,
+    ///第一个问题中的域名, 不带末尾的点
+    pub name: String,
+    ///第一个问题中的记录类型
+    pub qtype: u16,
+    ///请求的原始数据
+    pub raw_data: Bytes,
+    ///问题部分结束的位置
+    question_end: usize,
+}
+
+impl DnsQuery {
+    ///构造查询 `name` 的 `qtype` 记录的请求
+    pub fn new(id: u16, name: &str, qtype: u16) -> Result<Self, ParseDnsQueryError> {
+        let mut buf = BytesMut::with_capacity(HEADER_LEN + name.len() + 6);
+        buf.put_u16(id);
+        buf.put_u16(FLAG_RD);
+        buf.put_u16(1);
+        buf.put_u16(0);
+        buf.put_u16(0);
+        buf.put_u16(0);
+        for label in name.split('.').filter(|s| !s.is_empty()) {
+            if label.len() > 63 {
+                return Err(ParseDnsQueryError::InvalidName);
+            }
+            buf.put_u8(label.len() as u8);
+            buf.put_slice(label.as_bytes());
+        }
+        buf.put_u8(0);
+        buf.put_u16(qtype);
+        buf.put_u16(CLASS_IN);
+        Self::try_from(buf.freeze())
+    }
+
+    ///构造一个域名不存在的响应
+    pub fn nxdomain_response(&self) -> Bytes {
+        self.error_response(RCODE_NXDOMAIN)
+    }
+
+    ///构造一个服务器错误的响应
+    pub fn servfail_response(&self) -> Bytes {
+        self.error_response(RCODE_SERVFAIL)
+    }
+
+    ///构造包含一个A或者AAAA记录的响应
+    pub fn answer_response(&self, ip: IpAddr, ttl: u32) -> Bytes {
+        let ip_data = match ip {
+            IpAddr::V4(s) => s.octets().to_vec(),
+            IpAddr::V6(s) => s.octets().to_vec(),
+        };
+        let mut buf = self.response_header(0, 1);
+        //NAME TYPE CLASS TTL RDLENGTH RDATA
+        buf.put_u16(NAME_POINTER);
+        buf.put_u16(self.qtype);
+        buf.put_u16(CLASS_IN);
+        buf.put_u32(ttl);
+        buf.put_u16(ip_data.len() as u16);
+        buf.put_slice(&ip_data);
+        buf.into()
+    }
+
+    ///请求中第一个问题的原始数据
+    pub fn question(&self) -> &[u8] {
+        &self.raw_data[HEADER_LEN..self.question_end]
+    }
+
+    ///构造没有answer的响应
+    fn error_response(&self, rcode: u16) -> Bytes {
+        self.response_header(rcode, 0).into()
+    }
+
+    ///构造响应头和问题部分
+    fn response_header(&self, rcode: u16, answer_count: u16) -> BytesMut {
+        let request_flags = u16::from_be_bytes([self.raw_data[2], self.raw_data[3]]);
+        let flags = FLAG_QR | FLAG_RA | (request_flags & FLAG_REQUEST_MASK) | rcode;
+        let mut buf = BytesMut::with_capacity(self.question_end + 28);
+        buf.put_u16(self.id);
+        buf.put_u16(flags);
+        //只回复第一个问题
+        buf.put_u16(1);
+        buf.put_u16(answer_count);
+        buf.put_u16(0);
+        buf.put_u16(0);
+        buf.put_slice(&self.raw_data[HEADER_LEN..self.question_end]);
+        buf
+    }
+}
+
+impl TryFrom<Bytes> for DnsQuery {
+    type Error = ParseDnsQueryError;
+
+    ///解析请求头和第一个问题
+    ///
+    /// ```text
+    /// +----+-------+---------+---------+---------+---------+----------+
+    /// | ID | FLAGS | QDCOUNT | ANCOUNT | NSCOUNT | ARCOUNT | QUESTION |
+    /// +----+-------+---------+---------+---------+---------+----------+
+    /// | 2  |   2   |    2    |    2    |    2    |    2    | Variable |
+    /// +----+-------+---------+---------+---------+---------+----------+
+    /// ```
+    fn try_from(value: Bytes) -> Result<Self, Self::Error> {
+        if value.len() < HEADER_LEN {
+            return Err(ParseDnsQueryError::Incomplete);
+        }
+        let id = u16::from_be_bytes([value[0], value[1]]);
+        let flags = u16::from_be_bytes([value[2], value[3]]);
+        if flags & FLAG_QR != 0 {
+            return Err(ParseDnsQueryError::NotQuery);
+        }
+        let question_count = u16::from_be_bytes([value[4], value[5]]);
+        if question_count == 0 {
+            return Err(ParseDnsQueryError::NoQuestion);
+        }
+        //QNAME: 由长度前缀的label组成, 以0结束, 请求中不会使用压缩指针
+        let mut pos = HEADER_LEN;
+        let mut name = String::new();
+        loop {
+            let label_len = *value.get(pos).ok_or(ParseDnsQueryError::Incomplete)? as usize;
+            pos += 1;
+            if label_len == 0 {
+                break;
+            }
+            if label_len > 63 {
+                return Err(ParseDnsQueryError::InvalidName);
+            }
+            let label = value
+                .get(pos..pos + label_len)
+                .ok_or(ParseDnsQueryError::Incomplete)?;
+            let label = std::str::from_utf8(label).map_err(|_| ParseDnsQueryError::InvalidName)?;
+            if !name.is_empty() {
+                name.push('.');
+            }
+            name.push_str(label);
+            if name.len() > MAX_NAME_LEN {
+                return Err(ParseDnsQueryError::InvalidName);
+            }
+            pos += label_len;
+        }
+        //QTYPE(2) QCLASS(2)
+        let qtype_data = value
+            .get(pos..pos + 4)
+            .ok_or(ParseDnsQueryError::Incomplete)?;
+        let qtype = u16::from_be_bytes([qtype_data[0], qtype_data[1]]);
+        Ok(Self {
+            id,
+            name: name.to_ascii_lowercase(),
+            qtype,
+            raw_data: value,
+            question_end: pos + 4,
+        })
+    }
+}
@@ -0,0 +1,256 @@
+use super::{
+    super::{connection::RemoteConnection, server_conn_manger::ServerConnManger},
+    dns_cache::DnsCache,
+    dns_query::{QTYPE_A, QTYPE_AAAA},
+    dns_response::DnsResponse,
+    DnsError, DnsQuery,
+};
+use crate::common::{
+    DnsConfig, ResolveDomain, ResolveDomainError, RouteConfigAction, RouteConfigCom,
+};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use futures_util::future::BoxFuture;
+use std::{
+    net::{AddrParseError, IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::atomic::{AtomicU16, Ordering},
+    time::Duration,
+};
+use tokio::{net::UdpSocket, time};
+
+///查询上游dns服务器的超时时间
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+///udp响应的最大长度
+const MAX_UDP_RESPONSE_SIZE: usize = 65535;
+///fake-ip响应的ttl, 避免客户端长时间缓存已经被回收的地址
+const FAKE_IP_TTL: u32 = 1;
+
+///根据路由规则选择上游的dns解析器
+pub struct DnsResolver {
+    ///直连域名使用的上游
+    direct_upstream: SocketAddr,
+    ///代理域名使用的上游, 由服务端连接
+    proxy_upstream: String,
+    conn_manger: ServerConnManger,
+    ///上游响应的缓存
+    cache: DnsCache,
+    ///自己发起的查询使用的id
+    next_query_id: AtomicU16,
+}
+
+impl std::fmt::Debug for DnsResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DnsResolver")
+            .field("direct_upstream", &self.direct_upstream)
+            .field("proxy_upstream", &self.proxy_upstream)
+            .finish()
+    }
+}
+
+impl ResolveDomain for DnsResolver {
+    fn resolve_domain<'a>(
+        &'a self,
+        domain: &'a str,
+        route_config: &'a RouteConfigCom,
+    ) -> BoxFuture<'a, Result<Vec<IpAddr>, ResolveDomainError>> {
+        Box::pin(async move { Ok(self.lookup_ip(domain, route_config).await?) })
+    }
+}
+
+impl DnsResolver {
+    pub fn try_new(
+        config: &DnsConfig,
+        conn_manger: ServerConnManger,
+    ) -> Result<Self, AddrParseError> {
+        Ok(Self {
+            direct_upstream: config.direct_upstream.parse()?,
+            proxy_upstream: config.proxy_upstream.to_string(),
+            conn_manger,
+            cache: DnsCache::default(),
+            next_query_id: AtomicU16::new(1),
+        })
+    }
+
+    ///查询域名, 返回上游的响应
+    ///
+    ///直连的域名使用 `direct_upstream` 查询, 代理的域名通过服务端(或者第三方代理)使用tcp查询, 阻断的域名返回NXDOMAIN
+    ///
+    ///开启fake-ip时, 代理域名的A和AAAA查询直接返回地址池中的地址
+    ///
+    ///上游的响应按照ttl缓存, 不同的路由行为分开缓存
+    pub async fn resolve(
+        &self,
+        query: &DnsQuery,
+        route_config: &RouteConfigCom,
+    ) -> Result<Bytes, DnsError> {
+        let t_action = route_config.match_domain(&query.name);
+        log::info!("[{t_action}]dns {} (type {})", query.name, query.qtype);
+        match &t_action {
+            RouteConfigAction::Block => return Ok(query.nxdomain_response()),
+            RouteConfigAction::Proxy(_) => {
+                if let Some(s) = Self::fake_ip_response(route_config, query) {
+                    return Ok(s);
+                }
+            }
+            _ => (),
+        }
+        self.query_cached(query, t_action).await
+    }
+
+    ///解析域名的A和AAAA记录, 用于使用ip规则匹配域名
+    ///
+    ///与dns服务使用同样的上游和缓存, 不使用fake-ip, 阻断的域名返回空的列表
+    pub async fn lookup_ip(
+        &self,
+        domain: &str,
+        route_config: &RouteConfigCom,
+    ) -> Result<Vec<IpAddr>, DnsError> {
+        let t_action = route_config.match_domain(domain);
+        if t_action == RouteConfigAction::Block {
+            return Ok(Vec::new());
+        }
+        let (ipv4_result, ipv6_result) = tokio::join!(
+            self.lookup_type(domain, QTYPE_A, t_action.clone()),
+            self.lookup_type(domain, QTYPE_AAAA, t_action)
+        );
+        match (ipv4_result, ipv6_result) {
+            (Ok(mut ip_list), Ok(ipv6_list)) => {
+                ip_list.extend(ipv6_list);
+                Ok(ip_list)
+            }
+            (Ok(ip_list), Err(_)) | (Err(_), Ok(ip_list)) => Ok(ip_list),
+            (Err(e), Err(_)) => Err(e),
+        }
+    }
+
+    ///查询一种类型的记录, 返回其中的地址
+    async fn lookup_type(
+        &self,
+        domain: &str,
+        qtype: u16,
+        t_action: RouteConfigAction,
+    ) -> Result<Vec<IpAddr>, DnsError> {
+        let query_id = self.next_query_id.fetch_add(1, Ordering::Relaxed);
+        let query = DnsQuery::new(query_id, domain, qtype)?;
+        let response_data = self.query_cached(&query, t_action).await?;
+        let response =
+            DnsResponse::try_from(response_data).map_err(|_| DnsError::InvalidResponse)?;
+        Ok(response.ip_list)
+    }
+
+    ///使用缓存或者按照路由行为查询上游
+    async fn query_cached(
+        &self,
+        query: &DnsQuery,
+        t_action: RouteConfigAction,
+    ) -> Result<Bytes, DnsError> {
+        if let Some(s) = self.cache.get(query, &t_action) {
+            return Ok(s);
+        }
+        let response = match t_action.clone() {
+            RouteConfigAction::Direct => self.query_direct(query).await?,
+            RouteConfigAction::Proxy(outbound) => {
+                self.query_proxy(query, outbound.as_deref()).await?
+            }
+            RouteConfigAction::Forward(tag) => self.query_forward(query, &tag).await?,
+            RouteConfigAction::Block => return Ok(query.nxdomain_response()),
+        };
+        self.cache.insert(query, t_action, &response);
+        Ok(response)
+    }
+
+    ///使用fake-ip回复A和AAAA查询
+    fn fake_ip_response(route_config: &RouteConfigCom, query: &DnsQuery) -> Option<Bytes> {
+        let fake_ip_pool = route_config.fake_ip_pool.as_ref()?;
+        if query.qtype != QTYPE_A && query.qtype != QTYPE_AAAA {
+            return None;
+        }
+        let (ipv4, ipv6) = fake_ip_pool.allocate(&query.name);
+        let ip = if query.qtype == QTYPE_A {
+            IpAddr::V4(ipv4)
+        } else {
+            IpAddr::V6(ipv6)
+        };
+        Some(query.answer_response(ip, FAKE_IP_TTL))
+    }
+
+    ///通过udp查询直连的上游
+    async fn query_direct(&self, query: &DnsQuery) -> Result<Bytes, DnsError> {
+        //每次查询使用新的随机端口
+        let bind_ip = match self.direct_upstream {
+            SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        };
+        let socket = UdpSocket::bind((bind_ip, 0)).await?;
+        socket.connect(self.direct_upstream).await?;
+        socket.send(&query.raw_data).await?;
+        let mut buf = vec![0; MAX_UDP_RESPONSE_SIZE];
+        let recv_future = async {
+            loop {
+                let size = socket.recv(&mut buf).await?;
+                //忽略id不匹配的响应
+                if size >= 2 && buf[..2] == query.id.to_be_bytes() {
+                    return Ok::<_, DnsError>(size);
+                }
+            }
+        };
+        let size = time::timeout(QUERY_TIMEOUT, recv_future)
+            .await
+            .map_err(|_| DnsError::Timeout)??;
+        Ok(Bytes::copy_from_slice(&buf[..size]))
+    }
+
+    ///通过服务端使用tcp查询上游
+    async fn query_proxy(
+        &self,
+        query: &DnsQuery,
+        outbound: Option<&str>,
+    ) -> Result<Bytes, DnsError> {
+        let remote_conn =
+            RemoteConnection::connect_server(&self.proxy_upstream, outbound, &self.conn_manger)
+                .await?;
+        Self::query_tcp(query, remote_conn).await
+    }
+
+    ///通过第三方代理使用tcp查询 `proxy_upstream`
+    async fn query_forward(&self, query: &DnsQuery, tag: &str) -> Result<Bytes, DnsError> {
+        let remote_conn =
+            RemoteConnection::connect_forward(&self.proxy_upstream, tag, &self.conn_manger).await?;
+        Self::query_tcp(query, remote_conn).await
+    }
+
+    ///在已经建立的连接上使用tcp查询
+    ///
+    ///tcp消息前面有两个字节的长度
+    async fn query_tcp(
+        query: &DnsQuery,
+        mut remote_conn: RemoteConnection,
+    ) -> Result<Bytes, DnsError> {
+        let (mut conn_writer, mut conn_reader) = remote_conn.split();
+        let mut request_data = BytesMut::with_capacity(2 + query.raw_data.len());
+        request_data.put_u16(query.raw_data.len() as u16);
+        request_data.put_slice(&query.raw_data);
+        conn_writer.write_data(request_data.into()).await?;
+        let read_future = async {
+            let mut response_data = BytesMut::new();
+            loop {
+                if response_data.len() >= 2 {
+                    let response_len = u16::from_be_bytes([response_data[0], response_data[1]]);
+                    if response_data.len() >= 2 + response_len as usize {
+                        response_data.advance(2);
+                        response_data.truncate(response_len as usize);
+                        return Ok::<_, DnsError>(response_data.freeze());
+                    }
+                }
+                let data = conn_reader.read_data().await?;
+                response_data.put_slice(&data);
+            }
+        };
+        let response = time::timeout(QUERY_TIMEOUT, read_future)
+            .await
+            .map_err(|_| DnsError::Timeout)??;
+        if response.len() < 2 || response[..2] != query.id.to_be_bytes() {
+            return Err(DnsError::InvalidResponse);
+        }
+        Ok(response)
+    }
+}
@@ -0,0 +1,263 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use thiserror::Error;
+
+///dns消息头的长度
+const HEADER_LEN: usize = 12;
+///响应标志位
+const FLAG_QR: u16 = 0x8000;
+///截断标志位
+const FLAG_TC: u16 = 0x0200;
+///响应码的位置
+const RCODE_MASK: u16 = 0x000F;
+///A记录
+const QTYPE_A: u16 = 1;
+///AAAA记录
+const QTYPE_AAAA: u16 = 28;
+///OPT伪记录, ttl字段保存的是扩展标志
+const QTYPE_OPT: u16 = 41;
+///压缩指针的标志位
+const POINTER_MASK: u8 = 0xC0;
+
+///解析dns响应出错
+#[derive(Error, Debug)]
+pub enum ParseDnsResponseError {
+    #[error("dns message too short")]
+    Incomplete,
+    #[error("dns message is not a response")]
+    NotResponse,
+    #[error("invalid dns name")]
+    InvalidName,
+}
+
+///上游dns服务器的响应
+#[derive(Debug)]
+pub struct DnsResponse {
+    ///响应的原始数据
+    pub raw_data: Bytes,
+    ///响应码
+    pub rcode: u16,
+    ///响应是否被截断
+    pub truncated: bool,
+    ///问题部分结束的位置
+    question_end: usize,
+    ///所有资源记录的ttl字段的位置, 不包括OPT记录
+    ttl_offsets: Vec<usize>,
+    ///answer中所有A和AAAA记录的地址
+    pub ip_list: Vec<IpAddr>,
+}
+
+impl DnsResponse {
+    ///所有资源记录中最小的ttl, 没有记录时返回 `None`
+    pub fn min_ttl(&self) -> Option<u32> {
+        self.ttl_offsets
+            .iter()
+            .map(|offset| self.read_u32(*offset))
+            .min()
+    }
+
+    ///构造回复给客户端的响应
+    ///
+    ///使用请求的id和问题部分, 所有记录的ttl减去已经缓存的秒数
+    pub fn to_response(&self, id: u16, question: &[u8], elapsed_secs: u32) -> Bytes {
+        let mut buf = BytesMut::with_capacity(self.raw_data.len());
+        buf.put_u16(id);
+        buf.put_slice(&self.raw_data[2..HEADER_LEN]);
+        //域名大小写可能不同, 问题长度不同时保留原来的问题部分
+        if question.len() == self.question_end - HEADER_LEN {
+            buf.put_slice(question);
+        } else {
+            buf.put_slice(&self.raw_data[HEADER_LEN..self.question_end]);
+        }
+        buf.put_slice(&self.raw_data[self.question_end..]);
+        for offset in &self.ttl_offsets {
+            let ttl = self.read_u32(*offset).saturating_sub(elapsed_secs);
+            buf[*offset..*offset + 4].copy_from_slice(&ttl.to_be_bytes());
+        }
+        buf.freeze()
+    }
+
+    fn read_u32(&self, offset: usize) -> u32 {
+        let data = &self.raw_data[offset..offset + 4];
+        u32::from_be_bytes([data[0], data[1], data[2], data[3]])
+    }
+}
+
+impl TryFrom<Bytes> for DnsResponse {
+    type Error = ParseDnsResponseError;
+
+    ///解析响应头, 跳过问题部分, 记录answer, authority 和 additional 中每个记录的ttl位置
+    ///
+    /// ```text
+    /// +----------+------+-------+-----+----------+----------+
+    /// |   NAME   | TYPE | CLASS | TTL | RDLENGTH |  RDATA   |
+    /// +----------+------+-------+-----+----------+----------+
+    /// | Variable |  2   |   2   |  4  |    2     | Variable |
+    /// +----------+------+-------+-----+----------+----------+
+    /// ```
+    fn try_from(value: Bytes) -> Result<Self, Self::Error> {
+        if value.len() < HEADER_LEN {
+            return Err(ParseDnsResponseError::Incomplete);
+        }
+        let read_u16 = |pos: usize| {
+            value
+                .get(pos..pos + 2)
+                .map(|s| u16::from_be_bytes([s[0], s[1]]))
+                .ok_or(ParseDnsResponseError::Incomplete)
+        };
+        let flags = read_u16(2)?;
+        if flags & FLAG_QR == 0 {
+            return Err(ParseDnsResponseError::NotResponse);
+        }
+        let question_count = read_u16(4)?;
+        let answer_count = read_u16(6)? as usize;
+        let record_count = answer_count + read_u16(8)? as usize + read_u16(10)? as usize;
+        let mut pos = HEADER_LEN;
+        for _ in 0..question_count {
+            //QNAME QTYPE(2) QCLASS(2)
+            pos = skip_name(&value, pos)? + 4;
+        }
+        if pos > value.len() {
+            return Err(ParseDnsResponseError::Incomplete);
+        }
+        let question_end = pos;
+        let mut ttl_offsets = Vec::with_capacity(record_count);
+        let mut ip_list = Vec::new();
+        for index in 0..record_count {
+            pos = skip_name(&value, pos)?;
+            let record_type = read_u16(pos)?;
+            let data_len = read_u16(pos + 8)? as usize;
+            if record_type != QTYPE_OPT {
+                ttl_offsets.push(pos + 4);
+            }
+            pos += 10;
+            let record_data = value
+                .get(pos..pos + data_len)
+                .ok_or(ParseDnsResponseError::Incomplete)?;
+            match (record_type, data_len) {
+                _ if index >= answer_count => (),
+                (QTYPE_A, 4) => {
+                    let octets: [u8; 4] = record_data.try_into().unwrap();
+                    ip_list.push(IpAddr::V4(Ipv4Addr::from(octets)));
+                }
+                (QTYPE_AAAA, 16) => {
+                    let octets: [u8; 16] = record_data.try_into().unwrap();
+                    ip_list.push(IpAddr::V6(Ipv6Addr::from(octets)));
+                }
+                _ => (),
+            }
+            pos += data_len;
+        }
+        Ok(Self {
+            raw_data: value,
+            rcode: flags & RCODE_MASK,
+            truncated: flags & FLAG_TC != 0,
+            question_end,
+            ttl_offsets,
+            ip_list,
+        })
+    }
+}
+
+///跳过一个可能被压缩的域名, 返回域名之后的位置
+fn skip_name(data: &[u8], mut pos: usize) -> Result<usize, ParseDnsResponseError> {
+    loop {
+        let label_len = *data.get(pos).ok_or(ParseDnsResponseError::Incomplete)?;
+        if label_len & POINTER_MASK == POINTER_MASK {
+            //压缩指针占两个字节, 指向的内容不需要读取
+            return Ok(pos + 2);
+        }
+        if label_len & POINTER_MASK != 0 {
+            return Err(ParseDnsResponseError::InvalidName);
+        }
+        pos += 1;
+        if label_len == 0 {
+            return Ok(pos);
+        }
+        pos += label_len as usize;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///构造A记录的响应, 每个ttl对应一条记录, 最后带有一个OPT记录
+    fn build_response(ttl_list: &[u32], question_name: &[u8]) -> Vec<u8> {
+        let mut data = vec![0x12, 0x34, 0x81, 0x80, 0, 1];
+        data.extend_from_slice(&(ttl_list.len() as u16).to_be_bytes());
+        data.extend_from_slice(&[0, 0, 0, 1]);
+        data.extend_from_slice(question_name);
+        data.extend_from_slice(&[0, 1, 0, 1]);
+        for (index, ttl) in ttl_list.iter().enumerate() {
+            //压缩指针指向问题中的域名
+            data.extend_from_slice(&[0xC0, 0x0C, 0, 1, 0, 1]);
+            data.extend_from_slice(&ttl.to_be_bytes());
+            data.extend_from_slice(&[0, 4, 93, 184, 216, index as u8]);
+        }
+        //OPT: 根域名, ttl字段为扩展标志
+        data.extend_from_slice(&[0, 0, 41, 0x10, 0, 0, 0, 0x80, 0, 0, 0]);
+        data
+    }
+
+    const EXAMPLE_NAME: &[u8] = b"\x07example\x03com\x00";
+
+    fn read_ttl(data: &[u8], offset: usize) -> u32 {
+        u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn parse_records() {
+        let data = Bytes::from(build_response(&[300, 60], EXAMPLE_NAME));
+        let response = DnsResponse::try_from(data).unwrap();
+        assert_eq!(response.rcode, 0);
+        assert!(!response.truncated);
+        assert_eq!(response.ttl_offsets.len(), 2);
+        assert_eq!(response.min_ttl(), Some(60));
+        let ip_list: Vec<IpAddr> = vec![[93, 184, 216, 0].into(), [93, 184, 216, 1].into()];
+        assert_eq!(response.ip_list, ip_list);
+    }
+
+    #[test]
+    fn rewrite_id_question_and_ttl() {
+        let data = Bytes::from(build_response(&[300, 60], EXAMPLE_NAME));
+        let response = DnsResponse::try_from(data.clone()).unwrap();
+        let question = b"\x07EXAMPLE\x03com\x00\x00\x01\x00\x01";
+        let output = response.to_response(0xabcd, question, 10);
+        assert_eq!(output.len(), data.len());
+        assert_eq!(output[..2], [0xab, 0xcd]);
+        assert_eq!(output[12..12 + question.len()], question[..]);
+        let offsets = &response.ttl_offsets;
+        assert_eq!(read_ttl(&output, offsets[0]), 290);
+        assert_eq!(read_ttl(&output, offsets[1]), 50);
+        //OPT记录不变
+        assert_eq!(output[output.len() - 6..], data[data.len() - 6..]);
+    }
+
+    #[test]
+    fn no_records() {
+        let data = Bytes::from(build_response(&[], EXAMPLE_NAME));
+        let response = DnsResponse::try_from(data).unwrap();
+        assert_eq!(response.min_ttl(), None);
+    }
+
+    #[test]
+    fn truncated_message() {
+        let data = build_response(&[300], EXAMPLE_NAME);
+        for size in 0..data.len() {
+            let result = DnsResponse::try_from(Bytes::copy_from_slice(&data[..size]));
+            assert!(result.is_err(), "size {size}");
+        }
+    }
+
+    #[test]
+    fn invalid_message() {
+        //请求不是响应
+        let mut data = build_response(&[300], EXAMPLE_NAME);
+        data[2] = 0x01;
+        assert!(DnsResponse::try_from(Bytes::from(data)).is_err());
+        //非法的label长度
+        let data = build_response(&[300], b"\x47example\x03com\x00");
+        assert!(DnsResponse::try_from(Bytes::from(data)).is_err());
+    }
+}
@@ -0,0 +1,135 @@
+use super::{super::shared_route_config::SharedRouteConfig, DnsQuery, DnsResolver};
+use crate::common::{ClientError, DnsConfig};
+use bytes::{BufMut, Bytes, BytesMut};
+use std::{io::Error as IoError, net::SocketAddr, sync::Arc};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream, UdpSocket},
+};
+
+///udp请求的最大长度
+const MAX_UDP_QUERY_SIZE: usize = 4096;
+
+///运行dns服务, 同时监听udp和tcp
+pub async fn run_dns_server(
+    config: &DnsConfig,
+    resolver: Arc<DnsResolver>,
+    route_config: SharedRouteConfig,
+) -> Result<(), ClientError> {
+    let addr = format!("{}:{}", &config.address, config.port);
+    let udp_socket = UdpSocket::bind(&addr)
+        .await
+        .map_err(|e| ClientError::Bind(addr.to_string(), e))?;
+    let tcp_listener = TcpListener::bind(&addr)
+        .await
+        .map_err(|e| ClientError::Bind(addr.to_string(), e))?;
+    log::info!("dns listening on: {addr}");
+    tokio::select! {
+        _ = run_udp_loop(Arc::new(udp_socket), resolver.clone(), route_config.clone()) => (),
+        _ = run_tcp_loop(tcp_listener, resolver, route_config) => (),
+    }
+    Ok(())
+}
+
+///接收udp请求
+async fn run_udp_loop(
+    udp_socket: Arc<UdpSocket>,
+    resolver: Arc<DnsResolver>,
+    route_config: SharedRouteConfig,
+) {
+    let mut buf = vec![0; MAX_UDP_QUERY_SIZE];
+    loop {
+        let (size, addr) = match udp_socket.recv_from(&mut buf).await {
+            Ok(s) => s,
+            Err(e) => {
+                log::error!("dns recv failed: {e}");
+                continue;
+            }
+        };
+        let query = match DnsQuery::try_from(Bytes::copy_from_slice(&buf[..size])) {
+            Ok(s) => s,
+            Err(e) => {
+                log::warn!("parse dns query from {addr} failed: {e}");
+                continue;
+            }
+        };
+        let udp_socket = udp_socket.clone();
+        let resolver = resolver.clone();
+        let route_config = route_config.clone();
+        tokio::spawn(async move {
+            let response = resolve_query(&resolver, &query, &route_config).await;
+            if let Err(e) = udp_socket.send_to(&response, addr).await {
+                log::error!("dns send to {addr} failed: {e}");
+            }
+        });
+    }
+}
+
+///接收tcp连接
+async fn run_tcp_loop(
+    tcp_listener: TcpListener,
+    resolver: Arc<DnsResolver>,
+    route_config: SharedRouteConfig,
+) {
+    loop {
+        let (stream, addr) = match tcp_listener.accept().await {
+            Ok(s) => s,
+            Err(e) => {
+                log::error!("dns accept failed: {e}");
+                continue;
+            }
+        };
+        let resolver = resolver.clone();
+        let route_config = route_config.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_tcp_conn(stream, addr, &resolver, &route_config).await {
+                log::error!("dns tcp conn [{addr}] failed: {e}");
+            }
+        });
+    }
+}
+
+///处理一个tcp连接上的所有请求, 每个消息前面有两个字节的长度
+async fn handle_tcp_conn(
+    mut stream: TcpStream,
+    addr: SocketAddr,
+    resolver: &DnsResolver,
+    route_config: &SharedRouteConfig,
+) -> Result<(), IoError> {
+    loop {
+        let query_len = match stream.read_u16().await {
+            Ok(s) => s as usize,
+            //客户端断开
+            Err(_) => return Ok(()),
+        };
+        let mut query_data = vec![0; query_len];
+        stream.read_exact(&mut query_data).await?;
+        let query = match DnsQuery::try_from(Bytes::from(query_data)) {
+            Ok(s) => s,
+            Err(e) => {
+                log::warn!("parse dns query from {addr} failed: {e}");
+                return Ok(());
+            }
+        };
+        let response = resolve_query(resolver, &query, route_config).await;
+        let mut response_data = BytesMut::with_capacity(2 + response.len());
+        response_data.put_u16(response.len() as u16);
+        response_data.put_slice(&response);
+        stream.write_all(&response_data).await?;
+    }
+}
+
+///使用当时的路由配置查询域名, 出错时返回SERVFAIL
+async fn resolve_query(
+    resolver: &DnsResolver,
+    query: &DnsQuery,
+    route_config: &SharedRouteConfig,
+) -> Bytes {
+    match resolver.resolve(query, &route_config.load()).await {
+        Ok(s) => s,
+        Err(e) => {
+            log::error!("dns resolve {} failed: {e}", query.name);
+            query.servfail_response()
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use crate::common::{ResolveDomain, ResolveDomainError, RouteConfigCom};
+use futures_util::future::BoxFuture;
+use lru::LruCache;
+use std::{
+    net::IpAddr,
+    num::NonZeroUsize,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tokio::net;
+
+///缓存的最大条数
+const CACHE_SIZE: usize = 1024;
+///系统解析器不返回ttl, 解析结果缓存固定的时间
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+///使用系统的解析器解析域名, 没有配置dns服务时用于 `domain_strategy`
+pub struct SystemResolver {
+    ///域名 => (解析的时间, 地址列表)
+    cache: Mutex<LruCache<String, (Instant, Vec<IpAddr>)>>,
+}
+
+impl Default for SystemResolver {
+    fn default() -> Self {
+        let cache_size = NonZeroUsize::new(CACHE_SIZE).unwrap();
+        Self {
+            cache: Mutex::new(LruCache::new(cache_size)),
+        }
+    }
+}
+
+impl std::fmt::Debug for SystemResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SystemResolver").finish()
+    }
+}
+
+impl ResolveDomain for SystemResolver {
+    fn resolve_domain<'a>(
+        &'a self,
+        domain: &'a str,
+        _route_config: &'a RouteConfigCom,
+    ) -> BoxFuture<'a, Result<Vec<IpAddr>, ResolveDomainError>> {
+        Box::pin(async move { Ok(self.lookup_ip(domain).await?) })
+    }
+}
+
+impl SystemResolver {
+    ///解析域名, 使用缓存的结果
+    async fn lookup_ip(&self, domain: &str) -> Result<Vec<IpAddr>, std::io::Error> {
+        if let Some((resolved_at, ip_list)) = self.cache.lock().unwrap().get(domain) {
+            if resolved_at.elapsed() < CACHE_TTL {
+                return Ok(ip_list.clone());
+            }
+        }
+        let ip_list: Vec<IpAddr> = net::lookup_host((domain, 0))
+            .await?
+            .map(|addr| addr.ip())
+            .collect();
+        self.cache
+            .lock()
+            .unwrap()
+            .put(domain.to_string(), (Instant::now(), ip_list.clone()));
+        Ok(ip_list)
+    }
+}
@@ -1,7 +1,7 @@
 use super::http::handle_connection::handle_connection as http_handle_connection;
 use super::server_conn_manger::ServerConnManger;
 use super::socks5::handle_connection::handle_connection as socks5_handle_connection;
-use crate::common::{socks5, RouteConfigCom};
+use crate::common::{socks5, InboundConfig, InboundProtocol, RouteConfigCom};
 use std::{net::SocketAddr, sync::Arc};
 use tokio::{io::AsyncReadExt, net::TcpStream};
 
@@ -9,6 +9,7 @@ use tokio::{io::AsyncReadExt, net::TcpStream};
 pub async fn handle_connection(
     mut stream: TcpStream,
     addr: SocketAddr,
+    inbound: Arc<InboundConfig>,
     conn_manger: ServerConnManger,
     route_config: Arc<RouteConfigCom>,
 ) {
@@ -19,11 +20,23 @@ pub async fn handle_connection(
             return;
         }
     };
-    if first_byte == socks5::VERSION {
+    let is_socks5 = match inbound.protocol {
+        InboundProtocol::Socks5 => {
+            if first_byte != socks5::VERSION {
+                log::error!("invalid socks5 version {first_byte} [{addr}]");
+                return;
+            }
+            true
+        }
+        InboundProtocol::Mixed => first_byte == socks5::VERSION,
+        //透明代理不经过这里处理
+        _ => false,
+    };
+    if is_socks5 {
         //log::info!("socks5 handshake");
-        socks5_handle_connection(stream, addr, conn_manger, route_config).await;
+        socks5_handle_connection(stream, addr, inbound, conn_manger, route_config).await;
     } else {
         //log::info!("http handshake");
-        http_handle_connection(stream, addr, conn_manger, route_config, first_byte).await;
+        http_handle_connection(stream, addr, inbound, conn_manger, route_config, first_byte).await;
     }
 }
@@ -1,7 +1,9 @@
 mod build_request;
+mod check_proxy_auth;
 pub mod handle_connection;
 pub mod parse_request;
 mod proxy_request;
 mod read_request_loop;
 mod run_proxy_request_loop;
+mod write_auth_required_response;
 mod write_handshake_response;
@@ -3,6 +3,8 @@ use http::Uri;
 use httparse::Request;
 
 ///从req中构造request bytes
+///
+///`Proxy-Authorization` 只用于本地代理的授权, 不会发给远端
 pub fn build_request_data(req: &Request<'_, '_>, buf: &[u8], body_offset: usize) -> Bytes {
     let req_method = req.method.unwrap();
     let request_uri: Uri = req.path.unwrap().parse().unwrap();
@@ -19,6 +21,12 @@ pub fn build_request_data(req: &Request<'_, '_>, buf: &[u8], body_offset: usize)
     let mut data_bytes = BytesMut::from(request_line.as_bytes());
     //写入http header
     for header_info in req.headers.iter() {
+        if header_info
+            .name
+            .eq_ignore_ascii_case(http::header::PROXY_AUTHORIZATION.as_str())
+        {
+            continue;
+        }
         data_bytes.put_slice(header_info.name.as_bytes());
         data_bytes.put_slice(b": ");
         data_bytes.put_slice(header_info.value);
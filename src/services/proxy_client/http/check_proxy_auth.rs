@@ -0,0 +1,38 @@
+use crate::common::LocalUser;
+use httparse::Header;
+
+///检查 `Proxy-Authorization` 中的用户名密码, 没有配置本地用户时不需要检查
+pub fn check_proxy_auth(headers: &[Header<'_>], local_users: &[LocalUser]) -> bool {
+    if local_users.is_empty() {
+        return true;
+    }
+    let header_value = match headers.iter().find(|header_info| {
+        header_info
+            .name
+            .eq_ignore_ascii_case(http::header::PROXY_AUTHORIZATION.as_str())
+    }) {
+        Some(s) => s.value,
+        None => return false,
+    };
+    let header_value = match std::str::from_utf8(header_value) {
+        Ok(s) => s.trim(),
+        Err(_) => return false,
+    };
+    //格式: Basic base64(user:password)
+    let credentials = match header_value.split_once(' ') {
+        Some((scheme, s)) if scheme.eq_ignore_ascii_case("basic") => s.trim(),
+        _ => return false,
+    };
+    let decode_data = match base64::decode(credentials) {
+        Ok(s) => s,
+        Err(_) => return false,
+    };
+    let decode_str = match String::from_utf8(decode_data) {
+        Ok(s) => s,
+        Err(_) => return false,
+    };
+    match decode_str.split_once(':') {
+        Some((user, password)) => LocalUser::check(local_users, user, password),
+        None => false,
+    }
+}
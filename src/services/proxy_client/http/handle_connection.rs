@@ -1,12 +1,14 @@
 use super::{
-    super::{run_proxy_tcp_loop::run_proxy_tcp_loop, server_conn_manger::ServerConnManger},
+    super::{run_proxy_tcp_loop::run_proxy_tcp_loop, server_conn_manger::ServerConnManger, sniff},
     build_request,
+    check_proxy_auth::check_proxy_auth,
     parse_request::{parse_conn_dest, parse_request_body_size},
     run_proxy_request_loop::run_proxy_request_loop,
+    write_auth_required_response::write_auth_required_response,
     write_handshake_response::write_handshake_response,
 };
 use crate::{
-    common::RouteConfigCom,
+    common::{InboundConfig, RouteConfigCom, RouteContext, RouteNetwork},
     services::{
         proxy_client::connection::{ConnectionError, RemoteConnection},
         read_raw_data,
@@ -21,6 +23,7 @@ use tokio::net::TcpStream;
 pub async fn handle_connection(
     mut stream: TcpStream,
     _addr: SocketAddr,
+    inbound: Arc<InboundConfig>,
     conn_manger: ServerConnManger,
     route_config: Arc<RouteConfigCom>,
     first_byte: u8,
@@ -49,7 +52,16 @@ pub async fn handle_connection(
         };
         //解析到完整的header头
         if let Status::Complete(body_offset) = offset_status {
-            handle_request(stream, conn_manger, route_config, &req, &buf, body_offset).await;
+            handle_request(
+                stream,
+                &inbound,
+                conn_manger,
+                route_config,
+                &req,
+                &buf,
+                body_offset,
+            )
+            .await;
             break;
         }
     }
@@ -57,6 +69,7 @@ pub async fn handle_connection(
 
 async fn handle_request(
     mut stream: TcpStream,
+    inbound: &InboundConfig,
     conn_manger: ServerConnManger,
     route_config: Arc<RouteConfigCom>,
     req: &Request<'_, '_>,
@@ -65,6 +78,19 @@ async fn handle_request(
 ) {
     let req_method = req.method.unwrap();
     let req_path = req.path.unwrap();
+    let http_version = if req.version.unwrap() == 1 {
+        "1.1"
+    } else {
+        "1.0"
+    };
+    //检查本地代理授权
+    if !check_proxy_auth(req.headers, inbound.local_users()) {
+        log::warn!("proxy auth failed: {req_method} {req_path}");
+        if let Err(e) = write_auth_required_response(&mut stream, http_version).await {
+            log::error!("write http_response failed: {e}");
+        }
+        return;
+    }
     let (is_connect, conn_dest) = if req_method == http::Method::CONNECT {
         (true, req_path.to_string())
     } else {
@@ -77,46 +103,72 @@ async fn handle_request(
             }
         }
     };
-    let http_version = if req.version.unwrap() == 1 {
-        "1.1"
+    let route_context = match stream.peer_addr() {
+        Ok(addr) => RouteContext::new(RouteNetwork::Tcp, inbound, addr),
+        Err(e) => {
+            log::error!("get peer addr failed: {e}");
+            return;
+        }
+    };
+    //CONNECT请求的目标为ip时, 先回复成功, 再嗅探客户端发送的第一个数据包,
+    //ip规则拦截的连接在回复之前就会收到失败, 嗅探之后的路由拦截和连接失败只能断开连接
+    let is_sniff = is_connect && inbound.sniffing() && sniff::is_ip_dest(&conn_dest);
+    let (route_dest, conn_dest) = if is_sniff {
+        let is_blocked =
+            sniff::is_blocked_before_sniff(&conn_dest, &route_context, &route_config).await;
+        if let Err(e) =
+            write_handshake_response(&mut stream, http_version, req_path, !is_blocked).await
+        {
+            log::error!("write http_response failed: {e}");
+            return;
+        }
+        if is_blocked {
+            return;
+        }
+        sniff::sniff_dest(&stream, &conn_dest, inbound.sniff_override_dest()).await
     } else {
-        "1.0"
+        (conn_dest.clone(), conn_dest)
     };
-    let remote_conn = match RemoteConnection::connect(&conn_dest, &conn_manger, &route_config).await
+    let remote_conn = match RemoteConnection::connect_with_route(
+        &conn_dest,
+        &route_dest,
+        &route_context,
+        &conn_manger,
+        &route_config,
+    )
+    .await
     {
         Ok(s) => s,
         Err(e) => {
             if !matches!(e, ConnectionError::RouteBlocked) {
                 log::error!("conn {conn_dest} failed: {e}");
             }
-            //http 通知失败信息
-            if let Err(e1) =
-                write_handshake_response(&mut stream, http_version, req_path, false).await
-            {
-                log::error!("write http_response failed: {e1}");
+            //http 通知失败信息, 已经回复过成功时只能断开连接
+            if !is_sniff {
+                if let Err(e1) =
+                    write_handshake_response(&mut stream, http_version, req_path, false).await
+                {
+                    log::error!("write http_response failed: {e1}");
+                }
             }
             return;
         }
     };
     //CONNECT请求: 写入http_response
-    if is_connect {
+    if is_connect && !is_sniff {
         if let Err(e) = write_handshake_response(&mut stream, http_version, req_path, true).await {
-            //回收连接
-            remote_conn.push_back_conn(&conn_manger).await;
             log::error!("write http_response failed: {e}");
             return;
         }
     }
     let proxy_result = if is_connect {
-        run_proxy_tcp_loop(&conn_manger, remote_conn, stream).await
+        run_proxy_tcp_loop(remote_conn, stream).await
     } else {
         let first_request_data = build_request::build_request_data(req, buf, body_offset);
         let body_total_size = match parse_request_body_size(req.headers) {
             Ok(s) => s,
             Err(e) => {
                 log::error!("parse body size failed: {e}");
-                //回收连接
-                remote_conn.push_back_conn(&conn_manger).await;
                 return;
             }
         };
@@ -125,14 +177,7 @@ async fn handle_request(
         } else {
             0
         };
-        run_proxy_request_loop(
-            &conn_manger,
-            remote_conn,
-            stream,
-            first_request_data,
-            remain_data_size,
-        )
-        .await
+        run_proxy_request_loop(remote_conn, stream, first_request_data, remain_data_size).await
     };
     //proxy
     if let Err(proxy_error) = proxy_result {
@@ -0,0 +1,18 @@
+use std::fmt::Write;
+use std::io::Result as IoResult;
+use tokio::{io::AsyncWriteExt, net::TcpStream};
+
+///要求客户端提供 `Proxy-Authorization`
+pub async fn write_auth_required_response(
+    stream: &mut TcpStream,
+    http_version: &str,
+) -> IoResult<()> {
+    let message = "<h1>407 Proxy Authentication Required</h1>";
+    let mut buf = format!("HTTP/{http_version} 407 Proxy Authentication Required");
+    write!(buf, "\r\nProxy-Authenticate: Basic realm=\"liu-proxy\"").unwrap();
+    write!(buf, "\r\nContent-Type: text/html; charset=utf-8").unwrap();
+    write!(buf, "\r\nContent-length: {}", message.len()).unwrap();
+    write!(buf, "\r\nConnection: close").unwrap();
+    write!(buf, "\r\n\r\n{message}").unwrap();
+    stream.write_all(buf.as_bytes()).await
+}
@@ -1,8 +1,9 @@
 use crate::{
     common::{
-        ClientError, RouteConfig, RouteConfigCom, RouteConfigDomainRuleCom, RouteConfigIpRuleCom,
+        geoip::CountryLookup, ClientError, RouteConditions, RouteConfig, RouteConfigCom,
+        RouteConfigDomainRuleCom, RouteConfigIpRuleCom, RouteConfigRuleCom,
     },
-    services::{self, geoip, geosite},
+    services::{self, geoip, geosite, rule_set},
 };
 use std::path::PathBuf;
 
@@ -10,22 +11,26 @@ pub async fn load_route_config(
     route_file: &str,
     data_dir: &str,
 ) -> Result<RouteConfigCom, ClientError> {
-    let geosite_data_path = PathBuf::from(format!("{data_dir}/geosite.pak"));
-    if !geosite_data_path.exists() {
-        log::warn!("{data_dir}/geosite.pak not found !");
-        return Ok(RouteConfigCom::default());
-    }
+    let geosite_data_path = match geosite::data_file_path(data_dir) {
+        Some(s) => s,
+        None => {
+            log::warn!("{data_dir}/geosite.pak or {data_dir}/geosite.dat not found !");
+            return Ok(RouteConfigCom::default());
+        }
+    };
     let mmdb_data_path = PathBuf::from(format!("{data_dir}/GeoLite2-Country.mmdb"));
-    if !mmdb_data_path.exists() {
-        log::warn!("{data_dir}/GeoLite2-Country.mmdb not found !");
+    //geoip.dat或者cidr列表中的地址段在解析规则时编译, mmdb用于查询其余的国家/地区代码
+    let geoip_sets = geoip::from_data_dir(data_dir).await?;
+    if !mmdb_data_path.exists() && geoip_sets.is_none() {
+        log::warn!("{data_dir}/GeoLite2-Country.mmdb, geoip.dat or geoip dir not found !");
     }
     //加载geosite数据
     //let time_1 = SystemTime::now();
     //log::info!("load geosite data");
-    let geosite_data = geosite::from_binary_file(&geosite_data_path).await?;
-    let mmdb_data = if mmdb_data_path.exists() {
+    let geosite_data = geosite::from_file(&geosite_data_path).await?;
+    let country_lookup = if mmdb_data_path.exists() {
         let data = geoip::load_mmdb(&mmdb_data_path).await?;
-        Some(data)
+        Some(CountryLookup::from(data))
     } else {
         None
     };
@@ -37,32 +42,75 @@ pub async fn load_route_config(
         .await
         .map_err(|e| ClientError::Config(route_file.to_string(), e))?;
     //dbg!(&routes_config);
+    let rule_set_list = routes_config.rule_sets.unwrap_or_default();
+    let rule_sets = rule_set::load_rule_sets(&rule_set_list, data_dir).await?;
     let mut route_config_com = RouteConfigCom {
+        rules: Vec::default(),
         default_domain_action: routes_config.default_domain_action,
         default_ip_action: routes_config.default_ip_action,
         domain_rules: Vec::default(),
         ip_rules: Vec::default(),
-        mmdb_data,
+        country_lookup,
+        domain_strategy: routes_config.domain_strategy.unwrap_or_default(),
+        fake_ip_pool: None,
+        domain_resolver: None,
+        rule_sets: rule_set_list,
     };
     //let time_1 = SystemTime::now();
+    for rule in routes_config.rules.iter().flatten() {
+        let conditions = RouteConditions::try_from(rule)?;
+        route_config_com.rules.push(RouteConfigRuleCom {
+            t_action: rule.t_action.clone(),
+            conditions,
+        });
+    }
     for rule in routes_config.domain_rules {
-        let selection = geosite::parse_domain_selection(&rule.selection, &geosite_data)?;
+        let selection = match &rule.selection {
+            Some(selection) => Some(geosite::parse_domain_selection(
+                selection,
+                &geosite_data,
+                &rule_sets,
+            )?),
+            None => None,
+        };
+        let conditions = RouteConditions::try_from(&rule)?;
         let t_action = rule.t_action;
         route_config_com
             .domain_rules
             .push(RouteConfigDomainRuleCom {
                 t_action,
                 selection,
+                conditions,
             });
     }
     for rule in routes_config.ip_rules {
-        let selection = geoip::parse_ip_selection(&rule.selection)?;
+        let selection = match &rule.selection {
+            Some(selection) => Some(geoip::parse_ip_selection(
+                selection,
+                &rule_sets,
+                geoip_sets.as_ref(),
+            )?),
+            None => None,
+        };
+        let conditions = RouteConditions::try_from(&rule)?;
         let t_action = rule.t_action;
         route_config_com.ip_rules.push(RouteConfigIpRuleCom {
             t_action,
             selection,
+            conditions,
         });
     }
+    if route_config_com.country_lookup.is_none()
+        && route_config_com
+            .ip_rules
+            .iter()
+            .flat_map(|rule| &rule.selection)
+            .any(|selection| selection.has_country_rules())
+    {
+        log::warn!(
+            "some geoip rules not found in geoip.dat or geoip dir, they need GeoLite2-Country.mmdb"
+        );
+    }
     //let time_2 = SystemTime::now();
     //let d = time_2.duration_since(time_1).unwrap();
     //log::info!("parse selection list {d:?}");
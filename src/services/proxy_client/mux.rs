@@ -0,0 +1,5 @@
+mod mux_session;
+mod mux_stream;
+
+pub use mux_session::{MuxSession, SessionClosedError};
+pub use mux_stream::{MuxStream, MuxStreamReader, MuxStreamWriter};
@@ -0,0 +1,238 @@
+use super::MuxStream;
+use crate::common::msg::{ClientMessage, ServerMessage};
+use crate::services::flow_window::SendWindow;
+use bytes::Bytes;
+use futures_util::{
+    stream::{SplitSink, SplitStream},
+    SinkExt, StreamExt,
+};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+use thiserror::Error;
+use tokio::{
+    net::TcpStream,
+    sync::mpsc::{self, error::TrySendError, Receiver, Sender, UnboundedSender},
+};
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+///websocket连接已关闭
+#[derive(Error, Debug)]
+#[error("websocket session closed")]
+pub struct SessionClosedError;
+
+///会话中一个stream的句柄
+struct StreamHandle {
+    ///服务端消息的发送端, 数据量由服务端的发送窗口限制
+    tx: UnboundedSender<ServerMessage>,
+    ///向服务端发送数据的窗口
+    send_window: Arc<SendWindow>,
+}
+
+impl Drop for StreamHandle {
+    ///stream移除或者会话结束时, 唤醒等待发送窗口的writer
+    fn drop(&mut self) {
+        self.send_window.close();
+    }
+}
+
+///一个与服务端之间的websocket连接, 上面可以同时承载多个stream
+pub struct MuxSession {
+    ///发给writer task的消息
+    msg_tx: Sender<Message>,
+    ///stream id => stream句柄
+    streams: Mutex<HashMap<u32, StreamHandle>>,
+    ///下一个分配的stream id
+    next_stream_id: AtomicU32,
+    closed: AtomicBool,
+    ///最后一次收到服务端消息的时间
+    last_active: Mutex<Instant>,
+    ///没有stream时是否保留连接
+    keep_idle: bool,
+    ///服务端的地址
+    server_addr: Option<SocketAddr>,
+}
+
+impl MuxSession {
+    ///使用已经握手完成的websocket连接创建会话, 并启动读写task
+    ///
+    ///`server_addr` 为服务端的地址, 通过前置代理连接时为 `None`
+    pub fn new(ws_stream: WsStream, keep_idle: bool, server_addr: Option<SocketAddr>) -> Arc<Self> {
+        let (msg_tx, msg_rx) = mpsc::channel(20);
+        let session = Arc::new(Self {
+            msg_tx,
+            streams: Mutex::new(HashMap::new()),
+            next_stream_id: AtomicU32::new(1),
+            closed: AtomicBool::new(false),
+            last_active: Mutex::new(Instant::now()),
+            keep_idle,
+            server_addr,
+        });
+        let (writer, reader) = ws_stream.split();
+        tokio::spawn(Self::run_writer(session.clone(), writer, msg_rx));
+        tokio::spawn(Self::run_reader(session.clone(), reader));
+        session
+    }
+
+    ///连接是否已关闭
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    ///服务端的地址
+    pub fn server_addr(&self) -> Option<SocketAddr> {
+        self.server_addr
+    }
+
+    ///当前承载的stream个数
+    pub fn stream_count(&self) -> usize {
+        self.streams.lock().unwrap().len()
+    }
+
+    ///距离最后一次收到服务端消息的时间
+    pub fn idle_time(&self) -> std::time::Duration {
+        self.last_active.lock().unwrap().elapsed()
+    }
+
+    ///在这个连接上分配一个新的stream, 连接已关闭时返回 `None`
+    pub fn open_stream(self: &Arc<Self>) -> Option<MuxStream> {
+        if self.is_closed() {
+            return None;
+        }
+        let stream_id = self.next_stream_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = mpsc::unbounded_channel();
+        let send_window = Arc::new(SendWindow::default());
+        let stream_handle = StreamHandle {
+            tx,
+            send_window: send_window.clone(),
+        };
+        self.streams
+            .lock()
+            .unwrap()
+            .insert(stream_id, stream_handle);
+        Some(MuxStream::new(stream_id, self.clone(), rx, send_window))
+    }
+
+    ///stream结束时调用, 移除stream并通知服务端断开remote
+    pub fn close_stream(&self, stream_id: u32, send_disconn: bool) {
+        let is_empty = {
+            let mut streams = self.streams.lock().unwrap();
+            streams.remove(&stream_id);
+            streams.is_empty()
+        };
+        if send_disconn {
+            let msg_bytes: Bytes = ClientMessage::DisConn(stream_id).into();
+            self.try_send(Message::Binary(msg_bytes.to_vec()));
+        }
+        if is_empty && !self.keep_idle {
+            self.close();
+        }
+    }
+
+    ///发送客户端消息
+    pub async fn send_message(&self, message: ClientMessage) -> Result<(), SessionClosedError> {
+        let msg_bytes: Bytes = message.into();
+        self.msg_tx
+            .send(Message::Binary(msg_bytes.to_vec()))
+            .await
+            .map_err(|_| SessionClosedError)
+    }
+
+    ///发送ping, 用于保持连接和检测连接状态
+    pub fn ping(&self) {
+        self.try_send(Message::Ping(vec![6, 6, 6]));
+    }
+
+    ///关闭连接, 已经打开的stream会收到连接关闭的错误
+    pub fn close(&self) {
+        if !self.closed.swap(true, Ordering::AcqRel) {
+            self.try_send(Message::Close(None));
+        }
+    }
+
+    ///在同步代码中发送消息, channel已满时转为异步发送
+    fn try_send(&self, message: Message) {
+        if let Err(TrySendError::Full(message)) = self.msg_tx.try_send(message) {
+            let msg_tx = self.msg_tx.clone();
+            tokio::spawn(async move { msg_tx.send(message).await });
+        }
+    }
+
+    ///把服务端消息分发给对应的stream, stream已经结束时丢弃消息
+    fn dispatch_message(&self, message: ServerMessage) {
+        let stream_id = message.stream_id();
+        let mut streams = self.streams.lock().unwrap();
+        let stream_handle = match streams.get(&stream_id) {
+            Some(s) => s,
+            None => return,
+        };
+        if let ServerMessage::WindowUpdate(_, update_msg) = message {
+            if let Err(e) = stream_handle.send_window.release(update_msg.0) {
+                //重置stream, drop发送端之后stream会收到连接关闭的错误
+                log::warn!("stream #{stream_id} {e}");
+                streams.remove(&stream_id);
+                drop(streams);
+                let msg_bytes: Bytes = ClientMessage::DisConn(stream_id).into();
+                self.try_send(Message::Binary(msg_bytes.to_vec()));
+            }
+        } else {
+            _ = stream_handle.tx.send(message);
+        }
+    }
+
+    ///将消息写入websocket
+    async fn run_writer(
+        session: Arc<Self>,
+        mut writer: SplitSink<WsStream, Message>,
+        mut msg_rx: Receiver<Message>,
+    ) {
+        while let Some(message) = msg_rx.recv().await {
+            let is_close = matches!(message, Message::Close(_));
+            if let Err(e) = writer.send(message).await {
+                log::error!("write ws conn failed: {e}");
+                session.close();
+                break;
+            }
+            if is_close {
+                break;
+            }
+        }
+    }
+
+    ///读取服务端消息, 按照stream id分发
+    async fn run_reader(session: Arc<Self>, mut reader: SplitStream<WsStream>) {
+        while let Some(message_result) = reader.next().await {
+            let message = match message_result {
+                Ok(s) => s,
+                Err(e) => {
+                    if !session.is_closed() {
+                        log::error!("read ws conn failed: {e}");
+                    }
+                    break;
+                }
+            };
+            *session.last_active.lock().unwrap() = Instant::now();
+            if let Message::Binary(data) = message {
+                let server_message = match ServerMessage::try_from(Bytes::from(data)) {
+                    Ok(s) => s,
+                    Err(e) => {
+                        log::error!("parse server message failed: {e}");
+                        break;
+                    }
+                };
+                session.dispatch_message(server_message);
+            }
+        }
+        session.close();
+        //drop所有发送端, stream会收到连接关闭的错误
+        session.streams.lock().unwrap().clear();
+    }
+}
@@ -0,0 +1,146 @@
+use super::{MuxSession, SessionClosedError};
+use crate::common::msg::{
+    client::ProxyRequest, server::ProxyResponseResult, ClientMessage, ServerMessage, WindowUpdate,
+};
+use crate::services::flow_window::{RecvWindow, SendWindow};
+use bytes::Bytes;
+use std::{net::SocketAddr, sync::Arc};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+///每条请求消息的最大数据长度
+const MAX_REQUEST_SIZE: usize = 16 * 1024;
+
+///websocket连接上的一个stream, 对应服务端的一个远端连接
+pub struct MuxStream {
+    stream_id: u32,
+    session: Arc<MuxSession>,
+    rx: UnboundedReceiver<ServerMessage>,
+    ///向服务端发送数据的窗口
+    send_window: Arc<SendWindow>,
+    ///接收服务端数据的窗口
+    recv_window: RecvWindow,
+    ///已经通知服务端断开
+    disconn_sent: bool,
+}
+
+///stream的写入端
+pub struct MuxStreamWriter<'a> {
+    stream_id: u32,
+    session: &'a MuxSession,
+    send_window: &'a SendWindow,
+    disconn_sent: &'a mut bool,
+}
+
+///stream的读取端
+pub struct MuxStreamReader<'a> {
+    stream_id: u32,
+    session: &'a MuxSession,
+    rx: &'a mut UnboundedReceiver<ServerMessage>,
+    recv_window: &'a mut RecvWindow,
+}
+
+impl MuxStream {
+    pub fn new(
+        stream_id: u32,
+        session: Arc<MuxSession>,
+        rx: UnboundedReceiver<ServerMessage>,
+        send_window: Arc<SendWindow>,
+    ) -> Self {
+        Self {
+            stream_id,
+            session,
+            rx,
+            send_window,
+            recv_window: RecvWindow::default(),
+            disconn_sent: false,
+        }
+    }
+
+    pub fn stream_id(&self) -> u32 {
+        self.stream_id
+    }
+
+    ///服务端的地址
+    pub fn server_addr(&self) -> Option<SocketAddr> {
+        self.session.server_addr()
+    }
+
+    ///发送客户端消息
+    pub async fn send_message(&self, message: ClientMessage) -> Result<(), SessionClosedError> {
+        self.session.send_message(message).await
+    }
+
+    ///接收这个stream的服务端消息, 连接关闭时返回 `None`
+    pub async fn recv(&mut self) -> Option<ServerMessage> {
+        self.rx.recv().await
+    }
+
+    ///分割为writer和reader
+    pub fn split(&mut self) -> (MuxStreamWriter<'_>, MuxStreamReader<'_>) {
+        (
+            MuxStreamWriter {
+                stream_id: self.stream_id,
+                session: &self.session,
+                send_window: &self.send_window,
+                disconn_sent: &mut self.disconn_sent,
+            },
+            MuxStreamReader {
+                stream_id: self.stream_id,
+                session: &self.session,
+                rx: &mut self.rx,
+                recv_window: &mut self.recv_window,
+            },
+        )
+    }
+}
+
+impl Drop for MuxStream {
+    fn drop(&mut self) {
+        self.session
+            .close_stream(self.stream_id, !self.disconn_sent);
+    }
+}
+
+impl MuxStreamWriter<'_> {
+    ///写入请求数据, 发送窗口用完时等待服务端的窗口更新
+    pub async fn write_data(&mut self, mut data: Bytes) -> Result<(), SessionClosedError> {
+        while !data.is_empty() {
+            let chunk = data.split_to(data.len().min(MAX_REQUEST_SIZE));
+            self.send_window
+                .acquire(chunk.len())
+                .await
+                .map_err(|_| SessionClosedError)?;
+            let request_msg = ClientMessage::Request(self.stream_id, ProxyRequest(chunk));
+            self.session.send_message(request_msg).await?;
+        }
+        Ok(())
+    }
+
+    ///通知服务端断开remote
+    pub async fn disconn(&mut self) -> Result<(), SessionClosedError> {
+        *self.disconn_sent = true;
+        self.session
+            .send_message(ClientMessage::DisConn(self.stream_id))
+            .await
+    }
+}
+
+impl MuxStreamReader<'_> {
+    ///接收服务端消息, 连接关闭时返回 `None`
+    ///
+    ///取出的响应数据计入接收窗口, 必要时通知服务端继续发送
+    pub async fn recv(&mut self) -> Result<Option<ServerMessage>, SessionClosedError> {
+        let message = match self.rx.recv().await {
+            Some(s) => s,
+            None => return Ok(None),
+        };
+        if let ServerMessage::ResponseResult(_, ProxyResponseResult::Ok(data)) = &message {
+            if let Some(update_size) = self.recv_window.consume(data.len()) {
+                let update_msg =
+                    ClientMessage::WindowUpdate(self.stream_id, WindowUpdate(update_size));
+                self.session.send_message(update_msg).await?;
+            }
+        }
+        Ok(Some(message))
+    }
+}
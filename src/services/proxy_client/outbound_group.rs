@@ -0,0 +1,173 @@
+use crate::common::{GroupStrategy, OutboundGroupConfig};
+use std::{
+    cmp::Reverse,
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+///默认的健康检查间隔
+const DEFAULT_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+///一组服务端, 根据健康检查的结果和策略选择使用的服务端
+pub struct OutboundGroup {
+    pub tag: String,
+    strategy: GroupStrategy,
+    ///分组中的outbound的tag
+    members: Vec<String>,
+    ///每个outbound最近一次检查的延迟, `None` 表示不可用
+    latency_list: Mutex<Vec<Option<Duration>>>,
+    ///轮询使用的计数
+    next_index: AtomicUsize,
+    pub check_interval: Duration,
+    pub probe_dest: Option<String>,
+}
+
+impl From<&OutboundGroupConfig> for OutboundGroup {
+    fn from(config: &OutboundGroupConfig) -> Self {
+        let check_interval = match config.check_interval {
+            Some(s) if s > 0 => Duration::from_secs(s),
+            _ => DEFAULT_CHECK_INTERVAL,
+        };
+        Self {
+            tag: config.tag.to_string(),
+            strategy: config.strategy,
+            members: config.outbounds.clone(),
+            //第一次检查之前认为所有的outbound都可用
+            latency_list: Mutex::new(vec![Some(Duration::ZERO); config.outbounds.len()]),
+            next_index: AtomicUsize::new(0),
+            check_interval,
+            probe_dest: config.probe_dest.to_owned(),
+        }
+    }
+}
+
+impl OutboundGroup {
+    ///分组中的outbound的tag
+    pub fn members(&self) -> &[String] {
+        &self.members
+    }
+
+    ///记录一个outbound的检查结果, `None` 表示不可用
+    pub fn set_latency(&self, member: &str, latency: Option<Duration>) {
+        let mut latency_list = self.latency_list.lock().unwrap();
+        for (index, tag) in self.members.iter().enumerate() {
+            if tag == member {
+                latency_list[index] = latency;
+            }
+        }
+    }
+
+    ///按照策略对outbound排序, 第一个是优先使用的outbound, 后面的用于连接失败时依次尝试
+    ///
+    ///健康检查标记为不可用的outbound不会被选择, 所有的outbound都不可用时按照配置的顺序全部尝试
+    pub fn select(&self, conn_dest: &str) -> Vec<&str> {
+        let latency_list = self.latency_list.lock().unwrap().clone();
+        let mut available: Vec<usize> = (0..self.members.len())
+            .filter(|index| latency_list[*index].is_some())
+            .collect();
+        let available_count = available.len();
+        if available_count == 0 {
+            return self.members.iter().map(String::as_str).collect();
+        }
+        match self.strategy {
+            GroupStrategy::UrlTest => {
+                available.sort_by_key(|index| latency_list[*index]);
+            }
+            GroupStrategy::Fallback => (),
+            GroupStrategy::RoundRobin => {
+                let offset = self.next_index.fetch_add(1, Ordering::Relaxed);
+                available.rotate_left(offset % available_count);
+            }
+            GroupStrategy::ConsistentHash => {
+                //rendezvous hash, 按照 `hash(host, tag)` 从大到小排序,
+                //一个outbound不可用时只有原来使用它的目标会改用其他的outbound
+                let host = dest_host(conn_dest);
+                available.sort_by_cached_key(|index| {
+                    Reverse(rendezvous_hash(host, &self.members[*index]))
+                });
+            }
+        }
+        available
+            .into_iter()
+            .map(|index| self.members[index].as_str())
+            .collect()
+    }
+}
+
+///目标和outbound组合的hash
+fn rendezvous_hash(host: &str, member: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    host.hash(&mut hasher);
+    member.hash(&mut hasher);
+    hasher.finish()
+}
+
+///目标地址(host:port)中的host, 同一个域名的不同端口使用相同的服务端
+fn dest_host(conn_dest: &str) -> &str {
+    match conn_dest.rsplit_once(':') {
+        Some((host, _)) => host,
+        None => conn_dest,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn consistent_hash_group(members: &[&str]) -> OutboundGroup {
+        OutboundGroup::from(&OutboundGroupConfig {
+            tag: "group".to_string(),
+            strategy: GroupStrategy::ConsistentHash,
+            outbounds: members.iter().map(|s| s.to_string()).collect(),
+            check_interval: None,
+            probe_dest: None,
+        })
+    }
+
+    ///每个目标使用的outbound
+    fn owners(group: &OutboundGroup, dest_list: &[String]) -> HashMap<String, String> {
+        dest_list
+            .iter()
+            .map(|dest| (dest.to_string(), group.select(dest)[0].to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn consistent_hash_remap_only_removed_member() {
+        let members = ["a", "b", "c", "d"];
+        let group = consistent_hash_group(&members);
+        let dest_list: Vec<String> = (0..1000).map(|i| format!("{i}.example.com:443")).collect();
+        let before = owners(&group, &dest_list);
+        //每个outbound都会被使用
+        for member in members {
+            assert!(before.values().any(|s| s == member));
+        }
+        group.set_latency("c", None);
+        let after = owners(&group, &dest_list);
+        for dest in &dest_list {
+            if before[dest] == "c" {
+                assert_ne!(after[dest], "c");
+            } else {
+                assert_eq!(after[dest], before[dest], "{dest}");
+            }
+        }
+        //恢复之后回到原来的outbound
+        group.set_latency("c", Some(Duration::from_millis(10)));
+        assert_eq!(owners(&group, &dest_list), before);
+    }
+
+    #[test]
+    fn consistent_hash_ignore_port() {
+        let group = consistent_hash_group(&["a", "b", "c"]);
+        assert_eq!(
+            group.select("example.com:80"),
+            group.select("example.com:443")
+        );
+    }
+}
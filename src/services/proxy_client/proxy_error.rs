@@ -16,11 +16,3 @@ pub enum ProxyError {
     #[error("parse request length failed: {0}")]
     ParseLength(#[from] ParseRequestError),
 }
-impl ProxyError {
-    pub fn is_ws_error(&self) -> bool {
-        match self {
-            Self::ConnIoErr(e) => e.is_ws_error(),
-            _ => false,
-        }
-    }
-}
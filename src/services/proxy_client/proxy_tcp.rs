@@ -11,9 +11,29 @@ pub async fn proxy_tcp(
     stream: &mut TcpStream,
 ) -> Result<(), ProxyError> {
     let (mut stream_reader, mut stream_writer) = stream.split();
+    proxy_io(
+        remote_conn_writer,
+        remote_conn_reader,
+        &mut stream_reader,
+        &mut stream_writer,
+    )
+    .await
+}
+
+///在任意的读写对象和远端连接之间转发数据
+pub async fn proxy_io<R, W>(
+    remote_conn_writer: &mut ConnWriter<'_>,
+    remote_conn_reader: &mut ConnReader<'_>,
+    stream_reader: &mut R,
+    stream_writer: &mut W,
+) -> Result<(), ProxyError>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
     tokio::select! {
-        request_result = read_request_loop(&mut stream_reader, remote_conn_writer)=>request_result,
-        response_result = read_response_loop(remote_conn_reader,&mut  stream_writer)=>response_result,
+        request_result = read_request_loop(stream_reader, remote_conn_writer)=>request_result,
+        response_result = read_response_loop(remote_conn_reader, stream_writer)=>response_result,
     }
 }
 
@@ -0,0 +1,7 @@
+mod fetch_rule_set;
+mod fetch_rule_set_error;
+mod refresh_rule_sets;
+
+pub use fetch_rule_set::fetch_rule_set;
+pub use fetch_rule_set_error::FetchRuleSetError;
+pub use refresh_rule_sets::refresh_rule_sets;
@@ -0,0 +1,407 @@
+use super::{
+    super::{
+        connection::RemoteConnection, proxy_error::ProxyError, proxy_tcp,
+        server_conn_manger::ServerConnManger,
+    },
+    FetchRuleSetError,
+};
+use crate::common::{RouteConfigCom, RouteContext, RouteNetwork};
+use http::Uri;
+use rustls::{ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName};
+use std::{io::ErrorKind, sync::Arc, time::Duration};
+use tokio::{
+    io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream},
+    time,
+};
+use tokio_rustls::TlsConnector;
+
+///下载的超时时间
+const FETCH_TIMEOUT: Duration = Duration::from_secs(60);
+///规则文件的最大长度
+const MAX_BODY_SIZE: usize = 64 * 1024 * 1024;
+///响应头最多包含的header个数
+const MAX_HEADERS: usize = 64;
+///最多跟随的重定向次数
+const MAX_REDIRECTS: usize = 5;
+///本地管道的缓冲区大小
+const PIPE_BUF_SIZE: usize = 64 * 1024;
+
+///一次http请求的结果
+enum FetchResponse {
+    Body(Vec<u8>),
+    ///重定向到新的url
+    Redirect(String),
+}
+
+///下载规则文件, 支持http和https
+///
+///与普通的tcp连接一样按照路由规则选择直连、服务端或者第三方代理, 重定向之后的地址重新匹配路由
+pub async fn fetch_rule_set(
+    url: &str,
+    conn_manger: &ServerConnManger,
+    route_config: &RouteConfigCom,
+) -> Result<Vec<u8>, FetchRuleSetError> {
+    let fetch_future = async {
+        let mut url = url.to_string();
+        for _ in 0..=MAX_REDIRECTS {
+            match fetch_once(&url, conn_manger, route_config).await? {
+                FetchResponse::Body(body) => return Ok(body),
+                FetchResponse::Redirect(location) => {
+                    log::info!("rule-set {url} redirect to {location}");
+                    url = location;
+                }
+            }
+        }
+        Err(FetchRuleSetError::TooManyRedirects)
+    };
+    time::timeout(FETCH_TIMEOUT, fetch_future)
+        .await
+        .map_err(|_| FetchRuleSetError::Timeout)?
+}
+
+///发送一次GET请求
+async fn fetch_once(
+    url: &str,
+    conn_manger: &ServerConnManger,
+    route_config: &RouteConfigCom,
+) -> Result<FetchResponse, FetchRuleSetError> {
+    let invalid_url = || FetchRuleSetError::InvalidUrl(url.to_string());
+    let uri: Uri = url.parse().map_err(|_| invalid_url())?;
+    let is_https = match uri.scheme_str() {
+        Some("http") => false,
+        Some("https") => true,
+        _ => return Err(invalid_url()),
+    };
+    let host = uri.host().ok_or_else(invalid_url)?;
+    let port = uri.port_u16().unwrap_or(if is_https { 443 } else { 80 });
+    let host_header = match uri.port() {
+        Some(_) => format!("{host}:{port}"),
+        None => host.to_string(),
+    };
+    let path = uri.path_and_query().map_or("/", |s| s.as_str());
+    let request = format!(
+        "GET {path} HTTP/1.1\r\nHost: {host_header}\r\nUser-Agent: liu-proxy\r\nAccept: */*\r\nConnection: close\r\n\r\n"
+    );
+    //rustls需要AsyncRead/AsyncWrite, 通过本地管道转发远程连接的数据
+    let route_context = RouteContext {
+        network: Some(RouteNetwork::Tcp),
+        ..Default::default()
+    };
+    let remote_conn = RemoteConnection::connect(
+        &format!("{host}:{port}"),
+        &route_context,
+        conn_manger,
+        route_config,
+    )
+    .await
+    .map_err(|e| FetchRuleSetError::Conn(Box::new(e)))?;
+    let (client_stream, pipe_stream) = io::duplex(PIPE_BUF_SIZE);
+    let request_future = async {
+        let mut client_stream = client_stream;
+        let response_data = if is_https {
+            //ipv6地址去掉中括号
+            let server_name = host.trim_start_matches('[').trim_end_matches(']');
+            let server_name = ServerName::try_from(server_name).map_err(|_| invalid_url())?;
+            let mut tls_stream = tls_connector().connect(server_name, client_stream).await?;
+            http_get(&mut tls_stream, &request).await?
+        } else {
+            http_get(&mut client_stream, &request).await?
+        };
+        parse_response(&response_data, &uri)
+    };
+    let (result, pipe_result) = tokio::join!(request_future, run_pipe(remote_conn, pipe_stream));
+    match (result, pipe_result) {
+        //连接出错时, 请求只会得到不完整的响应
+        (Err(_), Err(e)) => Err(FetchRuleSetError::Proxy(Box::new(e))),
+        (result, _) => result,
+    }
+}
+
+///在远程连接和本地管道之间转发数据, 任意一边断开时结束
+async fn run_pipe(
+    mut remote_conn: RemoteConnection,
+    pipe_stream: DuplexStream,
+) -> Result<(), ProxyError> {
+    let (mut remote_conn_writer, mut remote_conn_reader) = remote_conn.split();
+    let (mut pipe_reader, mut pipe_writer) = io::split(pipe_stream);
+    proxy_tcp::proxy_io(
+        &mut remote_conn_writer,
+        &mut remote_conn_reader,
+        &mut pipe_reader,
+        &mut pipe_writer,
+    )
+    .await
+}
+
+///使用webpki内置的根证书
+fn tls_connector() -> TlsConnector {
+    let mut root_store = RootCertStore::empty();
+    root_store.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(|ta| {
+        OwnedTrustAnchor::from_subject_spki_name_constraints(
+            ta.subject,
+            ta.spki,
+            ta.name_constraints,
+        )
+    }));
+    let config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(root_store)
+        .with_no_client_auth();
+    TlsConnector::from(Arc::new(config))
+}
+
+///发送请求, 读取完整的响应直到连接关闭
+async fn http_get<S>(stream: &mut S, request: &str) -> Result<Vec<u8>, FetchRuleSetError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    stream.write_all(request.as_bytes()).await?;
+    stream.flush().await?;
+    let mut response_data = Vec::new();
+    loop {
+        response_data.reserve(8192);
+        match stream.read_buf(&mut response_data).await {
+            Ok(0) => break,
+            Ok(_) => {
+                if response_data.len() > MAX_BODY_SIZE {
+                    return Err(FetchRuleSetError::TooLarge);
+                }
+            }
+            //有些https服务端不发送close_notify就断开连接, 由响应头判断数据是否完整
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(response_data)
+}
+
+///解析响应, 得到响应体或者重定向的url
+fn parse_response(response_data: &[u8], uri: &Uri) -> Result<FetchResponse, FetchRuleSetError> {
+    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    let mut response = httparse::Response::new(&mut headers);
+    let header_len = match response.parse(response_data) {
+        Ok(httparse::Status::Complete(s)) => s,
+        Ok(httparse::Status::Partial) => return Err(FetchRuleSetError::Incomplete),
+        Err(_) => return Err(FetchRuleSetError::InvalidResponse),
+    };
+    let code = response.code.ok_or(FetchRuleSetError::InvalidResponse)?;
+    let header_value = |name: &str| {
+        response
+            .headers
+            .iter()
+            .find(|header| header.name.eq_ignore_ascii_case(name))
+            .and_then(|header| std::str::from_utf8(header.value).ok())
+    };
+    if matches!(code, 301 | 302 | 303 | 307 | 308) {
+        let location = header_value("location").ok_or(FetchRuleSetError::InvalidResponse)?;
+        return Ok(FetchResponse::Redirect(resolve_location(location, uri)));
+    }
+    if code != 200 {
+        return Err(FetchRuleSetError::Status(code));
+    }
+    let body = &response_data[header_len..];
+    let is_chunked = header_value("transfer-encoding")
+        .is_some_and(|s| s.to_ascii_lowercase().contains("chunked"));
+    if is_chunked {
+        return decode_chunked(body).map(FetchResponse::Body);
+    }
+    match header_value("content-length") {
+        Some(content_length) => {
+            let content_length: usize = content_length
+                .trim()
+                .parse()
+                .map_err(|_| FetchRuleSetError::InvalidResponse)?;
+            if body.len() < content_length {
+                return Err(FetchRuleSetError::Incomplete);
+            }
+            Ok(FetchResponse::Body(body[..content_length].to_vec()))
+        }
+        None => Ok(FetchResponse::Body(body.to_vec())),
+    }
+}
+
+///重定向的地址可能是相对路径
+fn resolve_location(location: &str, uri: &Uri) -> String {
+    if location.contains("://") {
+        return location.to_string();
+    }
+    let scheme = uri.scheme_str().unwrap_or("http");
+    let authority = uri.authority().map_or("", |s| s.as_str());
+    if location.starts_with('/') {
+        format!("{scheme}://{authority}{location}")
+    } else {
+        let base_path = uri.path().rsplit_once('/').map_or("", |(s, _)| s);
+        format!("{scheme}://{authority}{base_path}/{location}")
+    }
+}
+
+///解码chunked编码的响应体
+fn decode_chunked(mut data: &[u8]) -> Result<Vec<u8>, FetchRuleSetError> {
+    let mut body = Vec::with_capacity(data.len());
+    loop {
+        let (pos, size) = match httparse::parse_chunk_size(data) {
+            Ok(httparse::Status::Complete(s)) => s,
+            Ok(httparse::Status::Partial) => return Err(FetchRuleSetError::Incomplete),
+            Err(_) => return Err(FetchRuleSetError::InvalidResponse),
+        };
+        data = &data[pos..];
+        if size == 0 {
+            return Ok(body);
+        }
+        //数据之后是 `\r\n`
+        let size = usize::try_from(size).map_err(|_| FetchRuleSetError::TooLarge)?;
+        if data.len() < size.saturating_add(2) {
+            return Err(FetchRuleSetError::Incomplete);
+        }
+        body.extend_from_slice(&data[..size]);
+        data = &data[size + 2..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(result: Result<FetchResponse, FetchRuleSetError>) -> Vec<u8> {
+        match result {
+            Ok(FetchResponse::Body(s)) => s,
+            Ok(FetchResponse::Redirect(s)) => panic!("unexpected redirect to {s}"),
+            Err(e) => panic!("unexpected error: {e}"),
+        }
+    }
+
+    fn redirect(response_data: &[u8], url: &str) -> String {
+        match parse_response(response_data, &url.parse().unwrap()) {
+            Ok(FetchResponse::Redirect(s)) => s,
+            Ok(FetchResponse::Body(_)) => panic!("unexpected body"),
+            Err(e) => panic!("unexpected error: {e}"),
+        }
+    }
+
+    #[test]
+    fn decode_chunked_body() {
+        let data = b"5\r\nhello\r\n7;ext=1\r\n, world\r\n0\r\n\r\n";
+        assert_eq!(decode_chunked(data).unwrap(), b"hello, world");
+        //最后一个chunk之后可以没有trailer和空行
+        assert_eq!(decode_chunked(b"0\r\n").unwrap(), b"");
+    }
+
+    #[test]
+    fn decode_truncated_chunk() {
+        let data = b"5\r\nhello\r\n7\r\n, world\r\n0\r\n";
+        //任意位置截断都不能得到不完整的数据
+        for len in 0..data.len() - 3 {
+            assert!(
+                matches!(
+                    decode_chunked(&data[..len]),
+                    Err(FetchRuleSetError::Incomplete)
+                ),
+                "{len}"
+            );
+        }
+    }
+
+    #[test]
+    fn decode_malformed_chunk_size() {
+        for data in [
+            &b"xyz\r\nhello\r\n0\r\n\r\n"[..],
+            b"-5\r\nhello\r\n0\r\n\r\n",
+            b"5 5\r\nhello\r\n0\r\n\r\n",
+            b"fffffffffffffffffffff\r\n",
+        ] {
+            assert!(
+                matches!(
+                    decode_chunked(data),
+                    Err(FetchRuleSetError::InvalidResponse)
+                ),
+                "{}",
+                String::from_utf8_lossy(data)
+            );
+        }
+        //声明的长度超过实际的数据
+        assert!(matches!(
+            decode_chunked(b"ffffffffffffffff\r\nhello\r\n"),
+            Err(FetchRuleSetError::Incomplete)
+        ));
+    }
+
+    #[test]
+    fn parse_chunked_response() {
+        let response_data =
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\n\r\n";
+        let uri = "http://example.com/a.txt".parse().unwrap();
+        assert_eq!(body(parse_response(response_data, &uri)), b"abc");
+    }
+
+    #[test]
+    fn parse_content_length_response() {
+        let uri = "http://example.com/a.txt".parse().unwrap();
+        let response_data = b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nabcdef";
+        assert_eq!(body(parse_response(response_data, &uri)), b"abc");
+        let response_data = b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nabc";
+        assert!(matches!(
+            parse_response(response_data, &uri),
+            Err(FetchRuleSetError::Incomplete)
+        ));
+        let response_data = b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n";
+        assert!(matches!(
+            parse_response(response_data, &uri),
+            Err(FetchRuleSetError::Status(404))
+        ));
+        assert!(matches!(
+            parse_response(b"HTTP/1.1 200 OK\r\nContent-", &uri),
+            Err(FetchRuleSetError::Incomplete)
+        ));
+    }
+
+    #[test]
+    fn redirect_location() {
+        let url = "https://example.com:8443/rules/a.txt?v=1";
+        let response = |location: &str| {
+            format!("HTTP/1.1 302 Found\r\nLocation: {location}\r\nContent-Length: 0\r\n\r\n")
+        };
+        assert_eq!(
+            redirect(response("http://cdn.example.net/b.txt").as_bytes(), url),
+            "http://cdn.example.net/b.txt"
+        );
+        assert_eq!(
+            redirect(response("/other/b.txt").as_bytes(), url),
+            "https://example.com:8443/other/b.txt"
+        );
+        assert_eq!(
+            redirect(response("b.txt?v=2").as_bytes(), url),
+            "https://example.com:8443/rules/b.txt?v=2"
+        );
+        assert_eq!(
+            redirect(response("b.txt").as_bytes(), "http://example.com"),
+            "http://example.com/b.txt"
+        );
+        //没有Location的重定向
+        let response_data = b"HTTP/1.1 301 Moved Permanently\r\n\r\n";
+        assert!(matches!(
+            parse_response(response_data, &url.parse().unwrap()),
+            Err(FetchRuleSetError::InvalidResponse)
+        ));
+    }
+
+    #[tokio::test]
+    async fn body_size_limit() {
+        let (mut client_stream, mut server_stream) = io::duplex(PIPE_BUF_SIZE);
+        let server_future = async move {
+            let mut buf = [0; 1024];
+            let _ = server_stream.read(&mut buf).await;
+            let header = b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n";
+            server_stream.write_all(header).await?;
+            //客户端超过限制之后断开, 写入失败
+            let chunk = vec![b'a'; PIPE_BUF_SIZE];
+            loop {
+                server_stream.write_all(&chunk).await?;
+            }
+        };
+        let server_task: tokio::task::JoinHandle<io::Result<()>> = tokio::spawn(server_future);
+        let result = http_get(&mut client_stream, "GET / HTTP/1.1\r\n\r\n").await;
+        assert!(matches!(result, Err(FetchRuleSetError::TooLarge)));
+        drop(client_stream);
+        assert!(server_task.await.unwrap().is_err());
+    }
+}
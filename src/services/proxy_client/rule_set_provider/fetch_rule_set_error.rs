@@ -0,0 +1,38 @@
+use super::super::{connection::ConnectionError, proxy_error::ProxyError};
+use std::io::Error as IoError;
+use thiserror::Error;
+
+///下载规则文件出现的错误
+#[derive(Error, Debug)]
+pub enum FetchRuleSetError {
+    ///url格式错误, 或者不是http/https
+    #[error("invalid url {0}")]
+    InvalidUrl(String),
+    ///连接远程地址失败, 包括被路由规则阻止
+    #[error("{0}")]
+    Conn(Box<ConnectionError>),
+    ///与远程地址之间转发数据失败
+    #[error("{0}")]
+    Proxy(Box<ProxyError>),
+    ///读写http(s)数据失败
+    #[error("{0}")]
+    Io(#[from] IoError),
+    ///http响应无法解析
+    #[error("invalid http response")]
+    InvalidResponse,
+    ///响应的数据不完整
+    #[error("incomplete http response")]
+    Incomplete,
+    ///状态码不是200
+    #[error("http status: {0}")]
+    Status(u16),
+    ///规则文件超过大小限制
+    #[error("response too large")]
+    TooLarge,
+    ///重定向次数过多
+    #[error("too many redirects")]
+    TooManyRedirects,
+    ///下载超时
+    #[error("fetch timeout")]
+    Timeout,
+}
@@ -0,0 +1,106 @@
+use super::{
+    super::{server_conn_manger::ServerConnManger, shared_route_config::SharedRouteConfig},
+    fetch_rule_set, FetchRuleSetError,
+};
+use crate::{
+    common::{RouteConfigCom, RuleSetConfig},
+    services::rule_set::{self, ParseRuleSetError},
+};
+use std::{
+    collections::HashMap,
+    path::Path,
+    time::{Duration, Instant, SystemTime},
+};
+use thiserror::Error;
+use tokio::{fs, time};
+
+///检查规则文件是否需要更新的间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+///默认的更新间隔
+const DEFAULT_REFRESH_INTERVAL: u64 = 86400;
+///下载失败之后重试的最小间隔
+const RETRY_INTERVAL: Duration = Duration::from_secs(300);
+
+///更新规则文件出现的错误
+#[derive(Error, Debug)]
+enum RefreshRuleSetError {
+    #[error("{0}")]
+    Fetch(#[from] FetchRuleSetError),
+    #[error("invalid content: {0}")]
+    Parse(#[from] ParseRuleSetError),
+    #[error("save file failed: {0}")]
+    Save(#[from] std::io::Error),
+}
+
+///定时下载设置了 `url` 的规则集
+///
+///文件不存在或者修改时间超过 `interval` 时下载, 下载的内容检查无误之后替换原来的文件,
+///监视路由配置的任务发现文件修改之后重新加载路由配置
+pub async fn refresh_rule_sets(
+    data_dir: &str,
+    shared_route_config: SharedRouteConfig,
+    conn_manger: ServerConnManger,
+) {
+    //每个规则集最近一次下载的时间
+    let mut last_fetch: HashMap<String, Instant> = HashMap::new();
+    let mut interval = time::interval(CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        let route_config = shared_route_config.load();
+        for rule_set in &route_config.rule_sets {
+            let url = match &rule_set.url {
+                Some(s) => s,
+                None => continue,
+            };
+            let path = rule_set.file_path(data_dir);
+            let refresh_interval =
+                Duration::from_secs(rule_set.interval.unwrap_or(DEFAULT_REFRESH_INTERVAL));
+            if !need_refresh(&path, refresh_interval).await {
+                continue;
+            }
+            if let Some(fetch_time) = last_fetch.get(&rule_set.tag) {
+                if fetch_time.elapsed() < RETRY_INTERVAL.min(refresh_interval) {
+                    continue;
+                }
+            }
+            last_fetch.insert(rule_set.tag.to_string(), Instant::now());
+            log::info!("fetch rule-set {} from {url}", rule_set.tag);
+            match refresh_rule_set(rule_set, url, &path, &conn_manger, &route_config).await {
+                Ok(_) => log::info!("rule-set {} saved to {}", rule_set.tag, path.display()),
+                Err(e) => log::error!("fetch rule-set {} failed: {e}", rule_set.tag),
+            }
+        }
+    }
+}
+
+///文件不存在或者修改时间超过更新间隔
+async fn need_refresh(path: &Path, refresh_interval: Duration) -> bool {
+    let modified = match fs::metadata(path).await.and_then(|s| s.modified()) {
+        Ok(s) => s,
+        Err(_) => return true,
+    };
+    match SystemTime::now().duration_since(modified) {
+        Ok(elapsed) => elapsed >= refresh_interval,
+        //修改时间在未来
+        Err(_) => false,
+    }
+}
+
+///下载规则文件, 先写入临时文件再替换, 避免加载到不完整的文件
+async fn refresh_rule_set(
+    rule_set: &RuleSetConfig,
+    url: &str,
+    path: &Path,
+    conn_manger: &ServerConnManger,
+    route_config: &RouteConfigCom,
+) -> Result<(), RefreshRuleSetError> {
+    let data = fetch_rule_set(url, conn_manger, route_config).await?;
+    rule_set::parse_rule_set(rule_set.rule_type, &String::from_utf8_lossy(&data))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    let tmp_path = format!("{}.tmp", path.display());
+    fs::write(&tmp_path, &data).await?;
+    fs::rename(&tmp_path, path).await?;
+    Ok(())
+}
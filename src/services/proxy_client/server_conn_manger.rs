@@ -1,185 +1,225 @@
-use crate::common::{ClientConfig, ParseWebsocketRequestError, WebsocketRequest};
-use futures_util::{
-    stream::{SplitSink, SplitStream},
-    SinkExt, StreamExt,
+use super::{mux::MuxStream, outbound_group::OutboundGroup, server_conn_pool::ServerConnPool};
+use crate::common::{ClientConfig, ClientError, UpstreamProxyConfig};
+use futures_util::future;
+use std::{
+    collections::{HashMap, HashSet},
+    io::{Error as IoError, ErrorKind},
+    sync::Arc,
+    time::Duration,
 };
-use std::{collections::VecDeque, sync::Arc, time::Duration};
-use tokio::{net::TcpStream, time::timeout};
-use tokio::{sync::Mutex, time};
-use tokio_tungstenite::{
-    tungstenite::{handshake::server::Response, Error as WsError, Message},
-    MaybeTlsStream, WebSocketStream,
-};
-pub type ConnPairReader = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
-pub type ConnPairWriter = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
-///一对连接
-pub type ConnPair = (ConnPairWriter, ConnPairReader);
+use tokio::time;
+use tokio_tungstenite::tungstenite::Error as WsError;
+
+///分组中每个服务端打开stream的超时时间, 超时后尝试下一个服务端
+const MEMBER_OPEN_TIMEOUT: Duration = Duration::from_secs(5);
 
-///服务端连接管理器
+///服务端连接管理器, 管理默认的服务端和 `outbounds` 中每个服务端的连接池
 #[derive(Clone)]
 pub struct ServerConnManger {
-    ws_request: Arc<WebsocketRequest>,
-    max_idle_conns: u32,
-    conn_pool: Arc<Mutex<VecDeque<ConnPair>>>,
+    ///`[client]` 中配置的服务端
+    default_pool: ServerConnPool,
+    ///tag => 连接池
+    pools: Arc<HashMap<String, ServerConnPool>>,
+    ///tag => 服务端分组
+    groups: Arc<HashMap<String, OutboundGroup>>,
+    ///tag => 第三方代理出口
+    forwards: Arc<HashMap<String, UpstreamProxyConfig>>,
 }
 
 impl ServerConnManger {
-    pub fn try_init(config: &ClientConfig) -> Result<Self, ParseWebsocketRequestError> {
-        let ws_request = WebsocketRequest::try_from(config)?;
-        let ws_request = Arc::new(ws_request);
-        let conn_list = if config.max_idle_conns > 0 {
-            VecDeque::with_capacity(config.max_idle_conns as usize)
-        } else {
-            VecDeque::new()
-        };
-        let conn_pool = Arc::new(Mutex::new(conn_list));
+    pub fn try_init(config: &ClientConfig) -> Result<Self, ClientError> {
+        let default_outbound = config.default_outbound();
+        let default_pool = ServerConnPool::try_init(&default_outbound)
+            .map_err(|e| ClientError::InitManger(default_outbound.tag, e))?;
+        let mut pools = HashMap::new();
+        for outbound in config.outbound_list() {
+            if outbound.tag == default_pool.tag() || pools.contains_key(&outbound.tag) {
+                return Err(ClientError::DuplicateOutbound(outbound.tag));
+            }
+            let pool = ServerConnPool::try_init(&outbound)
+                .map_err(|e| ClientError::InitManger(outbound.tag.to_string(), e))?;
+            pools.insert(outbound.tag, pool);
+        }
+        let mut groups = HashMap::new();
+        for group_config in config.outbound_groups.iter().flatten() {
+            let tag = &group_config.tag;
+            if tag == default_pool.tag() || pools.contains_key(tag) || groups.contains_key(tag) {
+                return Err(ClientError::DuplicateOutbound(tag.to_string()));
+            }
+            if group_config.outbounds.is_empty() {
+                return Err(ClientError::EmptyOutboundGroup(tag.to_string()));
+            }
+            //分组中只能使用outbound, 不能嵌套分组
+            for member in &group_config.outbounds {
+                if member != default_pool.tag() && !pools.contains_key(member) {
+                    return Err(ClientError::UnknownOutbound(member.to_string()));
+                }
+            }
+            groups.insert(tag.to_string(), OutboundGroup::from(group_config));
+        }
+        let mut forwards = HashMap::new();
+        for forward_config in config.forward_outbounds.iter().flatten() {
+            let tag = &forward_config.tag;
+            if forwards.contains_key(tag) {
+                return Err(ClientError::DuplicateOutbound(tag.to_string()));
+            }
+            forwards.insert(tag.to_string(), forward_config.proxy.clone());
+        }
         Ok(Self {
-            ws_request,
-            max_idle_conns: config.max_idle_conns,
-            conn_pool,
+            default_pool,
+            pools: Arc::new(pools),
+            groups: Arc::new(groups),
+            forwards: Arc::new(forwards),
         })
     }
 
-    ///处理客户端与服务端之间的握手操作
-    async fn auth_handshake(
-        &self,
-    ) -> Result<(WebSocketStream<MaybeTlsStream<TcpStream>>, Response), WsError> {
-        //tls connector
-        let connector = self.ws_request.ssl_connector.to_owned();
-        //建立tcp连接
-        //log::info!("tcp conn: {}", ws_request.server_addr);
-        let stream = TcpStream::connect(self.ws_request.server_addr)
-            .await
-            .map_err(WsError::Io)?;
-        //websocket握手
-        tokio_tungstenite::client_async_tls_with_config(
-            self.ws_request.as_ref(),
-            stream,
-            None,
-            connector,
-        )
-        .await
+    ///是否存在指定tag的outbound或者分组
+    pub fn contains_outbound(&self, tag: &str) -> bool {
+        self.pools.contains_key(tag) || self.groups.contains_key(tag)
     }
 
-    ///建立一个新的连接
-    async fn create_new_conn(&self) -> Result<ConnPair, WsError> {
-        let (stream, _) = self.auth_handshake().await?;
-        let conn_pair = stream.split();
-        Ok(conn_pair)
+    ///根据tag获取第三方代理出口的配置
+    pub fn get_forward(&self, tag: &str) -> Option<&UpstreamProxyConfig> {
+        self.forwards.get(tag)
     }
 
-    ///取出一个存在的连接
-    async fn fetch_exist_conn(&self) -> Option<ConnPair> {
-        let mut lock = self.conn_pool.lock().await;
-        /*log::info!(
-            "get_conn_pair(in lock): idle_conns={}, max_idle_conns={}",
-            lock.len(),
-            self.max_idle_conns
-        );*/
-        lock.pop_front()
+    ///根据tag获取outbound的连接池
+    fn get_pool(&self, tag: &str) -> Option<&ServerConnPool> {
+        if tag == self.default_pool.tag() {
+            Some(&self.default_pool)
+        } else {
+            self.pools.get(tag)
+        }
+    }
+
+    ///所有的连接池
+    fn all_pools(&self) -> impl Iterator<Item = (&str, &ServerConnPool)> {
+        let default_pool = (self.default_pool.tag(), &self.default_pool);
+        let pools = self.pools.iter().map(|(tag, pool)| (tag.as_str(), pool));
+        std::iter::once(default_pool).chain(pools)
     }
 
-    ///检测取出的连接,判断是否有效
-    async fn check_conn_status(&self, conn_pair: &mut ConnPair) -> Result<(), WsError> {
-        let ws_writer = &mut conn_pair.0;
-        //发送ping
-        let data = vec![6, 6, 6];
-        ws_writer.send(Message::Ping(data)).await?;
-        //等待pong
-        let ws_reader = &mut conn_pair.1;
-        while let Some(message_result) = ws_reader.next().await {
-            //log::info!("check1");
-            //可能收到上一轮的二进制消息(远端关闭比用户关闭快),或者pong
-            //dbg!(&message_result);
-            let message = message_result?;
-            if let Message::Pong(_) = message {
-                return Ok(());
+    ///连接所有的服务端,测试连通性
+    ///
+    ///分组中的服务端连接失败时只标记为不可用, 其它服务端连接失败时返回错误
+    pub async fn check_server_status(&self) -> Result<(), ClientError> {
+        let group_members: HashSet<&str> = self
+            .groups
+            .values()
+            .flat_map(|group| group.members().iter().map(String::as_str))
+            .collect();
+        let check_list = self.all_pools().map(|(tag, pool)| {
+            let is_member = group_members.contains(tag);
+            async move {
+                match pool.check_server_status().await {
+                    Ok(_) => Ok(()),
+                    Err(e) if is_member => {
+                        log::warn!("check server {tag} status failed: {e}");
+                        self.groups
+                            .values()
+                            .for_each(|group| group.set_latency(tag, None));
+                        Ok(())
+                    }
+                    Err(e) => Err(ClientError::CheckConn(tag.to_string(), Box::new(e))),
+                }
             }
-        }
-        //log::info!("check3");
-        Err(WsError::AlreadyClosed)
+        });
+        future::try_join_all(check_list).await?;
+        Ok(())
     }
 
-    ///取出连接
-    pub async fn get_conn_pair(&self) -> Result<ConnPair, WsError> {
-        if self.max_idle_conns == 0 {
-            //不使用连接池
-            return self.create_new_conn().await;
+    ///在指定的outbound上打开一个stream, `None` 表示默认的服务端
+    ///
+    ///`tag` 是分组时, 按照分组的策略选择服务端, 失败或者超时时依次尝试分组中的其它服务端
+    pub async fn open_stream(
+        &self,
+        tag: Option<&str>,
+        conn_dest: &str,
+    ) -> Result<MuxStream, WsError> {
+        let tag = match tag {
+            Some(s) => s,
+            None => return self.default_pool.open_stream().await,
+        };
+        if let Some(pool) = self.get_pool(tag) {
+            return pool.open_stream().await;
         }
-        //指定每次ping测试时间限制
-        let timeout_duration = Duration::from_millis(1800);
-        //取一个连接,如果连接不可用就新建连接(不尝试连接池剩余的)
-        if let Some(mut conn_pair) = self.fetch_exist_conn().await {
-            //判断取出的连接是否有效
-            let tm_check_result =
-                timeout(timeout_duration, self.check_conn_status(&mut conn_pair)).await;
-            //dbg!(&tm_check_result);
-            if let Ok(Ok(_)) = tm_check_result {
-                //log::info!("get_conn_pair(from pool)");
-                return Ok(conn_pair);
+        let group = match self.groups.get(tag) {
+            Some(s) => s,
+            None => {
+                return Err(WsError::Io(IoError::new(
+                    ErrorKind::NotFound,
+                    format!("outbound {tag} not found"),
+                )))
+            }
+        };
+        let mut last_error = WsError::AlreadyClosed;
+        for member in group.select(conn_dest) {
+            let pool = match self.get_pool(member) {
+                Some(s) => s,
+                None => continue,
+            };
+            let open_result = match time::timeout(MEMBER_OPEN_TIMEOUT, pool.open_stream()).await {
+                Ok(s) => s,
+                Err(_) => Err(WsError::Io(IoError::new(
+                    ErrorKind::TimedOut,
+                    "open stream timeout",
+                ))),
+            };
+            match open_result {
+                Ok(s) => return Ok(s),
+                Err(e) => {
+                    log::warn!("[{tag}]open stream on {member} failed: {e}");
+                    group.set_latency(member, None);
+                    last_error = e;
+                }
             }
         }
-        //log::info!("get_conn_pair(create new)");
-        self.create_new_conn().await
+        Err(last_error)
     }
 
-    async fn close_conn_pair(&self, conn: ConnPair) {
-        let mut ws_writer = conn.0;
-        if let Err(e) = ws_writer.close().await {
-            log::error!("close ws conn failed: {e}");
-        }
+    ///定时检测所有连接池中的连接状态
+    pub async fn scan_conn_pool(&self) {
+        let scan_list = self.all_pools().map(|(_, pool)| pool.scan_conn_pool());
+        future::join_all(scan_list).await;
     }
 
-    ///将空闲连接放回
-    pub async fn push_back_conn(&self, conn: ConnPair) {
-        //不使用连接池
-        if self.max_idle_conns == 0 {
-            self.close_conn_pair(conn).await;
-            return;
-        }
-        let mut old_conn_opt = None;
-        {
-            let mut lock = self.conn_pool.lock().await;
-            let idle_conns = lock.len();
-            //dbg!(idle_conns,self.max_idle_conns);
-            //空闲连接已满
-            if idle_conns >= (self.max_idle_conns as usize) {
-                //弹出前面最旧的连接
-                old_conn_opt = lock.pop_front();
-            }
-            //把新连接放到后面
-            lock.push_back(conn);
-        }
-        //关闭旧连接
-        if let Some(old_conn) = old_conn_opt {
-            self.close_conn_pair(old_conn).await;
+    ///定时检查每个分组中的服务端的延迟, 没有分组时一直等待
+    pub async fn check_groups(&self) {
+        if self.groups.is_empty() {
+            return future::pending().await;
         }
+        let check_list = self.groups.values().map(|group| self.check_group(group));
+        future::join_all(check_list).await;
     }
-    ///每隔一断时间检测一次空闲连接状态
-    pub async fn scan_conn_pool(&self) {
-        let mut interval = time::interval(Duration::from_secs(8));
+
+    ///定时检查一个分组
+    async fn check_group(&self, group: &OutboundGroup) {
+        let mut interval = time::interval(group.check_interval);
         loop {
             interval.tick().await;
-            //log::info!("scan start");
-            let conn_count = {
-                let lock = self.conn_pool.lock().await;
-                lock.len()
-            };
-            //log::info!("conn total count={conn_count}");
-            for _i in 0..conn_count {
-                //log::info!("scan {}/{conn_count}", i + 1);
-                if let Ok(conn) = self.get_conn_pair().await {
-                    self.push_back_conn(conn).await;
-                }
-            }
-            //log::info!("scan end");
+            let check_list = group.members().iter().filter_map(|member| {
+                let pool = self.get_pool(member)?;
+                Some(async move {
+                    let latency = match pool.check_latency(group.probe_dest.as_deref()).await {
+                        Ok(s) => {
+                            log::debug!("[{}]{member} latency {}ms", group.tag, s.as_millis());
+                            Some(s)
+                        }
+                        Err(e) => {
+                            log::warn!("[{}]check {member} failed: {e}", group.tag);
+                            None
+                        }
+                    };
+                    group.set_latency(member, latency);
+                })
+            });
+            future::join_all(check_list).await;
         }
     }
 
     ///释放所有连接
     pub async fn clear_conns(&self) {
-        while let Some(conn) = self.fetch_exist_conn().await {
-            self.close_conn_pair(conn).await;
-        }
+        let clear_list = self.all_pools().map(|(_, pool)| pool.clear_conns());
+        future::join_all(clear_list).await;
     }
 }
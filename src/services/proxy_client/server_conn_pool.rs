@@ -0,0 +1,217 @@
+use super::{
+    check_server_conn,
+    connection::ConnectionError,
+    mux::{MuxSession, MuxStream},
+    upstream_proxy,
+};
+use crate::common::{OutboundConfig, ParseWebsocketRequestError, WebsocketRequest};
+use std::{
+    io::{Error as IoError, ErrorKind},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::net::TcpStream;
+use tokio::{sync::Mutex, time};
+use tokio_tungstenite::{
+    tungstenite::{handshake::server::Response, Error as WsError},
+    MaybeTlsStream, WebSocketStream,
+};
+
+///每个连接默认最多承载的stream个数
+const DEFAULT_MAX_STREAMS_PER_CONN: u32 = 32;
+///检测连接状态的间隔
+const SCAN_INTERVAL: Duration = Duration::from_secs(8);
+///健康检查的超时时间
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+///建立连接的超时时间, 包括tcp连接、tls和websocket握手
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+///一个服务端的连接池
+#[derive(Clone)]
+pub struct ServerConnPool {
+    ///outbound的tag
+    tag: Arc<str>,
+    ws_request: Arc<WebsocketRequest>,
+    max_idle_conns: u32,
+    max_streams_per_conn: u32,
+    conn_pool: Arc<Mutex<Vec<Arc<MuxSession>>>>,
+    ///建立新连接时持有, 避免并发请求同时创建大量连接
+    dial_lock: Arc<Mutex<()>>,
+}
+
+impl ServerConnPool {
+    pub fn try_init(config: &OutboundConfig) -> Result<Self, ParseWebsocketRequestError> {
+        let ws_request = WebsocketRequest::try_from(config)?;
+        let ws_request = Arc::new(ws_request);
+        let max_streams_per_conn = match config.max_streams_per_conn {
+            Some(s) if s > 0 => s,
+            _ => DEFAULT_MAX_STREAMS_PER_CONN,
+        };
+        Ok(Self {
+            tag: Arc::from(config.tag.as_str()),
+            ws_request,
+            max_idle_conns: config.max_idle_conns.unwrap_or_default(),
+            max_streams_per_conn,
+            conn_pool: Arc::new(Mutex::new(Vec::new())),
+            dial_lock: Arc::new(Mutex::new(())),
+        })
+    }
+
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    ///处理客户端与服务端之间的握手操作
+    async fn auth_handshake(
+        &self,
+    ) -> Result<(WebSocketStream<MaybeTlsStream<TcpStream>>, Response), WsError> {
+        //tls connector
+        let connector = self.ws_request.ssl_connector.to_owned();
+        //建立tcp连接, 配置了前置代理时通过代理连接
+        //log::info!("tcp conn: {}", ws_request.server_addr);
+        let stream = match (&self.ws_request.upstream_proxy, self.ws_request.server_addr) {
+            (Some(proxy_config), _) => upstream_proxy::connect_upstream_proxy(
+                proxy_config,
+                &self.ws_request.server_host,
+                self.ws_request.server_port,
+            )
+            .await
+            .map_err(|e| WsError::Io(IoError::other(e)))?,
+            (None, Some(server_addr)) => {
+                TcpStream::connect(server_addr).await.map_err(WsError::Io)?
+            }
+            (None, None) => TcpStream::connect((
+                self.ws_request.server_host.as_str(),
+                self.ws_request.server_port,
+            ))
+            .await
+            .map_err(WsError::Io)?,
+        };
+        //websocket握手
+        tokio_tungstenite::client_async_tls_with_config(
+            self.ws_request.as_ref(),
+            stream,
+            None,
+            connector,
+        )
+        .await
+    }
+
+    ///建立一个新的连接
+    async fn create_new_conn(&self) -> Result<Arc<MuxSession>, WsError> {
+        let (stream, _) = time::timeout(CONNECT_TIMEOUT, self.auth_handshake())
+            .await
+            .map_err(|_| WsError::Io(IoError::new(ErrorKind::TimedOut, "connect timeout")))??;
+        Ok(MuxSession::new(
+            stream,
+            self.max_idle_conns > 0,
+            self.ws_request.server_addr,
+        ))
+    }
+
+    ///连接服务端,测试连通性,成功后连接留在连接池中
+    pub async fn check_server_status(&self) -> Result<(), WsError> {
+        let session = self.create_new_conn().await?;
+        self.conn_pool.lock().await.push(session);
+        Ok(())
+    }
+
+    ///建立一个新的连接检查服务端的延迟, 成功后连接留在连接池中
+    ///
+    ///设置了 `probe_dest` 时, 还要通过服务端连接这个地址
+    pub async fn check_latency(
+        &self,
+        probe_dest: Option<&str>,
+    ) -> Result<Duration, ConnectionError> {
+        let start_time = Instant::now();
+        let check_future = async {
+            let session = self
+                .create_new_conn()
+                .await
+                .map_err(ConnectionError::WsConn)?;
+            if let Some(probe_dest) = probe_dest {
+                let mut mux_stream = session.open_stream().ok_or(ConnectionError::ConnClosed)?;
+                check_server_conn::check_server_conn(&mut mux_stream, probe_dest)
+                    .await
+                    .map_err(ConnectionError::ServerConn)?;
+            }
+            Ok(session)
+        };
+        let session = time::timeout(CHECK_TIMEOUT, check_future)
+            .await
+            .map_err(|_| ConnectionError::CheckTimeout)??;
+        let latency = start_time.elapsed();
+        self.conn_pool.lock().await.push(session);
+        Ok(latency)
+    }
+
+    ///打开一个stream, 优先使用连接池中负载最小的连接
+    ///
+    ///建立新连接时不持有连接池的锁, 服务端无响应时不影响连接池中的其他连接和状态检测
+    pub async fn open_stream(&self) -> Result<MuxStream, WsError> {
+        if let Some(mux_stream) = self.open_pooled_stream().await {
+            return Ok(mux_stream);
+        }
+        let _dial_guard = self.dial_lock.lock().await;
+        //等待期间其他请求可能已经建立了新连接
+        if let Some(mux_stream) = self.open_pooled_stream().await {
+            return Ok(mux_stream);
+        }
+        let session = self.create_new_conn().await?;
+        let opt_mux_stream = session.open_stream();
+        self.conn_pool.lock().await.push(session);
+        opt_mux_stream.ok_or(WsError::AlreadyClosed)
+    }
+
+    ///在连接池中负载最小的连接上打开stream, 所有连接都已满时返回 `None`
+    async fn open_pooled_stream(&self) -> Option<MuxStream> {
+        let mut lock = self.conn_pool.lock().await;
+        lock.retain(|session| !session.is_closed());
+        lock.iter()
+            .map(|session| (session.stream_count(), session))
+            .filter(|(count, _)| *count < self.max_streams_per_conn as usize)
+            .min_by_key(|(count, _)| *count)
+            .and_then(|(_, session)| session.open_stream())
+    }
+
+    ///每隔一断时间检测一次连接状态
+    ///
+    ///移除已关闭、无响应的连接, 关闭多余的空闲连接, 并对其余连接发送ping
+    pub async fn scan_conn_pool(&self) {
+        let mut interval = time::interval(SCAN_INTERVAL);
+        loop {
+            interval.tick().await;
+            let mut lock = self.conn_pool.lock().await;
+            let mut idle_conns = 0;
+            lock.retain(|session| {
+                if session.is_closed() {
+                    return false;
+                }
+                //超过3轮没有收到pong
+                if session.idle_time() > SCAN_INTERVAL * 3 {
+                    log::warn!("[{}]ws conn not responding, close it", self.tag);
+                    session.close();
+                    return false;
+                }
+                if session.stream_count() == 0 {
+                    idle_conns += 1;
+                    //空闲连接已满
+                    if idle_conns > self.max_idle_conns {
+                        session.close();
+                        return false;
+                    }
+                }
+                session.ping();
+                true
+            });
+        }
+    }
+
+    ///释放所有连接
+    pub async fn clear_conns(&self) {
+        let mut lock = self.conn_pool.lock().await;
+        for session in lock.drain(..) {
+            session.close();
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use crate::common::RouteConfigCom;
+use std::sync::{Arc, RwLock};
+
+///可以在运行时替换的路由配置
+///
+///每个新连接使用 `load` 取得当时的配置, 替换之后已经建立的连接继续使用旧的配置
+#[derive(Clone)]
+pub struct SharedRouteConfig {
+    inner: Arc<RwLock<Arc<RouteConfigCom>>>,
+}
+
+impl SharedRouteConfig {
+    pub fn new(route_config: Arc<RouteConfigCom>) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(route_config)),
+        }
+    }
+
+    ///当前的路由配置
+    pub fn load(&self) -> Arc<RouteConfigCom> {
+        self.inner.read().unwrap().clone()
+    }
+
+    ///替换路由配置
+    pub fn store(&self, route_config: Arc<RouteConfigCom>) {
+        *self.inner.write().unwrap() = route_config;
+    }
+}
@@ -0,0 +1,88 @@
+mod http_host;
+mod tls_sni;
+
+use crate::common::{RouteConfigAction, RouteConfigCom, RouteContext};
+use std::{net::IpAddr, time::Duration};
+use tokio::{net::TcpStream, time};
+
+///等待客户端发送第一个数据包的超时时间, 超时说明是服务端先发送数据的协议
+const SNIFF_TIMEOUT: Duration = Duration::from_millis(300);
+///嗅探时最多读取的数据长度
+const SNIFF_BUFFER_SIZE: usize = 4096;
+
+///目标地址(host:port)中的host是否为ip地址, 只有这种情况才需要嗅探
+pub fn is_ip_dest(conn_dest: &str) -> bool {
+    match conn_dest.rsplit_once(':') {
+        Some((host, _)) => host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .is_ok(),
+        None => false,
+    }
+}
+
+///嗅探之前使用ip目标地址预先匹配路由, 返回是否会被拦截
+///
+///嗅探需要先回复客户端连接成功, 之后路由拦截或者连接失败时只能断开连接.
+///预先匹配可以让ip规则拦截的连接仍然收到失败的回复, 这样的连接不再嗅探
+pub async fn is_blocked_before_sniff(
+    conn_dest: &str,
+    route_context: &RouteContext,
+    route_config: &RouteConfigCom,
+) -> bool {
+    //fake-ip使用对应的域名匹配
+    let route_dest = route_config
+        .resolve_fake_dest(conn_dest)
+        .unwrap_or_else(|| conn_dest.to_string());
+    let t_action = route_config.match_action(&route_dest, route_context).await;
+    if t_action == RouteConfigAction::Block {
+        log::info!("[{t_action}]{conn_dest}");
+        true
+    } else {
+        false
+    }
+}
+
+///从客户端发送的第一个数据包中嗅探域名(TLS SNI 或者 HTTP Host)
+///
+///返回 `(路由使用的目标地址, 连接的目标地址)` ,只有目标是ip地址时才会嗅探,
+///`override_dest` 为 `true` 时使用嗅探到的域名连接
+pub async fn sniff_dest(
+    stream: &TcpStream,
+    conn_dest: &str,
+    override_dest: bool,
+) -> (String, String) {
+    let unchanged = (conn_dest.to_string(), conn_dest.to_string());
+    let port = match conn_dest.rsplit_once(':') {
+        Some((_, port)) if is_ip_dest(conn_dest) => port,
+        _ => return unchanged,
+    };
+    //只查看数据, 不从socket中取出
+    let mut buf = vec![0; SNIFF_BUFFER_SIZE];
+    let size = match time::timeout(SNIFF_TIMEOUT, stream.peek(&mut buf)).await {
+        Ok(Ok(s)) => s,
+        _ => return unchanged,
+    };
+    let data = &buf[..size];
+    let domain = match tls_sni::parse_sni(data).or_else(|| http_host::parse_host(data)) {
+        Some(s) => s,
+        None => return unchanged,
+    };
+    let sniffed_dest = format!("{domain}:{port}");
+    if override_dest {
+        (sniffed_dest.clone(), sniffed_dest)
+    } else {
+        (sniffed_dest, unchanged.1)
+    }
+}
+
+///检查嗅探到的域名, 排除ip地址和非法字符
+fn is_valid_domain(domain: &str) -> bool {
+    !domain.is_empty()
+        && domain.len() <= 253
+        && domain.parse::<IpAddr>().is_err()
+        && domain
+            .bytes()
+            .all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'.' || c == b'_')
+}
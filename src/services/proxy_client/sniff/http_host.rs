@@ -0,0 +1,85 @@
+use super::is_valid_domain;
+
+///从HTTP请求头中解析Host
+pub fn parse_host(data: &[u8]) -> Option<String> {
+    let text = std::str::from_utf8(data).ok()?;
+    let mut lines = text.split("\r\n");
+    //请求行: METHOD PATH HTTP/1.x
+    let request_line = lines.next()?;
+    let mut parts = request_line.split(' ');
+    let method = parts.next()?;
+    if method.is_empty() || !method.bytes().all(|c| c.is_ascii_uppercase()) {
+        return None;
+    }
+    parts.next()?;
+    if !parts.next()?.starts_with("HTTP/1.") {
+        return None;
+    }
+    for line in lines {
+        //请求头结束
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = match line.split_once(':') {
+            Some(s) => s,
+            None => continue,
+        };
+        if !name.trim().eq_ignore_ascii_case("host") {
+            continue;
+        }
+        let value = value.trim();
+        //去掉端口
+        let domain = match value.rsplit_once(':') {
+            Some((s, port)) if port.bytes().all(|c| c.is_ascii_digit()) => s,
+            _ => value,
+        };
+        let domain = domain.to_ascii_lowercase();
+        return is_valid_domain(&domain).then_some(domain);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_without_port() {
+        let data = b"GET / HTTP/1.1\r\nHost: Example.com\r\nAccept: */*\r\n\r\n";
+        assert_eq!(parse_host(data).as_deref(), Some("example.com"));
+    }
+
+    #[test]
+    fn host_with_port() {
+        let data = b"POST /a HTTP/1.1\r\nUser-Agent: x\r\nhost:  www.example.com:8080 \r\n\r\n";
+        assert_eq!(parse_host(data).as_deref(), Some("www.example.com"));
+    }
+
+    #[test]
+    fn host_is_ip() {
+        for host in ["1.2.3.4", "1.2.3.4:80", "[::1]", "[2001:db8::1]:8080"] {
+            let data = format!("GET / HTTP/1.1\r\nHost: {host}\r\n\r\n");
+            assert_eq!(parse_host(data.as_bytes()), None, "{host}");
+        }
+    }
+
+    #[test]
+    fn missing_host() {
+        assert_eq!(parse_host(b"GET / HTTP/1.1\r\nAccept: */*\r\n\r\n"), None);
+        //Host在请求头之后
+        assert_eq!(
+            parse_host(b"GET / HTTP/1.1\r\n\r\nHost: example.com\r\n\r\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn not_http_request() {
+        assert_eq!(parse_host(b"SSH-2.0-OpenSSH_9.0\r\n"), None);
+        assert_eq!(
+            parse_host(b"get / HTTP/1.1\r\nHost: example.com\r\n\r\n"),
+            None
+        );
+        assert_eq!(parse_host(&[0x16, 0x03, 0x01, 0xff, 0xfe]), None);
+    }
+}
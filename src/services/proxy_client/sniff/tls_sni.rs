@@ -0,0 +1,193 @@
+use super::is_valid_domain;
+
+//TLS记录和握手类型
+const CONTENT_TYPE_HANDSHAKE: u8 = 0x16;
+const HANDSHAKE_TYPE_CLIENT_HELLO: u8 = 0x01;
+const EXTENSION_SERVER_NAME: u16 = 0x0000;
+const SERVER_NAME_TYPE_HOST: u8 = 0x00;
+
+///按顺序读取数据, 越界时返回 `None`
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn read_bytes(&mut self, size: usize) -> Option<&'a [u8]> {
+        let end = self.pos.checked_add(size)?;
+        let bytes = self.data.get(self.pos..end)?;
+        self.pos = end;
+        Some(bytes)
+    }
+
+    fn read_u8(&mut self) -> Option<u8> {
+        self.read_bytes(1).map(|s| s[0])
+    }
+
+    fn read_u16(&mut self) -> Option<u16> {
+        self.read_bytes(2).map(|s| u16::from_be_bytes([s[0], s[1]]))
+    }
+
+    fn read_u24(&mut self) -> Option<usize> {
+        self.read_bytes(3)
+            .map(|s| ((s[0] as usize) << 16) | ((s[1] as usize) << 8) | s[2] as usize)
+    }
+}
+
+///从TLS ClientHello中解析SNI
+///
+///只解析第一个TLS记录, ClientHello被拆分到多个记录时返回 `None`
+pub fn parse_sni(data: &[u8]) -> Option<String> {
+    let mut reader = Reader { data, pos: 0 };
+    //record header: type(1) version(2) length(2)
+    if reader.read_u8()? != CONTENT_TYPE_HANDSHAKE {
+        return None;
+    }
+    reader.read_bytes(2)?;
+    let record_len = reader.read_u16()? as usize;
+    let record = reader.read_bytes(record_len.min(data.len() - reader.pos))?;
+    let mut reader = Reader {
+        data: record,
+        pos: 0,
+    };
+    //handshake header: type(1) length(3)
+    if reader.read_u8()? != HANDSHAKE_TYPE_CLIENT_HELLO {
+        return None;
+    }
+    reader.read_u24()?;
+    //version(2) random(32)
+    reader.read_bytes(34)?;
+    let session_id_len = reader.read_u8()? as usize;
+    reader.read_bytes(session_id_len)?;
+    let cipher_suites_len = reader.read_u16()? as usize;
+    reader.read_bytes(cipher_suites_len)?;
+    let compression_len = reader.read_u8()? as usize;
+    reader.read_bytes(compression_len)?;
+    let extensions_len = reader.read_u16()? as usize;
+    let mut extensions = Reader {
+        data: reader.read_bytes(extensions_len)?,
+        pos: 0,
+    };
+    while let Some(ext_type) = extensions.read_u16() {
+        let ext_len = extensions.read_u16()? as usize;
+        let ext_data = extensions.read_bytes(ext_len)?;
+        if ext_type != EXTENSION_SERVER_NAME {
+            continue;
+        }
+        let mut ext_reader = Reader {
+            data: ext_data,
+            pos: 0,
+        };
+        let list_len = ext_reader.read_u16()? as usize;
+        let mut list_reader = Reader {
+            data: ext_reader.read_bytes(list_len)?,
+            pos: 0,
+        };
+        while let Some(name_type) = list_reader.read_u8() {
+            let name_len = list_reader.read_u16()? as usize;
+            let name = list_reader.read_bytes(name_len)?;
+            if name_type != SERVER_NAME_TYPE_HOST {
+                continue;
+            }
+            let domain = std::str::from_utf8(name).ok()?.to_ascii_lowercase();
+            return is_valid_domain(&domain).then_some(domain);
+        }
+        return None;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///python ssl模块连接 `www.example.com` 时抓取的ClientHello
+    const CLIENT_HELLO: &[u8] = include_bytes!("testdata/client_hello.bin");
+
+    ///构造只包含指定扩展的ClientHello
+    fn build_client_hello(extensions: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let mut ext_data = Vec::new();
+        for (ext_type, data) in extensions {
+            ext_data.extend_from_slice(&ext_type.to_be_bytes());
+            ext_data.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            ext_data.extend_from_slice(data);
+        }
+        //version random session_id cipher_suites compression
+        let mut hello = vec![0x03, 0x03];
+        hello.extend_from_slice(&[0; 32]);
+        hello.extend_from_slice(&[0, 0, 2, 0x13, 0x01, 1, 0]);
+        hello.extend_from_slice(&(ext_data.len() as u16).to_be_bytes());
+        hello.extend_from_slice(&ext_data);
+        let mut handshake = vec![HANDSHAKE_TYPE_CLIENT_HELLO];
+        handshake.extend_from_slice(&(hello.len() as u32).to_be_bytes()[1..]);
+        handshake.extend_from_slice(&hello);
+        let mut record = vec![CONTENT_TYPE_HANDSHAKE, 0x03, 0x01];
+        record.extend_from_slice(&(handshake.len() as u16).to_be_bytes());
+        record.extend_from_slice(&handshake);
+        record
+    }
+
+    ///构造server_name扩展的数据
+    fn server_name_ext(name_type: u8, name: &[u8]) -> Vec<u8> {
+        let mut list = vec![name_type];
+        list.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        list.extend_from_slice(name);
+        let mut data = (list.len() as u16).to_be_bytes().to_vec();
+        data.extend_from_slice(&list);
+        data
+    }
+
+    #[test]
+    fn parse_captured_client_hello() {
+        assert_eq!(parse_sni(CLIENT_HELLO).as_deref(), Some("www.example.com"));
+    }
+
+    #[test]
+    fn truncated_client_hello() {
+        for size in 0..CLIENT_HELLO.len() {
+            assert_eq!(parse_sni(&CLIENT_HELLO[..size]), None, "size {size}");
+        }
+    }
+
+    #[test]
+    fn missing_server_name() {
+        let data = build_client_hello(&[(0x000a, vec![0, 2, 0, 0x1d])]);
+        assert_eq!(parse_sni(&data), None);
+        let data = build_client_hello(&[]);
+        assert_eq!(parse_sni(&data), None);
+    }
+
+    #[test]
+    fn server_name_after_other_extensions() {
+        let data = build_client_hello(&[
+            (0x000a, vec![0, 2, 0, 0x1d]),
+            (
+                EXTENSION_SERVER_NAME,
+                server_name_ext(SERVER_NAME_TYPE_HOST, b"Example.COM"),
+            ),
+        ]);
+        assert_eq!(parse_sni(&data).as_deref(), Some("example.com"));
+    }
+
+    #[test]
+    fn invalid_server_name() {
+        let data = build_client_hello(&[(
+            EXTENSION_SERVER_NAME,
+            server_name_ext(SERVER_NAME_TYPE_HOST, b"1.2.3.4"),
+        )]);
+        assert_eq!(parse_sni(&data), None);
+        let data = build_client_hello(&[(
+            EXTENSION_SERVER_NAME,
+            server_name_ext(SERVER_NAME_TYPE_HOST, b"bad/name"),
+        )]);
+        assert_eq!(parse_sni(&data), None);
+    }
+
+    #[test]
+    fn not_client_hello() {
+        assert_eq!(parse_sni(b"GET / HTTP/1.1\r\n\r\n"), None);
+        let mut data = CLIENT_HELLO.to_vec();
+        data[5] = 0x02;
+        assert_eq!(parse_sni(&data), None);
+    }
+}
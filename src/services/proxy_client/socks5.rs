@@ -1,3 +1,5 @@
+mod bind;
 pub mod handle_connection;
 mod proxy_handshake;
+mod udp_associate;
 mod write_handshake_response;
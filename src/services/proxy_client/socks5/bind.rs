@@ -0,0 +1,146 @@
+use super::{
+    super::{
+        check_server_conn, connection::ConnectionError, connection::RemoteConnection,
+        run_proxy_tcp_loop::run_proxy_tcp_loop, server_conn_manger::ServerConnManger,
+    },
+    write_handshake_response::write_handshake_response,
+};
+use crate::common::{
+    socks5::{ConnDest, ConnDestAddr},
+    RouteConfigAction, RouteConfigCom, RouteContext,
+};
+use std::{
+    io::{Error as IoError, ErrorKind},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    time,
+};
+
+///等待远端连接的超时时间
+const BIND_TIMEOUT: Duration = Duration::from_secs(60);
+
+///处理bind命令
+///
+///监听成功和远端连接成功时分别回复一次, 之后与connect命令一样转发数据
+pub async fn bind(
+    mut stream: TcpStream,
+    conn_dest: ConnDest,
+    route_context: RouteContext,
+    conn_manger: ServerConnManger,
+    route_config: Arc<RouteConfigCom>,
+) {
+    let dest_str = conn_dest.to_string();
+    let t_action = route_config.match_action(&dest_str, &route_context).await;
+    log::info!("[{t_action}]bind {dest_str}");
+    let bind_result = match t_action {
+        RouteConfigAction::Direct => bind_direct(&mut stream, &conn_dest).await,
+        RouteConfigAction::Proxy(outbound) => {
+            bind_server(&mut stream, &dest_str, outbound.as_deref(), &conn_manger).await
+        }
+        RouteConfigAction::Forward(_) => Err(ConnectionError::ForwardUnsupported("bind")),
+        RouteConfigAction::Block => Err(ConnectionError::RouteBlocked),
+    };
+    let remote_conn = match bind_result {
+        Ok(s) => s,
+        Err(e) => {
+            if !matches!(e, ConnectionError::RouteBlocked) {
+                log::error!("bind {dest_str} failed: {e}");
+            }
+            //socket 5 通知失败信息
+            if let Err(e1) = write_handshake_response(&mut stream, false, None).await {
+                log::error!("write socks5_response failed: {e1}");
+            }
+            return;
+        }
+    };
+    //proxy
+    if let Err(proxy_error) = run_proxy_tcp_loop(remote_conn, stream).await {
+        log::error!("{proxy_error}");
+    }
+}
+
+///在本地监听端口, 等待远端连接
+async fn bind_direct(
+    stream: &mut TcpStream,
+    conn_dest: &ConnDest,
+) -> Result<RemoteConnection, ConnectionError> {
+    let local_ip = stream.local_addr().map_err(ConnectionError::TcpConn)?.ip();
+    let unspecified_ip = match local_ip {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    let listener = TcpListener::bind((unspecified_ip, 0))
+        .await
+        .map_err(ConnectionError::TcpConn)?;
+    let listen_port = listener
+        .local_addr()
+        .map_err(ConnectionError::TcpConn)?
+        .port();
+    //第一次回复, 告诉socks5客户端监听的地址
+    let listen_addr = SocketAddr::new(local_ip, listen_port);
+    write_handshake_response(stream, true, Some(listen_addr.into()))
+        .await
+        .map_err(ConnectionError::TcpWrite)?;
+    //只接受预期的远端ip的连接
+    let expect_ip = match conn_dest.addr {
+        ConnDestAddr::Ip(ip) if !ip.is_unspecified() => Some(ip),
+        _ => None,
+    };
+    let accept_future = async {
+        loop {
+            let (tcp_conn, peer_addr) = listener.accept().await?;
+            match expect_ip {
+                Some(ip) if ip != peer_addr.ip() => log::warn!("bind drop {peer_addr}"),
+                _ => return Ok::<_, IoError>((tcp_conn, peer_addr)),
+            }
+        }
+    };
+    let (tcp_conn, peer_addr) = time::timeout(BIND_TIMEOUT, accept_future)
+        .await
+        .map_err(|_| ConnectionError::TcpConn(IoError::from(ErrorKind::TimedOut)))?
+        .map_err(ConnectionError::TcpConn)?;
+    //第二次回复, 告诉socks5客户端远端的地址
+    write_handshake_response(stream, true, Some(peer_addr.into()))
+        .await
+        .map_err(ConnectionError::TcpWrite)?;
+    Ok(RemoteConnection::from_direct(tcp_conn))
+}
+
+///由服务端监听端口, 等待远端连接
+async fn bind_server(
+    stream: &mut TcpStream,
+    dest_str: &str,
+    outbound: Option<&str>,
+    conn_manger: &ServerConnManger,
+) -> Result<RemoteConnection, ConnectionError> {
+    let mut mux_stream = conn_manger
+        .open_stream(outbound, dest_str)
+        .await
+        .map_err(ConnectionError::WsConn)?;
+    let mut listen_addr = check_server_conn::check_server_bind(&mut mux_stream, dest_str)
+        .await
+        .map_err(ConnectionError::ServerConn)?;
+    //服务端监听在所有地址上时, 使用服务端的地址代替
+    if let (ConnDestAddr::Ip(ip), Some(server_addr)) = (&listen_addr.addr, mux_stream.server_addr())
+    {
+        if ip.is_unspecified() {
+            listen_addr.addr = ConnDestAddr::Ip(server_addr.ip());
+        }
+    }
+    //第一次回复, 告诉socks5客户端服务端监听的地址
+    write_handshake_response(stream, true, Some(listen_addr))
+        .await
+        .map_err(ConnectionError::TcpWrite)?;
+    let peer_addr = check_server_conn::wait_bind_result(&mut mux_stream)
+        .await
+        .map_err(ConnectionError::ServerConn)?;
+    //第二次回复, 告诉socks5客户端远端的地址
+    write_handshake_response(stream, true, Some(peer_addr))
+        .await
+        .map_err(ConnectionError::TcpWrite)?;
+    Ok(RemoteConnection::from_server(mux_stream))
+}
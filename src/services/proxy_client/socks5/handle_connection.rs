@@ -1,10 +1,15 @@
 use super::{
-    super::{run_proxy_tcp_loop::run_proxy_tcp_loop, server_conn_manger::ServerConnManger},
+    super::{run_proxy_tcp_loop::run_proxy_tcp_loop, server_conn_manger::ServerConnManger, sniff},
+    bind::bind,
     proxy_handshake::proxy_handshake,
+    udp_associate::udp_associate,
     write_handshake_response::write_handshake_response,
 };
 use crate::{
-    common::RouteConfigCom,
+    common::{
+        socks5::{Command, ConnDest},
+        InboundConfig, RouteConfigCom, RouteContext, RouteNetwork,
+    },
     services::proxy_client::connection::{ConnectionError, RemoteConnection},
 };
 use std::{net::SocketAddr, sync::Arc};
@@ -14,41 +19,125 @@ use tokio::net::TcpStream;
 pub async fn handle_connection(
     mut stream: TcpStream,
     addr: SocketAddr,
+    inbound: Arc<InboundConfig>,
     conn_manger: ServerConnManger,
     route_config: Arc<RouteConfigCom>,
 ) {
-    //socks5初步握手,获取目标地址,端口
-    let conn_dest = match proxy_handshake(&mut stream).await {
+    //socks5初步握手,获取命令和目标地址,端口
+    let (command, conn_dest) = match proxy_handshake(&mut stream, inbound.local_users()).await {
         Ok(s) => s,
         Err(handshake_error) => {
             log::error!("socks5 handshake failed [{addr}]: {handshake_error}");
             return;
         }
     };
+    let network = match command {
+        Command::UdpAssociate => RouteNetwork::Udp,
+        _ => RouteNetwork::Tcp,
+    };
+    let route_context = RouteContext::new(network, &inbound, addr);
+    match command {
+        Command::UdpAssociate => {
+            udp_associate(stream, addr, route_context, conn_manger, route_config).await
+        }
+        Command::Bind => bind(stream, conn_dest, route_context, conn_manger, route_config).await,
+        Command::Connect if inbound.sniffing() && sniff::is_ip_dest(&conn_dest.to_string()) => {
+            connect_sniffed(
+                stream,
+                conn_dest,
+                &inbound,
+                route_context,
+                conn_manger,
+                route_config,
+            )
+            .await
+        }
+        Command::Connect => {
+            connect(stream, conn_dest, route_context, conn_manger, route_config).await
+        }
+    }
+}
+
+///处理connect命令
+async fn connect(
+    mut stream: TcpStream,
+    conn_dest: ConnDest,
+    route_context: RouteContext,
+    conn_manger: ServerConnManger,
+    route_config: Arc<RouteConfigCom>,
+) {
     let conn_dest = conn_dest.to_string();
-    let remote_conn = match RemoteConnection::connect(&conn_dest, &conn_manger, &route_config).await
+    let remote_conn =
+        match RemoteConnection::connect(&conn_dest, &route_context, &conn_manger, &route_config)
+            .await
+        {
+            Ok(s) => s,
+            Err(e) => {
+                if !matches!(e, ConnectionError::RouteBlocked) {
+                    log::error!("conn {conn_dest} failed: {e}");
+                }
+                //socket 5 通知失败信息
+                if let Err(e1) = write_handshake_response(&mut stream, false, None).await {
+                    log::error!("write socks5_response failed: {e1}");
+                }
+                return;
+            }
+        };
+    //socks5_response
+    if let Err(e) = write_handshake_response(&mut stream, true, None).await {
+        log::error!("write socks5_response failed: {e}");
+        return;
+    }
+    //proxy
+    if let Err(proxy_error) = run_proxy_tcp_loop(remote_conn, stream).await {
+        log::error!("{proxy_error}");
+    }
+}
+
+///处理connect命令, 先回复成功, 再根据客户端发送的第一个数据包嗅探域名
+///
+///ip规则拦截的连接在回复之前就会收到失败, 嗅探之后的路由拦截和连接失败只能断开连接
+async fn connect_sniffed(
+    mut stream: TcpStream,
+    conn_dest: ConnDest,
+    inbound: &InboundConfig,
+    route_context: RouteContext,
+    conn_manger: ServerConnManger,
+    route_config: Arc<RouteConfigCom>,
+) {
+    let conn_dest = conn_dest.to_string();
+    let is_blocked =
+        sniff::is_blocked_before_sniff(&conn_dest, &route_context, &route_config).await;
+    //socks5_response
+    if let Err(e) = write_handshake_response(&mut stream, !is_blocked, None).await {
+        log::error!("write socks5_response failed: {e}");
+        return;
+    }
+    if is_blocked {
+        return;
+    }
+    let (route_dest, conn_dest) =
+        sniff::sniff_dest(&stream, &conn_dest, inbound.sniff_override_dest()).await;
+    let remote_conn = match RemoteConnection::connect_with_route(
+        &conn_dest,
+        &route_dest,
+        &route_context,
+        &conn_manger,
+        &route_config,
+    )
+    .await
     {
         Ok(s) => s,
         Err(e) => {
+            //已经回复过成功, 只能断开连接
             if !matches!(e, ConnectionError::RouteBlocked) {
                 log::error!("conn {conn_dest} failed: {e}");
             }
-            //socket 5 通知失败信息
-            if let Err(e1) = write_handshake_response(&mut stream, false).await {
-                log::error!("write socks5_response failed: {e1}");
-            }
             return;
         }
     };
-    //socks5_response
-    if let Err(e) = write_handshake_response(&mut stream, true).await {
-        //回收连接
-        remote_conn.push_back_conn(&conn_manger).await;
-        log::error!("write socks5_response failed: {e}");
-        return;
-    }
     //proxy
-    if let Err(proxy_error) = run_proxy_tcp_loop(&conn_manger, remote_conn, stream).await {
+    if let Err(proxy_error) = run_proxy_tcp_loop(remote_conn, stream).await {
         log::error!("{proxy_error}");
     }
 }
@@ -1,11 +1,24 @@
-use crate::common::socks5::{self, ConnDest, ParseConnDestError};
-use std::io::Error as IoError;
+use crate::common::{
+    socks5::{self, Command, ConnDest, ParseConnDestError},
+    LocalUser,
+};
+use std::{io::Error as IoError, string::FromUtf8Error};
 use thiserror::Error;
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
     net::TcpStream,
 };
 
+//认证方式定义
+const METHOD_NO_AUTH: u8 = 0;
+const METHOD_PASSWORD: u8 = 2;
+const METHOD_NO_ACCEPTABLE: u8 = 0xFF;
+///用户名密码认证子协商的版本
+const PASSWORD_AUTH_VERSION: u8 = 1;
+//用户名密码认证结果
+const PASSWORD_AUTH_OK: u8 = 0;
+const PASSWORD_AUTH_FAILED: u8 = 1;
+
 #[derive(Error, Debug)]
 pub enum HandshakeError {
     #[error("invalid proxy version {0}")]
@@ -16,39 +29,94 @@ pub enum HandshakeError {
     IoErr(#[from] IoError),
     #[error("unsupported auth type")]
     UnsupportedAuthType,
+    #[error("invalid password auth version {0}")]
+    AuthVersion(u8),
+    #[error("invalid password auth data, {0}")]
+    AuthData(#[from] FromUtf8Error),
+    #[error("auth failed, user={0}")]
+    AuthFailed(String),
     #[error("unsupported cmd {0}")]
     UnsupportedCmd(u8),
     #[error("parse dest failed, {0}")]
     ParseDestError(#[from] ParseConnDestError),
 }
 
-///处理socks5握手,获取目标地址、端口
-pub async fn proxy_handshake(stream: &mut TcpStream) -> Result<ConnDest, HandshakeError> {
+///处理socks5握手,获取请求的命令和目标地址、端口
+///
+///`local_users` 不为空时, 要求客户端使用用户名密码认证
+pub async fn proxy_handshake(
+    stream: &mut TcpStream,
+    local_users: &[LocalUser],
+) -> Result<(Command, ConnDest), HandshakeError> {
     let methods = stream.read_u8().await?;
-    {
-        let mut buffer = vec![0; methods as usize];
-        stream.read_exact(&mut buffer).await?;
-        if methods == 0 {
-            return Err(HandshakeError::Methods(methods));
-        }
-        //客户端不支持无密码
-        if !buffer.as_slice().contains(&0) {
-            return Err(HandshakeError::UnsupportedAuthType);
-        }
+    let mut buffer = vec![0; methods as usize];
+    stream.read_exact(&mut buffer).await?;
+    if methods == 0 {
+        return Err(HandshakeError::Methods(methods));
+    }
+    let method = if local_users.is_empty() {
+        METHOD_NO_AUTH
+    } else {
+        METHOD_PASSWORD
+    };
+    //客户端不支持需要的认证方式
+    if !buffer.contains(&method) {
+        stream
+            .write_all(&[socks5::VERSION, METHOD_NO_ACCEPTABLE])
+            .await?;
+        return Err(HandshakeError::UnsupportedAuthType);
+    }
+    stream.write_all(&[socks5::VERSION, method]).await?;
+    if method == METHOD_PASSWORD {
+        password_auth(stream, local_users).await?;
     }
-    let response_data = 0x0500;
-    stream.write_u16(response_data).await?;
     //
     let version = stream.read_u8().await?;
     if version != socks5::VERSION {
         return Err(HandshakeError::Version(version));
     }
     let cmd = stream.read_u8().await?;
-    if cmd != 1 {
-        return Err(HandshakeError::UnsupportedCmd(cmd));
-    }
+    let command = Command::try_from(cmd).map_err(|_| HandshakeError::UnsupportedCmd(cmd))?;
     //rsv
     stream.read_u8().await?;
     let conn_dest = ConnDest::try_from_stream(stream).await?;
-    Ok(conn_dest)
+    Ok((command, conn_dest))
+}
+
+///用户名密码认证(RFC 1929)
+///
+/// ```text
+/// +-----+------+----------+------+----------+
+/// | VER | ULEN |  UNAME   | PLEN |  PASSWD  |
+/// +-----+------+----------+------+----------+
+/// |  1  |  1   | 1 to 255 |  1   | 1 to 255 |
+/// +-----+------+----------+------+----------+
+/// ```
+async fn password_auth(
+    stream: &mut TcpStream,
+    local_users: &[LocalUser],
+) -> Result<(), HandshakeError> {
+    let version = stream.read_u8().await?;
+    if version != PASSWORD_AUTH_VERSION {
+        return Err(HandshakeError::AuthVersion(version));
+    }
+    let user_len = stream.read_u8().await?;
+    let mut user_buffer = vec![0; user_len as usize];
+    stream.read_exact(&mut user_buffer).await?;
+    let password_len = stream.read_u8().await?;
+    let mut password_buffer = vec![0; password_len as usize];
+    stream.read_exact(&mut password_buffer).await?;
+    let user = String::from_utf8(user_buffer)?;
+    let password = String::from_utf8(password_buffer)?;
+    if !LocalUser::check(local_users, &user, &password) {
+        //认证失败后关闭连接
+        stream
+            .write_all(&[PASSWORD_AUTH_VERSION, PASSWORD_AUTH_FAILED])
+            .await?;
+        return Err(HandshakeError::AuthFailed(user));
+    }
+    stream
+        .write_all(&[PASSWORD_AUTH_VERSION, PASSWORD_AUTH_OK])
+        .await?;
+    Ok(())
 }
@@ -0,0 +1,482 @@
+use super::{
+    super::{check_server_conn, mux::MuxStream, server_conn_manger::ServerConnManger},
+    write_handshake_response::write_handshake_response,
+};
+use crate::{
+    common::{
+        msg::{ClientMessage, Datagram, ServerMessage},
+        socks5::{ConnDest, ConnDestAddr},
+        RouteConfigAction, RouteConfigCom, RouteContext,
+    },
+    services::relay_socket::RelaySocket,
+};
+use bytes::{BufMut, Bytes, BytesMut};
+use futures_util::future;
+use std::{
+    collections::HashMap,
+    io::Error as IoError,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+use tokio::{
+    io::AsyncReadExt,
+    net::{TcpStream, UdpSocket},
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+};
+
+///udp数据报的最大长度
+const MAX_DATAGRAM_SIZE: usize = 65535;
+///路由结果缓存的最大条数
+const MAX_ROUTE_CACHE_SIZE: usize = 1024;
+///解析域名或者打开stream期间最多暂存的数据报个数
+const MAX_PENDING_DATAGRAMS: usize = 16;
+
+///域名解析的结果, 目标地址 => 解析出的地址
+type ResolveResult = (String, Result<SocketAddr, IoError>);
+///打开stream的结果, outbound的tag => stream
+type OpenStreamResult = (Option<String>, Result<MuxStream, String>);
+
+///处理udp associate命令
+///
+///在接受tcp连接的地址上绑定udp端口, tcp连接断开时结束转发
+pub async fn udp_associate(
+    mut stream: TcpStream,
+    addr: SocketAddr,
+    route_context: RouteContext,
+    conn_manger: ServerConnManger,
+    route_config: Arc<RouteConfigCom>,
+) {
+    let bind_result = match stream.local_addr() {
+        Ok(local_addr) => UdpSocket::bind((local_addr.ip(), 0)).await,
+        Err(e) => Err(e),
+    };
+    let client_socket = match bind_result.and_then(|s| s.local_addr().map(|a| (s, a))) {
+        Ok(s) => s,
+        Err(e) => {
+            log::error!("socks5 udp bind failed [{addr}]: {e}");
+            if let Err(e1) = write_handshake_response(&mut stream, false, None).await {
+                log::error!("write socks5_response failed: {e1}");
+            }
+            return;
+        }
+    };
+    let (client_socket, bind_addr) = client_socket;
+    if let Err(e) = write_handshake_response(&mut stream, true, Some(bind_addr.into())).await {
+        log::error!("write socks5_response failed: {e}");
+        return;
+    }
+    log::info!("udp associate {bind_addr} for {addr}");
+    let (resolve_tx, resolve_rx) = mpsc::unbounded_channel();
+    let (open_tx, open_rx) = mpsc::unbounded_channel();
+    let mut udp_session = UdpSession {
+        client_socket,
+        peer_ip: addr.ip(),
+        client_addr: None,
+        conn_manger,
+        route_config,
+        route_context,
+        route_cache: HashMap::new(),
+        direct_socket: None,
+        resolve_tx,
+        open_tx,
+        mux_streams: HashMap::new(),
+    };
+    tokio::select! {
+        _ = wait_tcp_close(&mut stream) => (),
+        session_result = udp_session.run(resolve_rx, open_rx) => {
+            if let Err(e) = session_result {
+                log::error!("udp associate [{addr}] failed: {e}");
+            }
+        },
+    }
+}
+
+///等待socks5客户端断开tcp连接
+async fn wait_tcp_close(stream: &mut TcpStream) {
+    let mut buf = [0; 64];
+    while let Ok(n) = stream.read(&mut buf).await {
+        if n == 0 {
+            break;
+        }
+    }
+}
+
+///直连目标地址的解析状态
+enum DirectAddr {
+    ///正在解析, 暂存等待发送的数据报
+    Resolving(Vec<Bytes>),
+    Resolved(SocketAddr),
+}
+
+///通过服务端转发使用的stream
+enum ProxyStream {
+    ///正在打开, 暂存等待发送的数据报
+    Opening(Vec<Datagram>),
+    Opened(MuxStream),
+}
+
+///一个目标地址的路由结果
+struct RouteCacheEntry {
+    action: RouteConfigAction,
+    ///直连的目标为域名时, 解析出的地址
+    direct_addr: Option<DirectAddr>,
+}
+
+///一次udp associate的转发状态
+struct UdpSession {
+    ///接收socks5客户端数据报的socket
+    client_socket: UdpSocket,
+    ///socks5客户端的ip,只接受这个ip发来的数据报
+    peer_ip: IpAddr,
+    ///socks5客户端发送数据报的地址
+    client_addr: Option<SocketAddr>,
+    conn_manger: ServerConnManger,
+    route_config: Arc<RouteConfigCom>,
+    ///匹配路由使用的连接信息
+    route_context: RouteContext,
+    ///目标地址 => 路由结果
+    route_cache: HashMap<String, RouteCacheEntry>,
+    ///直连使用的socket
+    direct_socket: Option<RelaySocket>,
+    ///在单独的task中解析域名, 避免阻塞其他数据报
+    resolve_tx: UnboundedSender<ResolveResult>,
+    ///在单独的task中打开stream, 避免阻塞其他数据报
+    open_tx: UnboundedSender<OpenStreamResult>,
+    ///通过服务端转发使用的stream, outbound的tag => stream
+    mux_streams: HashMap<Option<String>, ProxyStream>,
+}
+
+impl UdpSession {
+    async fn run(
+        &mut self,
+        mut resolve_rx: UnboundedReceiver<ResolveResult>,
+        mut open_rx: UnboundedReceiver<OpenStreamResult>,
+    ) -> Result<(), IoError> {
+        let mut client_buf = vec![0; MAX_DATAGRAM_SIZE];
+        let mut direct_buf = vec![0; MAX_DATAGRAM_SIZE];
+        loop {
+            tokio::select! {
+                client_result = self.client_socket.recv_from(&mut client_buf) => {
+                    let (size, src_addr) = client_result?;
+                    self.process_client_datagram(&client_buf[..size], src_addr).await;
+                },
+                direct_result = recv_direct(&self.direct_socket, &mut direct_buf) => {
+                    let (size, src_addr) = direct_result?;
+                    let data = Bytes::copy_from_slice(&direct_buf[..size]);
+                    self.send_to_client(src_addr.into(), data).await?;
+                },
+                Some((dest_str, resolve_result)) = resolve_rx.recv() => {
+                    self.process_resolve_result(dest_str, resolve_result).await;
+                },
+                Some((outbound, open_result)) = open_rx.recv() => {
+                    self.process_open_result(outbound, open_result).await;
+                },
+                (outbound, server_message) = recv_server(&mut self.mux_streams) => match server_message {
+                    Some(ServerMessage::Datagram(_, datagram)) => {
+                        self.send_to_client(datagram.dest, datagram.data).await?;
+                    },
+                    Some(_) => (),
+                    //与服务端之间的连接已断开,下次发送时重新建立
+                    None => {
+                        self.mux_streams.remove(&outbound);
+                    },
+                },
+            }
+        }
+    }
+
+    ///处理socks5客户端发来的数据报
+    ///
+    /// ```text
+    /// +-----+------+------+----------+----------+----------+
+    /// | RSV | FRAG | ATYP | DST.ADDR | DST.PORT |   DATA   |
+    /// +-----+------+------+----------+----------+----------+
+    /// |  2  |  1   |  1   | Variable |    2     | Variable |
+    /// +-----+------+------+----------+----------+----------+
+    /// ```
+    async fn process_client_datagram(&mut self, buf: &[u8], src_addr: SocketAddr) {
+        if src_addr.ip() != self.peer_ip {
+            log::warn!("drop udp datagram from {src_addr}");
+            return;
+        }
+        self.client_addr = Some(src_addr);
+        //不支持分片
+        if buf.len() < 3 || buf[2] != 0 {
+            return;
+        }
+        let dest = match ConnDest::try_from_bytes(&buf[3..]) {
+            Ok(s) => s,
+            Err(e) => {
+                log::error!("parse udp datagram dest failed: {e}");
+                return;
+            }
+        };
+        let data = &buf[3 + dest.raw_data_len()..];
+        let dest = self.resolve_fake_dest(dest);
+        let dest_str = dest.to_string();
+        match self.match_action(&dest_str).await {
+            RouteConfigAction::Direct => {
+                if let Err(e) = self.send_direct(data, &dest, &dest_str).await {
+                    log::warn!("udp send to {dest_str} failed: {e}");
+                }
+            }
+            RouteConfigAction::Proxy(outbound) => {
+                let datagram = Datagram {
+                    dest,
+                    data: Bytes::copy_from_slice(data),
+                };
+                self.send_proxy(outbound, datagram).await;
+            }
+            //第三方代理出口不支持udp, 丢弃
+            RouteConfigAction::Forward(_) | RouteConfigAction::Block => (),
+        }
+    }
+
+    ///目标地址是fake-ip时, 使用对应的域名发送
+    fn resolve_fake_dest(&self, dest: ConnDest) -> ConnDest {
+        let fake_ip_pool = match &self.route_config.fake_ip_pool {
+            Some(s) => s,
+            None => return dest,
+        };
+        let domain = match &dest.addr {
+            ConnDestAddr::Ip(ip) => fake_ip_pool.lookup(ip),
+            ConnDestAddr::Domain(_) => None,
+        };
+        match domain {
+            Some(domain) => ConnDest {
+                addr: ConnDestAddr::Domain(domain),
+                port: dest.port,
+            },
+            None => dest,
+        }
+    }
+
+    ///匹配路由, 缓存每个目标地址的结果
+    async fn match_action(&mut self, dest_str: &str) -> RouteConfigAction {
+        if let Some(entry) = self.route_cache.get(dest_str) {
+            return entry.action.clone();
+        }
+        let t_action = self
+            .route_config
+            .match_action(dest_str, &self.route_context)
+            .await;
+        log::info!("[{t_action}]udp {dest_str}");
+        if let RouteConfigAction::Forward(tag) = &t_action {
+            log::warn!("forward outbound {tag} does not support udp, drop datagrams to {dest_str}");
+        }
+        if self.route_cache.len() >= MAX_ROUTE_CACHE_SIZE {
+            self.route_cache.clear();
+        }
+        let entry = RouteCacheEntry {
+            action: t_action.clone(),
+            direct_addr: None,
+        };
+        self.route_cache.insert(dest_str.to_string(), entry);
+        t_action
+    }
+
+    ///直连发送
+    ///
+    ///目标为域名时, 第一个数据报启动解析, 解析完成前的数据报先暂存, 解析结果缓存在路由结果中
+    async fn send_direct(
+        &mut self,
+        data: &[u8],
+        dest: &ConnDest,
+        dest_str: &str,
+    ) -> Result<(), IoError> {
+        if self.direct_socket.is_none() {
+            self.direct_socket = Some(RelaySocket::bind().await?);
+        }
+        let direct_socket = self.direct_socket.as_ref().unwrap();
+        let domain = match &dest.addr {
+            ConnDestAddr::Ip(ip) => {
+                return direct_socket
+                    .send_to_addr(data, SocketAddr::new(*ip, dest.port))
+                    .await
+            }
+            ConnDestAddr::Domain(domain) => domain,
+        };
+        let entry = match self.route_cache.get_mut(dest_str) {
+            Some(s) => s,
+            None => return Ok(()),
+        };
+        match &mut entry.direct_addr {
+            Some(DirectAddr::Resolved(target_addr)) => {
+                return direct_socket.send_to_addr(data, *target_addr).await;
+            }
+            Some(DirectAddr::Resolving(datagrams)) => {
+                if datagrams.len() < MAX_PENDING_DATAGRAMS {
+                    datagrams.push(Bytes::copy_from_slice(data));
+                }
+            }
+            None => {
+                entry.direct_addr = Some(DirectAddr::Resolving(vec![Bytes::copy_from_slice(data)]));
+                let (domain, port) = (domain.to_string(), dest.port);
+                let is_ipv6 = direct_socket.is_ipv6();
+                let dest_str = dest_str.to_string();
+                let resolve_tx = self.resolve_tx.clone();
+                tokio::spawn(async move {
+                    let resolve_result = RelaySocket::resolve(&domain, port, is_ipv6).await;
+                    _ = resolve_tx.send((dest_str, resolve_result));
+                });
+            }
+        }
+        Ok(())
+    }
+
+    ///域名解析完成, 发送暂存的数据报
+    async fn process_resolve_result(
+        &mut self,
+        dest_str: String,
+        resolve_result: Result<SocketAddr, IoError>,
+    ) {
+        //路由缓存可能已经清空
+        let entry = match self.route_cache.get_mut(&dest_str) {
+            Some(s) => s,
+            None => return,
+        };
+        let target_addr = match resolve_result {
+            Ok(s) => s,
+            Err(e) => {
+                //下一个数据报重新解析
+                entry.direct_addr = None;
+                log::warn!("udp send to {dest_str} failed: {e}");
+                return;
+            }
+        };
+        let datagrams = match entry.direct_addr.replace(DirectAddr::Resolved(target_addr)) {
+            Some(DirectAddr::Resolving(s)) => s,
+            _ => return,
+        };
+        if let Some(direct_socket) = &self.direct_socket {
+            for data in datagrams {
+                if let Err(e) = direct_socket.send_to_addr(&data, target_addr).await {
+                    log::warn!("udp send to {dest_str} failed: {e}");
+                }
+            }
+        }
+    }
+
+    ///通过服务端发送, 每个outbound使用单独的stream
+    ///
+    ///第一个数据报在单独的task中打开stream, 打开之前的数据报先暂存
+    async fn send_proxy(&mut self, outbound: Option<String>, datagram: Datagram) {
+        let mux_stream = match self.mux_streams.get_mut(&outbound) {
+            Some(ProxyStream::Opened(s)) => s,
+            Some(ProxyStream::Opening(datagrams)) => {
+                if datagrams.len() < MAX_PENDING_DATAGRAMS {
+                    datagrams.push(datagram);
+                }
+                return;
+            }
+            None => {
+                let dest_str = datagram.dest.to_string();
+                self.mux_streams
+                    .insert(outbound.clone(), ProxyStream::Opening(vec![datagram]));
+                let conn_manger = self.conn_manger.clone();
+                let open_tx = self.open_tx.clone();
+                tokio::spawn(async move {
+                    let open_result =
+                        open_mux_stream(&conn_manger, outbound.as_deref(), &dest_str).await;
+                    _ = open_tx.send((outbound, open_result));
+                });
+                return;
+            }
+        };
+        let message = ClientMessage::Datagram(mux_stream.stream_id(), datagram);
+        if mux_stream.send_message(message).await.is_err() {
+            self.mux_streams.remove(&outbound);
+        }
+    }
+
+    ///stream打开之后, 发送暂存的数据报
+    async fn process_open_result(
+        &mut self,
+        outbound: Option<String>,
+        open_result: Result<MuxStream, String>,
+    ) {
+        let mux_stream = match open_result {
+            Ok(s) => s,
+            Err(e) => {
+                //下一个数据报重新打开
+                self.mux_streams.remove(&outbound);
+                log::error!("udp associate with server failed: {e}");
+                return;
+            }
+        };
+        let datagrams = match self
+            .mux_streams
+            .insert(outbound.clone(), ProxyStream::Opened(mux_stream))
+        {
+            Some(ProxyStream::Opening(s)) => s,
+            _ => return,
+        };
+        for datagram in datagrams {
+            self.send_proxy(outbound.clone(), datagram).await;
+        }
+    }
+
+    ///把数据报加上socks5头部, 发给socks5客户端
+    async fn send_to_client(&self, src: ConnDest, data: Bytes) -> Result<(), IoError> {
+        let client_addr = match self.client_addr {
+            Some(s) => s,
+            None => return Ok(()),
+        };
+        let src_data = src.to_raw_data();
+        let mut buf = BytesMut::with_capacity(3 + src_data.len() + data.len());
+        buf.put_slice(&[0, 0, 0]);
+        buf.put_slice(&src_data);
+        buf.put_slice(&data);
+        self.client_socket.send_to(&buf, client_addr).await?;
+        Ok(())
+    }
+}
+
+///打开一个stream, 请求服务端建立udp转发
+///
+///`outbound` 是分组时, 使用第一个数据报的目标地址选择服务端
+async fn open_mux_stream(
+    conn_manger: &ServerConnManger,
+    outbound: Option<&str>,
+    dest_str: &str,
+) -> Result<MuxStream, String> {
+    let mut mux_stream = conn_manger
+        .open_stream(outbound, dest_str)
+        .await
+        .map_err(|e| e.to_string())?;
+    check_server_conn::check_server_udp_associate(&mut mux_stream)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(mux_stream)
+}
+
+///从直连socket接收数据报, 还没有创建socket时一直等待
+async fn recv_direct(
+    direct_socket: &Option<RelaySocket>,
+    buf: &mut [u8],
+) -> Result<(usize, SocketAddr), IoError> {
+    match direct_socket {
+        Some(s) => s.recv_from(buf).await,
+        None => future::pending().await,
+    }
+}
+
+///从所有已经打开的stream接收服务端消息, 返回消息来自的outbound, 没有打开的stream时一直等待
+async fn recv_server(
+    mux_streams: &mut HashMap<Option<String>, ProxyStream>,
+) -> (Option<String>, Option<ServerMessage>) {
+    let recv_list: Vec<_> = mux_streams
+        .iter_mut()
+        .filter_map(|(outbound, proxy_stream)| match proxy_stream {
+            ProxyStream::Opened(mux_stream) => Some(Box::pin(async move {
+                (outbound.clone(), mux_stream.recv().await)
+            })),
+            ProxyStream::Opening(_) => None,
+        })
+        .collect();
+    if recv_list.is_empty() {
+        return future::pending().await;
+    }
+    let (output, _, _) = future::select_all(recv_list).await;
+    output
+}
@@ -2,10 +2,18 @@ use crate::common::socks5::{ConnDest, VERSION};
 use std::io::Result as IoResult;
 use tokio::{io::AsyncWriteExt, net::TcpStream};
 
-pub async fn write_handshake_response(stream: &mut TcpStream, is_ok: bool) -> IoResult<()> {
+///写入socks5握手结果
+///
+///`bind_addr` 为服务器绑定的地址, udp associate时客户端需要向这个地址发送数据报,
+///bind时依次为监听的地址和远端连接的地址
+pub async fn write_handshake_response(
+    stream: &mut TcpStream,
+    is_ok: bool,
+    bind_addr: Option<ConnDest>,
+) -> IoResult<()> {
     let rep = if is_ok { 0 } else { 5 };
-    //构造目标ip和端口返回给连接者(实际无意义)
-    let target_dest = ConnDest::default();
+    //没有绑定地址时,构造目标ip和端口返回给连接者(实际无意义)
+    let target_dest = bind_addr.unwrap_or_default();
     let addr_raw_data = target_dest.to_raw_data();
     let mut socks5_response = Vec::with_capacity(3 + addr_raw_data.len());
     socks5_response.push(VERSION);
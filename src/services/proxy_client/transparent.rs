@@ -0,0 +1,6 @@
+mod bind_tproxy;
+pub mod handle_connection;
+mod is_local_ip;
+mod original_dst;
+
+pub use bind_tproxy::bind_tproxy;
@@ -0,0 +1,53 @@
+use std::io::Result as IoResult;
+use std::net::SocketAddr;
+use tokio::net::{TcpListener, TcpSocket};
+
+///监听队列长度
+const LISTEN_BACKLOG: u32 = 1024;
+
+///创建设置了 `IP_TRANSPARENT` 的监听socket, 用于接受TPROXY转发的连接
+///
+///需要 `CAP_NET_ADMIN` 权限
+pub fn bind_tproxy(addr: SocketAddr) -> IoResult<TcpListener> {
+    let socket = if addr.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    socket.set_reuseaddr(true)?;
+    set_ip_transparent(&socket, addr.is_ipv4())?;
+    socket.bind(addr)?;
+    socket.listen(LISTEN_BACKLOG)
+}
+
+#[cfg(target_os = "linux")]
+fn set_ip_transparent(socket: &TcpSocket, is_ipv4: bool) -> IoResult<()> {
+    use std::os::unix::io::AsRawFd;
+    let (level, name) = if is_ipv4 {
+        (libc::SOL_IP, libc::IP_TRANSPARENT)
+    } else {
+        (libc::SOL_IPV6, libc::IPV6_TRANSPARENT)
+    };
+    let enable: libc::c_int = 1;
+    let ret = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            level,
+            name,
+            &enable as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_ip_transparent(_socket: &TcpSocket, _is_ipv4: bool) -> IoResult<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "tproxy is only supported on linux",
+    ))
+}
@@ -0,0 +1,75 @@
+use super::{
+    super::{run_proxy_tcp_loop::run_proxy_tcp_loop, sniff},
+    is_local_ip::is_local_ip,
+    original_dst::original_dst,
+};
+use crate::{
+    common::{InboundConfig, InboundProtocol, RouteConfigCom, RouteContext, RouteNetwork},
+    services::proxy_client::{
+        connection::{ConnectionError, RemoteConnection},
+        server_conn_manger::ServerConnManger,
+    },
+};
+use std::{net::SocketAddr, sync::Arc};
+use tokio::net::TcpStream;
+
+///处理透明代理的连接
+///
+///REDIRECT模式从 `SO_ORIGINAL_DST` 获取目标地址, TPROXY模式的目标地址就是连接的本地地址
+pub async fn handle_connection(
+    stream: TcpStream,
+    addr: SocketAddr,
+    inbound: Arc<InboundConfig>,
+    listen_addr: SocketAddr,
+    conn_manger: ServerConnManger,
+    route_config: Arc<RouteConfigCom>,
+) {
+    let dst_result = match inbound.protocol {
+        InboundProtocol::Tproxy => stream.local_addr(),
+        _ => original_dst(&stream),
+    };
+    let dst_addr = match dst_result {
+        Ok(s) => s,
+        Err(e) => {
+            log::error!("get original dst failed [{addr}]: {e}");
+            return;
+        }
+    };
+    //直接连接监听端口时, 目标地址是本机的地址, 不能再连接自己
+    if dst_addr.port() == listen_addr.port() && is_local_ip(dst_addr.ip()) {
+        log::error!("connection from {addr} is not redirected");
+        return;
+    }
+    let (route_dest, conn_dest) = if inbound.sniffing() {
+        sniff::sniff_dest(
+            &stream,
+            &dst_addr.to_string(),
+            inbound.sniff_override_dest(),
+        )
+        .await
+    } else {
+        (dst_addr.to_string(), dst_addr.to_string())
+    };
+    let route_context = RouteContext::new(RouteNetwork::Tcp, &inbound, addr);
+    let remote_conn = match RemoteConnection::connect_with_route(
+        &conn_dest,
+        &route_dest,
+        &route_context,
+        &conn_manger,
+        &route_config,
+    )
+    .await
+    {
+        Ok(s) => s,
+        Err(e) => {
+            if !matches!(e, ConnectionError::RouteBlocked) {
+                log::error!("conn {conn_dest} failed: {e}");
+            }
+            return;
+        }
+    };
+    //proxy
+    if let Err(proxy_error) = run_proxy_tcp_loop(remote_conn, stream).await {
+        log::error!("{proxy_error}");
+    }
+}
@@ -0,0 +1,71 @@
+use std::net::IpAddr;
+
+///是否为本机的地址(回环地址或者网卡上的地址)
+///
+///发往其他主机相同端口的连接不受影响
+#[cfg(target_os = "linux")]
+pub fn is_local_ip(ip: IpAddr) -> bool {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    if ip.is_loopback() || ip.is_unspecified() {
+        return true;
+    }
+    let mut if_addrs: *mut libc::ifaddrs = std::ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut if_addrs) } != 0 {
+        log::error!("getifaddrs failed: {}", std::io::Error::last_os_error());
+        return false;
+    }
+    let mut found = false;
+    let mut if_addr = if_addrs;
+    while !if_addr.is_null() && !found {
+        let addr = unsafe { (*if_addr).ifa_addr };
+        if !addr.is_null() {
+            let if_ip = match unsafe { (*addr).sa_family } as libc::c_int {
+                libc::AF_INET => {
+                    let addr = unsafe { &*(addr as *const libc::sockaddr_in) };
+                    Some(IpAddr::V4(Ipv4Addr::from(u32::from_be(
+                        addr.sin_addr.s_addr,
+                    ))))
+                }
+                libc::AF_INET6 => {
+                    let addr = unsafe { &*(addr as *const libc::sockaddr_in6) };
+                    Some(IpAddr::V6(Ipv6Addr::from(addr.sin6_addr.s6_addr)))
+                }
+                _ => None,
+            };
+            found = if_ip == Some(ip);
+        }
+        if_addr = unsafe { (*if_addr).ifa_next };
+    }
+    unsafe { libc::freeifaddrs(if_addrs) };
+    found
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn is_local_ip(ip: IpAddr) -> bool {
+    ip.is_loopback() || ip.is_unspecified()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_and_remote_ip() {
+        assert!(is_local_ip("127.0.0.1".parse().unwrap()));
+        assert!(is_local_ip("::1".parse().unwrap()));
+        assert!(is_local_ip("0.0.0.0".parse().unwrap()));
+        assert!(!is_local_ip("203.0.113.1".parse().unwrap()));
+        assert!(!is_local_ip("2001:db8::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn interface_ip() {
+        //connect udp socket不会发送数据, 只是选择出口网卡的地址
+        let socket = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+        if socket.connect("203.0.113.1:9").is_err() {
+            //没有默认路由
+            return;
+        }
+        assert!(is_local_ip(socket.local_addr().unwrap().ip()));
+    }
+}
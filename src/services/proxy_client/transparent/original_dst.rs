@@ -0,0 +1,61 @@
+use std::io::Result as IoResult;
+use std::net::SocketAddr;
+use tokio::net::TcpStream;
+
+///获取被iptables REDIRECT之前的目标地址(`SO_ORIGINAL_DST`)
+#[cfg(target_os = "linux")]
+pub fn original_dst(stream: &TcpStream) -> IoResult<SocketAddr> {
+    use std::net::{Ipv4Addr, Ipv6Addr, SocketAddrV4, SocketAddrV6};
+    use std::os::unix::io::AsRawFd;
+    let (level, name) = if stream.local_addr()?.is_ipv4() {
+        (libc::SOL_IP, libc::SO_ORIGINAL_DST)
+    } else {
+        (libc::SOL_IPV6, libc::IP6T_SO_ORIGINAL_DST)
+    };
+    let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    let ret = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            level,
+            name,
+            &mut storage as *mut libc::sockaddr_storage as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let dst_addr = match storage.ss_family as libc::c_int {
+        libc::AF_INET => {
+            let addr = unsafe { &*(&storage as *const _ as *const libc::sockaddr_in) };
+            let ip = Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr));
+            SocketAddr::V4(SocketAddrV4::new(ip, u16::from_be(addr.sin_port)))
+        }
+        libc::AF_INET6 => {
+            let addr = unsafe { &*(&storage as *const _ as *const libc::sockaddr_in6) };
+            let ip = Ipv6Addr::from(addr.sin6_addr.s6_addr);
+            SocketAddr::V6(SocketAddrV6::new(
+                ip,
+                u16::from_be(addr.sin6_port),
+                addr.sin6_flowinfo,
+                addr.sin6_scope_id,
+            ))
+        }
+        family => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("invalid address family {family}"),
+            ))
+        }
+    };
+    Ok(dst_addr)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn original_dst(_stream: &TcpStream) -> IoResult<SocketAddr> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "redirect is only supported on linux",
+    ))
+}
@@ -0,0 +1,42 @@
+mod http_connect;
+mod socks5_connect;
+mod upstream_proxy_error;
+
+use crate::common::{UpstreamProxyConfig, UpstreamProxyProtocol};
+use tokio::net::TcpStream;
+pub use upstream_proxy_error::UpstreamProxyError;
+
+///通过前置代理建立到 `host:port` 的tcp隧道
+pub async fn connect_upstream_proxy(
+    config: &UpstreamProxyConfig,
+    host: &str,
+    port: u16,
+) -> Result<TcpStream, UpstreamProxyError> {
+    let mut stream = TcpStream::connect((config.address.as_str(), config.port))
+        .await
+        .map_err(UpstreamProxyError::Conn)?;
+    match config.protocol {
+        UpstreamProxyProtocol::Http => {
+            http_connect::http_connect(&mut stream, config, host, port).await?
+        }
+        UpstreamProxyProtocol::Socks5 => {
+            socks5_connect::socks5_connect(&mut stream, config, host, port).await?
+        }
+    }
+    Ok(stream)
+}
+
+///通过前置代理建立到目标地址(host:port)的tcp隧道
+pub async fn connect_upstream_proxy_dest(
+    config: &UpstreamProxyConfig,
+    conn_dest: &str,
+) -> Result<TcpStream, UpstreamProxyError> {
+    let (host, port) = match conn_dest.rsplit_once(':') {
+        Some((host, port)) => match port.parse() {
+            Ok(port) => (host.trim_start_matches('[').trim_end_matches(']'), port),
+            Err(_) => return Err(UpstreamProxyError::InvalidDest(conn_dest.to_string())),
+        },
+        None => return Err(UpstreamProxyError::InvalidDest(conn_dest.to_string())),
+    };
+    connect_upstream_proxy(config, host, port).await
+}
@@ -0,0 +1,53 @@
+use super::UpstreamProxyError;
+use crate::common::UpstreamProxyConfig;
+use std::net::Ipv6Addr;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+///响应头的最大长度
+const MAX_RESPONSE_SIZE: usize = 8192;
+///响应头最多包含的header个数
+const MAX_HEADERS: usize = 32;
+
+///使用http CONNECT方法建立隧道
+pub async fn http_connect(
+    stream: &mut TcpStream,
+    config: &UpstreamProxyConfig,
+    host: &str,
+    port: u16,
+) -> Result<(), UpstreamProxyError> {
+    let authority = if host.parse::<Ipv6Addr>().is_ok() {
+        format!("[{host}]:{port}")
+    } else {
+        format!("{host}:{port}")
+    };
+    let mut request = format!("CONNECT {authority} HTTP/1.1\r\nHost: {authority}\r\n");
+    if let Some(user) = &config.user {
+        let password = config.password.as_deref().unwrap_or_default();
+        let credentials = base64::encode(format!("{user}:{password}"));
+        request.push_str(&format!("Proxy-Authorization: Basic {credentials}\r\n"));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+    //逐个字节读取, 避免读到响应头之后的数据
+    let mut buf = Vec::with_capacity(512);
+    while !buf.ends_with(b"\r\n\r\n") {
+        if buf.len() >= MAX_RESPONSE_SIZE {
+            return Err(UpstreamProxyError::InvalidHttpResponse);
+        }
+        buf.push(stream.read_u8().await?);
+    }
+    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    let mut response = httparse::Response::new(&mut headers);
+    match response.parse(&buf) {
+        Ok(httparse::Status::Complete(_)) => (),
+        _ => return Err(UpstreamProxyError::InvalidHttpResponse),
+    }
+    match response.code {
+        Some(code) if (200..300).contains(&code) => Ok(()),
+        Some(code) => Err(UpstreamProxyError::HttpStatus(code)),
+        None => Err(UpstreamProxyError::InvalidHttpResponse),
+    }
+}
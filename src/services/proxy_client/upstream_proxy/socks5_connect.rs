@@ -0,0 +1,79 @@
+use super::UpstreamProxyError;
+use crate::common::{
+    socks5::{self, ConnDest, ConnDestAddr},
+    UpstreamProxyConfig,
+};
+use std::net::IpAddr;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+///不需要认证
+const METHOD_NO_AUTH: u8 = 0x00;
+///用户名密码认证
+const METHOD_USER_PASSWORD: u8 = 0x02;
+///用户名密码认证的版本
+const USER_PASSWORD_VERSION: u8 = 0x01;
+///CONNECT命令
+const CMD_CONNECT: u8 = 0x01;
+
+///使用socks5 CONNECT命令建立隧道
+pub async fn socks5_connect(
+    stream: &mut TcpStream,
+    config: &UpstreamProxyConfig,
+    host: &str,
+    port: u16,
+) -> Result<(), UpstreamProxyError> {
+    //协商认证方式
+    let method = match config.user {
+        Some(_) => METHOD_USER_PASSWORD,
+        None => METHOD_NO_AUTH,
+    };
+    stream.write_all(&[socks5::VERSION, 1, method]).await?;
+    let mut buf = [0; 2];
+    stream.read_exact(&mut buf).await?;
+    if buf[0] != socks5::VERSION {
+        return Err(UpstreamProxyError::InvalidSocks5Response);
+    }
+    if buf[1] != method {
+        return Err(UpstreamProxyError::Socks5AuthMethod);
+    }
+    if let Some(user) = &config.user {
+        let password = config.password.as_deref().unwrap_or_default();
+        let user_len =
+            u8::try_from(user.len()).map_err(|_| UpstreamProxyError::FieldTooLong("user"))?;
+        let password_len = u8::try_from(password.len())
+            .map_err(|_| UpstreamProxyError::FieldTooLong("password"))?;
+        let mut auth_request = vec![USER_PASSWORD_VERSION, user_len];
+        auth_request.extend_from_slice(user.as_bytes());
+        auth_request.push(password_len);
+        auth_request.extend_from_slice(password.as_bytes());
+        stream.write_all(&auth_request).await?;
+        stream.read_exact(&mut buf).await?;
+        if buf[1] != 0 {
+            return Err(UpstreamProxyError::Socks5AuthFailed);
+        }
+    }
+    //发送CONNECT请求
+    let addr = match host.parse::<IpAddr>() {
+        Ok(ip) => ConnDestAddr::Ip(ip),
+        Err(_) if host.len() <= u8::MAX as usize => ConnDestAddr::Domain(host.to_string()),
+        Err(_) => return Err(UpstreamProxyError::FieldTooLong("host")),
+    };
+    let conn_dest = ConnDest { addr, port };
+    let mut request = vec![socks5::VERSION, CMD_CONNECT, 0];
+    request.extend_from_slice(&conn_dest.to_raw_data());
+    stream.write_all(&request).await?;
+    //读取应答, 绑定地址不需要使用
+    let mut reply = [0; 3];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != socks5::VERSION {
+        return Err(UpstreamProxyError::InvalidSocks5Response);
+    }
+    if reply[1] != 0 {
+        return Err(UpstreamProxyError::Socks5Reply(reply[1]));
+    }
+    ConnDest::try_from_stream(stream).await?;
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use crate::common::socks5::ParseConnDestError;
+use std::io::Error as IoError;
+use thiserror::Error;
+
+///通过前置代理建立隧道出现的错误
+#[derive(Error, Debug)]
+pub enum UpstreamProxyError {
+    ///目标地址格式错误
+    #[error("invalid dest {0}")]
+    InvalidDest(String),
+    ///连接前置代理失败
+    #[error("connect upstream proxy failed: {0}")]
+    Conn(IoError),
+    ///与前置代理之间读写数据失败
+    #[error("upstream proxy io failed: {0}")]
+    Io(#[from] IoError),
+    ///http代理返回的响应无法解析
+    #[error("invalid upstream proxy http response")]
+    InvalidHttpResponse,
+    ///http代理返回的状态码不是2xx
+    #[error("upstream proxy http status: {0}")]
+    HttpStatus(u16),
+    ///socks5代理返回的数据不符合协议
+    #[error("invalid upstream proxy socks5 response")]
+    InvalidSocks5Response,
+    ///socks5代理不支持客户端提供的认证方式
+    #[error("upstream proxy socks5 auth method not accepted")]
+    Socks5AuthMethod,
+    ///socks5代理用户名密码认证失败
+    #[error("upstream proxy socks5 auth failed")]
+    Socks5AuthFailed,
+    ///socks5代理返回了失败的应答
+    #[error("upstream proxy socks5 reply: {0}")]
+    Socks5Reply(u8),
+    ///解析socks5代理返回的地址失败
+    #[error("parse upstream proxy socks5 address failed: {0}")]
+    Socks5Addr(#[from] ParseConnDestError),
+    ///域名、用户名或密码超过255字节
+    #[error("upstream proxy field too long: {0}")]
+    FieldTooLong(&'static str),
+}
@@ -0,0 +1,106 @@
+use super::{
+    load_route_config::load_route_config, server_conn_manger::ServerConnManger,
+    shared_route_config::SharedRouteConfig,
+};
+use crate::{
+    common::{ClientError, ConfigError},
+    services::{self, geoip, geosite, FileState, HangupSignal},
+};
+use std::{
+    io::{Error as IoError, ErrorKind},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{fs, time};
+
+///检查文件是否修改的间隔
+const WATCH_INTERVAL: Duration = Duration::from_secs(3);
+
+///监视路由配置文件、规则集文件和数据目录, 文件修改或者收到SIGHUP时重新加载路由配置
+///
+///加载失败时继续使用旧的配置
+pub async fn watch_route_config(
+    route_file: &str,
+    data_dir: &str,
+    shared_route_config: SharedRouteConfig,
+    conn_manger: ServerConnManger,
+) {
+    let mut hangup = HangupSignal::listen();
+    let mut last_state = route_files_state(route_file, data_dir, &shared_route_config).await;
+    let mut interval = time::interval(WATCH_INTERVAL);
+    interval.tick().await;
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let state = route_files_state(route_file, data_dir, &shared_route_config).await;
+                if state == last_state {
+                    continue;
+                }
+                last_state = state;
+                log::info!("route config changed, reloading ...");
+            }
+            _ = hangup.recv() => {
+                last_state = route_files_state(route_file, data_dir, &shared_route_config).await;
+                log::info!("received SIGHUP, reloading route config ...");
+            }
+        }
+        match reload_route_config(route_file, data_dir, &shared_route_config, &conn_manger).await {
+            Ok(_) => log::info!("route config reloaded"),
+            Err(e) => log::error!("reload route config failed, keep the old config: {e}"),
+        }
+    }
+}
+
+///重新加载路由配置, 成功之后替换旧的配置
+async fn reload_route_config(
+    route_file: &str,
+    data_dir: &str,
+    shared_route_config: &SharedRouteConfig,
+    conn_manger: &ServerConnManger,
+) -> Result<(), ClientError> {
+    //启动时缺少geosite数据会使用默认配置, 重新加载时可能是数据文件正在更新, 保留旧的配置
+    if geosite::data_file_path(data_dir).is_none() {
+        let not_found = IoError::from(ErrorKind::NotFound);
+        return Err(ClientError::Config(
+            format!("{data_dir}/geosite.pak or geosite.dat"),
+            ConfigError::Io(not_found),
+        ));
+    }
+    let mut route_config = load_route_config(route_file, data_dir).await?;
+    //fake-ip地址池和域名解析与dns服务共用, 保留已经分配的地址和缓存
+    let old_route_config = shared_route_config.load();
+    route_config.fake_ip_pool = old_route_config.fake_ip_pool.clone();
+    route_config.domain_resolver = old_route_config.domain_resolver.clone();
+    super::check_route_outbounds(&route_config, conn_manger)?;
+    shared_route_config.store(Arc::new(route_config));
+    Ok(())
+}
+
+///路由配置文件、规则集文件和数据目录(包括cidr列表目录)中所有文件的状态
+async fn route_files_state(
+    route_file: &str,
+    data_dir: &str,
+    shared_route_config: &SharedRouteConfig,
+) -> Vec<FileState> {
+    let mut paths = vec![PathBuf::from(route_file)];
+    let rule_set_paths = shared_route_config
+        .load()
+        .rule_sets
+        .iter()
+        .map(|rule_set| rule_set.file_path(data_dir))
+        .collect::<Vec<_>>();
+    paths.extend(rule_set_paths);
+    //数据目录和其中的cidr列表目录
+    let mut data_paths = Vec::new();
+    for dir in [PathBuf::from(data_dir), geoip::cidr_dir_path(data_dir)] {
+        if let Ok(mut read_dir) = fs::read_dir(dir).await {
+            while let Ok(Some(entry)) = read_dir.next_entry().await {
+                data_paths.push(entry.path());
+            }
+        }
+    }
+    data_paths.sort();
+    paths.extend(data_paths);
+    services::files_state(paths).await
+}
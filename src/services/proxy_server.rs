@@ -4,6 +4,10 @@ mod client_session;
 mod handle_connection;
 mod proxy_error;
 mod read_remote_stream;
+mod remote_stream;
+mod shared_auth_users;
+mod udp_relay;
+mod watch_server_config;
 mod ws_handler_ns;
 
 use crate::common::{ServerConfig, ServerError};
@@ -11,6 +15,7 @@ use axum::response::IntoResponse;
 use axum::{routing, Extension, Router};
 use axum_server::tls_rustls::RustlsConfig;
 use http::StatusCode;
+use shared_auth_users::SharedAuthUsers;
 use std::io::Error as IoError;
 use std::net::SocketAddr;
 use std::net::ToSocketAddrs;
@@ -19,7 +24,9 @@ use tokio::signal;
 use tower_http::services::{ServeDir, ServeFile};
 
 ///运行服务端程序
-pub async fn execute(config: ServerConfig) -> Result<(), ServerError> {
+///
+///`config_file` 修改之后重新加载授权用户和证书
+pub async fn execute(config: ServerConfig, config_file: &str) -> Result<(), ServerError> {
     let config = Arc::new(config);
     let mut addrs_iter = (config.address.as_str(), config.port)
         .to_socket_addrs()
@@ -35,12 +42,15 @@ pub async fn execute(config: ServerConfig) -> Result<(), ServerError> {
             }
         }
     }
+    let auth_users = SharedAuthUsers::new(config.auth_users.clone());
+    let app = build_app(config.clone(), auth_users.clone());
     //判断是否开启ssl
     if !config.use_ssl {
-        let app = build_app(config);
-        run_http(app, &listen_address).await?;
+        tokio::select! {
+            output1 = run_http(app, &listen_address) => output1?,
+            _ = watch_server_config::watch_server_config(config_file, &config, auth_users, None) => (),
+        };
     } else {
-        let app = build_app(config.clone());
         let cert_path = match &config.ssl_cert_path {
             Some(s) => s.as_str(),
             None => return Err(ServerError::ConfigSSlCertNone),
@@ -49,13 +59,25 @@ pub async fn execute(config: ServerConfig) -> Result<(), ServerError> {
             Some(s) => s.as_str(),
             None => return Err(ServerError::ConfigSSlKeyNone),
         };
-        run_https(app, &listen_address, cert_path, key_path).await?;
+        let tls_config = RustlsConfig::from_pem_file(cert_path, key_path)
+            .await
+            .map_err(ServerError::Cert)?;
+        let watch_future = watch_server_config::watch_server_config(
+            config_file,
+            &config,
+            auth_users,
+            Some(tls_config.clone()),
+        );
+        tokio::select! {
+            output1 = run_https(app, &listen_address, tls_config) => output1?,
+            _ = watch_future => (),
+        };
     }
     log::info!("proxy server shutdown");
     Ok(())
 }
 
-fn build_app(config: Arc<ServerConfig>) -> Router {
+fn build_app(config: Arc<ServerConfig>, auth_users: SharedAuthUsers) -> Router {
     //静态文件夹
     let static_file_service =
         ServeDir::new("./web/public").fallback(ServeFile::new("./web/404.html"));
@@ -65,7 +87,7 @@ fn build_app(config: Arc<ServerConfig>) -> Router {
         .route(&config.path, routing::get(ws_handler_ns::ws_handler))
         //默认路由
         .fallback(routing::get_service(static_file_service).handle_error(handle_error))
-        .layer(Extension(config))
+        .layer(Extension(auth_users))
 }
 
 async fn handle_error(_err: IoError) -> impl IntoResponse {
@@ -93,12 +115,8 @@ async fn run_http(app: Router, listen_address: &SocketAddr) -> Result<(), Server
 async fn run_https(
     app: Router,
     listen_address: &SocketAddr,
-    cert_path: &str,
-    key_path: &str,
+    tls_config: RustlsConfig,
 ) -> Result<(), ServerError> {
-    let tls_config = RustlsConfig::from_pem_file(cert_path, key_path)
-        .await
-        .map_err(ServerError::Cert)?;
     let server = axum_server::bind_rustls(listen_address.to_owned(), tls_config);
     log::info!("Server listen {listen_address} (ssl = true)");
     tokio::select! {
@@ -1,4 +1,4 @@
-use crate::common::ServerConfig;
+use super::shared_auth_users::SharedAuthUsers;
 use axum::{
     async_trait,
     extract::{FromRequest, RequestParts},
@@ -6,10 +6,7 @@ use axum::{
     TypedHeader,
 };
 use bytes::{Buf, Bytes};
-use std::{
-    sync::Arc,
-    time::{self, SystemTime},
-};
+use std::time::{self, SystemTime};
 
 ///身份认证
 pub struct CheckAuth {
@@ -58,11 +55,7 @@ where
         {
             let time_now = SystemTime::now();
             let current_ts = time_now.duration_since(time::UNIX_EPOCH).unwrap().as_secs();
-            let diff_ts = if current_ts >= ts {
-                current_ts - ts
-            } else {
-                ts - current_ts
-            };
+            let diff_ts = current_ts.abs_diff(ts);
             //时间误差过大
             if diff_ts > 90 {
                 return Err(reject_resp);
@@ -70,8 +63,8 @@ where
         }
         let token = buf.slice(8..28);
         //
-        let config = req.extensions().get::<Arc<ServerConfig>>().unwrap();
-        for auth_user in &config.auth_users {
+        let auth_users = req.extensions().get::<SharedAuthUsers>().unwrap().load();
+        for auth_user in auth_users.iter() {
             if auth_user.user == username && auth_user.get_token(ts) == token {
                 return Ok(Self {
                     user: username.to_string(),
@@ -1,23 +1,42 @@
-use super::{client_io, proxy_error::ProxyError, read_remote_stream};
-use crate::common::msg::{
-    client::{Connect, ProxyRequest},
-    server::{ConnectResult, RequestFail},
-    ClientMessage, ServerMessage,
-};
+use super::{client_io, proxy_error::ProxyError, remote_stream::RemoteStream, udp_relay::UdpRelay};
+use crate::common::msg::{server::RequestFail, ClientMessage, Datagram, ServerMessage};
+use crate::services::flow_window::{RecvPending, SendWindow};
 use axum::extract::ws::WebSocket;
+use bytes::Bytes;
 use futures_util::StreamExt;
-use std::time::Duration;
-use tokio::{
-    io::AsyncWriteExt,
-    net::{tcp::WriteHalf, TcpStream},
-    sync::mpsc::{self, Receiver, Sender},
-    time,
+use std::{collections::HashMap, future::Future, sync::Arc};
+use tokio::sync::mpsc::{
+    self, error::TrySendError, Receiver, Sender, UnboundedReceiver, UnboundedSender,
 };
 
+///会话中一个stream的句柄
+enum StreamHandle {
+    ///tcp连接
+    Tcp {
+        ///写入远端的数据发送端, 数据量由客户端的发送窗口限制
+        data_tx: UnboundedSender<Bytes>,
+        ///还没写入远端的数据, 客户端超出窗口时断开stream
+        recv_pending: Arc<RecvPending>,
+        ///向客户端发送数据的窗口
+        send_window: Arc<SendWindow>,
+    },
+    ///udp转发, 来不及处理的数据报直接丢弃
+    Udp { datagram_tx: Sender<Datagram> },
+}
+
+impl Drop for StreamHandle {
+    ///stream移除或者会话结束时, 唤醒等待发送窗口的task
+    fn drop(&mut self) {
+        if let Self::Tcp { send_window, .. } = self {
+            send_window.close();
+        }
+    }
+}
+
 pub struct ClientSession {
     pub username: String,
-    ///连接复用次数
-    pub use_count: u8,
+    ///处理过的stream个数
+    pub use_count: u32,
 }
 
 impl ClientSession {
@@ -38,140 +57,226 @@ impl ClientSession {
         }
     }
     ///处理客户端消息
+    ///
+    ///每个stream在独立的task中连接远端并转发数据,这里只负责把消息分发给对应的stream
     pub async fn process_message(
         &mut self,
         tx: Sender<ServerMessage>,
         mut rx: Receiver<ClientMessage>,
     ) -> Result<(), ProxyError> {
-        while let Some(message) = rx.recv().await {
-            //外围只处理Conn
-            if let ClientMessage::Conn(conn_msg) = message {
-                let mut option_conn_msg = self.process_conn(conn_msg, tx.clone(), &mut rx).await?;
-                while let Some(conn_msg) = option_conn_msg {
-                    option_conn_msg = self.process_conn(conn_msg, tx.clone(), &mut rx).await?;
+        //stream id => stream句柄
+        let mut streams = HashMap::new();
+        //stream结束后通知移除
+        let (done_tx, mut done_rx) = mpsc::unbounded_channel();
+        loop {
+            tokio::select! {
+                message = rx.recv() => match message {
+                    Some(message) => self.dispatch_message(message, &mut streams, &tx, &done_tx),
+                    None => break,
+                },
+                Some(stream_id) = done_rx.recv() => {
+                    streams.remove(&stream_id);
                 }
             }
         }
         Ok(())
     }
 
-    async fn process_conn(
+    ///分发一条客户端消息
+    fn dispatch_message(
         &mut self,
-        conn_msg: Connect,
-        tx: Sender<ServerMessage>,
-        rx: &mut Receiver<ClientMessage>,
-    ) -> Result<Option<Connect>, ProxyError> {
-        //log::info!("ClientMessage::Conn");
-        let conn_dest = conn_msg.0;
-        //指定超时时间, 执行connect
-        log::info!("[{}]server connect {conn_dest}", self.username);
-        let timeout_duration = Duration::from_secs(5);
-        let (conn_result_msg, option_stream) =
-            match time::timeout(timeout_duration, TcpStream::connect(&conn_dest)).await {
-                Ok(inner_result) => match inner_result {
-                    //成功
-                    Ok(s) => (ConnectResult::Ok, Some(s)),
-                    //失败
-                    Err(e) => {
-                        log::error!("[{}]server connect {conn_dest} failed: {e}", self.username);
-                        (ConnectResult::Err(e.to_string()), None)
+        message: ClientMessage,
+        streams: &mut HashMap<u32, StreamHandle>,
+        tx: &Sender<ServerMessage>,
+        done_tx: &UnboundedSender<u32>,
+    ) {
+        match message {
+            ClientMessage::Conn(stream_id, conn_msg) => {
+                self.spawn_remote_stream(
+                    stream_id,
+                    streams,
+                    tx,
+                    done_tx,
+                    |remote_stream, tx, data_rx| async move {
+                        remote_stream.run(conn_msg.0, tx, data_rx).await
+                    },
+                );
+            }
+            ClientMessage::Bind(stream_id, bind_msg) => {
+                self.spawn_remote_stream(
+                    stream_id,
+                    streams,
+                    tx,
+                    done_tx,
+                    |remote_stream, tx, data_rx| async move {
+                        remote_stream.run_bind(bind_msg.0, tx, data_rx).await
+                    },
+                );
+            }
+            ClientMessage::DisConn(stream_id) => {
+                //drop掉发送端, stream task会关闭远端连接
+                streams.remove(&stream_id);
+            }
+            ClientMessage::Request(stream_id, req_msg) => {
+                if let Some(StreamHandle::Tcp {
+                    data_tx,
+                    recv_pending,
+                    ..
+                }) = streams.get(&stream_id)
+                {
+                    if !recv_pending.push(req_msg.0.len()) {
+                        self.reset_stream(stream_id, streams, tx, "receive window exceeded");
+                    } else if data_tx.send(req_msg.0).is_err() {
+                        streams.remove(&stream_id);
                     }
-                },
-                //超时
-                Err(_) => {
-                    log::error!("[{}]server connect {conn_dest} timeout", self.username);
-                    (ConnectResult::Timeout, None)
                 }
-            };
-        //向客户端发送连接结果
-        tx.send(conn_result_msg.into())
-            .await
-            .map_err(|_| ProxyError::WriteChannel)?;
-        if let Some(remote_stream) = option_stream {
-            //复用计数+1
-            self.use_count += 1;
-            log::info!(
-                "[{}]server connect {conn_dest} ok (#{})",
-                self.username,
-                self.use_count
-            );
-            //处理远程连接
-            let option_conn_msg = self.process_remote(remote_stream, tx, rx).await?;
-            return Ok(option_conn_msg);
+            }
+            ClientMessage::WindowUpdate(stream_id, update_msg) => {
+                if let Some(StreamHandle::Tcp { send_window, .. }) = streams.get(&stream_id) {
+                    if let Err(e) = send_window.release(update_msg.0) {
+                        self.reset_stream(stream_id, streams, tx, &e.to_string());
+                    }
+                }
+            }
+            ClientMessage::UdpAssociate(stream_id) => {
+                if streams.contains_key(&stream_id) {
+                    log::warn!("[{}]stream #{stream_id} already exists", self.username);
+                    return;
+                }
+                self.use_count += 1;
+                let (datagram_tx, datagram_rx) = mpsc::channel(64);
+                streams.insert(stream_id, StreamHandle::Udp { datagram_tx });
+                let udp_relay = UdpRelay {
+                    stream_id,
+                    username: self.username.to_string(),
+                    use_count: self.use_count,
+                };
+                let tx = tx.clone();
+                let done_tx = done_tx.clone();
+                tokio::spawn(async move {
+                    udp_relay.run(tx, datagram_rx).await;
+                    _ = done_tx.send(stream_id);
+                });
+            }
+            ClientMessage::Datagram(stream_id, datagram) => {
+                if let Some(StreamHandle::Udp { datagram_tx }) = streams.get(&stream_id) {
+                    if let Err(TrySendError::Closed(_)) = datagram_tx.try_send(datagram) {
+                        streams.remove(&stream_id);
+                    }
+                }
+            }
         }
-        Ok(None)
     }
 
-    //处理远程连接io
-    async fn process_remote(
+    ///对方违反流量控制时重置stream, 移除stream并通知客户端请求失败
+    fn reset_stream(
+        &self,
+        stream_id: u32,
+        streams: &mut HashMap<u32, StreamHandle>,
+        tx: &Sender<ServerMessage>,
+        reason: &str,
+    ) {
+        log::warn!("[{}]stream #{stream_id} {reason}", self.username);
+        streams.remove(&stream_id);
+        let req_fail_msg = RequestFail(reason.to_string());
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            tx.send(ServerMessage::RequestFail(stream_id, req_fail_msg))
+                .await
+        });
+    }
+
+    ///创建一个tcp stream, 在新的task中运行
+    fn spawn_remote_stream<F, Fut>(
         &mut self,
-        mut remote_stream: TcpStream,
-        tx: Sender<ServerMessage>,
-        rx: &mut Receiver<ClientMessage>,
-    ) -> Result<Option<Connect>, ProxyError> {
-        let (remote_reader, remote_writer) = remote_stream.split();
-        //处理客户端消息和读取远端一起运行
-        let option_conn_msg = tokio::select! {
-            client_result = self.process_client_message(remote_writer,tx.clone(),rx)=>{
-                //dbg!(&client_result);
-                client_result?
-            },
-            remote_result = read_remote_stream::read_remote_stream(remote_reader,tx)=>{
-                //dbg!(&remote_result);
-                remote_result?;
-                None
-            },
+        stream_id: u32,
+        streams: &mut HashMap<u32, StreamHandle>,
+        tx: &Sender<ServerMessage>,
+        done_tx: &UnboundedSender<u32>,
+        run_fn: F,
+    ) where
+        F: FnOnce(RemoteStream, Sender<ServerMessage>, UnboundedReceiver<Bytes>) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        if streams.contains_key(&stream_id) {
+            log::warn!("[{}]stream #{stream_id} already exists", self.username);
+            return;
+        }
+        //复用计数+1
+        self.use_count += 1;
+        let (data_tx, data_rx) = mpsc::unbounded_channel();
+        let recv_pending = Arc::new(RecvPending::default());
+        let send_window = Arc::new(SendWindow::default());
+        let stream_handle = StreamHandle::Tcp {
+            data_tx,
+            recv_pending: recv_pending.clone(),
+            send_window: send_window.clone(),
+        };
+        streams.insert(stream_id, stream_handle);
+        let remote_stream = RemoteStream {
+            stream_id,
+            username: self.username.to_string(),
+            use_count: self.use_count,
+            recv_pending,
+            send_window,
         };
-        //关闭远端连接
-        _ = remote_stream.shutdown().await;
-        Ok(option_conn_msg)
+        let stream_future = run_fn(remote_stream, tx.clone(), data_rx);
+        let done_tx = done_tx.clone();
+        tokio::spawn(async move {
+            stream_future.await;
+            _ = done_tx.send(stream_id);
+        });
     }
+}
 
-    ///处理客户端的所有消息(remote已连接的条件下)
-    async fn process_client_message(
-        &mut self,
-        mut remote_writer: WriteHalf<'_>,
-        mut tx: Sender<ServerMessage>,
-        rx: &mut Receiver<ClientMessage>,
-    ) -> Result<Option<Connect>, ProxyError> {
-        while let Some(message) = rx.recv().await {
-            match message {
-                ClientMessage::Conn(conn_msg) => {
-                    //未收到断开消息,就又conn的异常情况
-                    //log::info!("ClientMessage::Conn");
-                    log::warn!("conn new remote without send ClientMessage::DisConn");
-                    return Ok(Some(conn_msg));
-                }
-                ClientMessage::DisConn => {
-                    //log::info!("ClientMessage::DisConn");
-                    break;
-                }
-                ClientMessage::Request(req_msg) => {
-                    //log::info!("ClientMessage::Request");
-                    self.process_request(&mut remote_writer, &mut tx, req_msg)
-                        .await?
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::msg::{
+        client::{Connect, ProxyRequest},
+        WindowUpdate, INITIAL_WINDOW_SIZE,
+    };
+    use std::time::Duration;
+    use tokio::{net::TcpListener, time};
+
+    ///打开一个stream之后发送 `message`, 返回服务端对这个stream的请求失败消息
+    async fn request_fail_after(message: ClientMessage) -> RequestFail {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let conn_dest = listener.local_addr().unwrap().to_string();
+        let (client_tx, client_rx) = mpsc::channel(20);
+        let (server_tx, mut server_rx) = mpsc::channel(20);
+        let mut client_session = ClientSession::new("test".to_string());
+        tokio::spawn(async move { client_session.process_message(server_tx, client_rx).await });
+        client_tx
+            .send(ClientMessage::Conn(1, Connect(conn_dest)))
+            .await
+            .unwrap();
+        client_tx.send(message).await.unwrap();
+        let wait_fail = async {
+            loop {
+                match server_rx.recv().await.unwrap() {
+                    ServerMessage::RequestFail(1, req_fail_msg) => return req_fail_msg,
+                    ServerMessage::ConnResult(1, _) => continue,
+                    message => panic!("unexpected message {message:?}"),
                 }
             }
-        }
-        Ok(None)
+        };
+        time::timeout(Duration::from_secs(5), wait_fail)
+            .await
+            .unwrap()
     }
 
-    ///把请求数据发给远端
-    async fn process_request(
-        &mut self,
-        remote_writer: &mut WriteHalf<'_>,
-        tx: &mut Sender<ServerMessage>,
-        req_msg: ProxyRequest,
-    ) -> Result<(), ProxyError> {
-        let request_data = req_msg.0;
-        if let Err(e) = remote_writer.write_all(&request_data).await {
-            log::error!("write remote failed: {e}");
-            //把write远端的失败信息发给客户端
-            let req_fail_msg = RequestFail(e.to_string());
-            tx.send(req_fail_msg.into())
-                .await
-                .map_err(|_| ProxyError::WriteChannel)?;
-        }
-        Ok(())
+    #[tokio::test]
+    async fn reset_on_recv_overrun() {
+        let data = Bytes::from(vec![0; INITIAL_WINDOW_SIZE as usize + 1]);
+        let req_fail_msg = request_fail_after(ClientMessage::Request(1, ProxyRequest(data))).await;
+        assert_eq!(req_fail_msg.0, "receive window exceeded");
+    }
+
+    #[tokio::test]
+    async fn reset_on_send_window_overflow() {
+        let req_fail_msg =
+            request_fail_after(ClientMessage::WindowUpdate(1, WindowUpdate(u32::MAX))).await;
+        assert_eq!(req_fail_msg.0, "send window overflow");
     }
 }
@@ -1,5 +1,7 @@
 use crate::common::msg::ParseMessageError;
+use crate::services::flow_window::WindowClosedError;
 use axum::Error as WsError;
+use std::io::Error as IoError;
 use thiserror::Error;
 
 ///服务端proxy错误定义
@@ -15,4 +17,8 @@ pub enum ProxyError {
     WriteChannel,
     #[error("parse message failed: {0}")]
     ParseMessage(#[from] ParseMessageError),
+    #[error("{0}")]
+    WindowClosed(#[from] WindowClosedError),
+    #[error("udp recv failed: {0}")]
+    UdpRecv(IoError),
 }
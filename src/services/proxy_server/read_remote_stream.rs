@@ -1,17 +1,23 @@
 use super::proxy_error::ProxyError;
 use crate::common::msg::{server::ProxyResponseResult, ServerMessage};
-use crate::services::read_raw_data;
+use crate::services::{flow_window::SendWindow, read_raw_data};
 use std::io::ErrorKind;
 use tokio::{net::tcp::ReadHalf, sync::mpsc::Sender};
 
 pub async fn read_remote_stream(
+    stream_id: u32,
     mut remote_reader: ReadHalf<'_>,
-    tx: Sender<ServerMessage>,
+    tx: &Sender<ServerMessage>,
+    send_window: &SendWindow,
 ) -> Result<(), ProxyError> {
     loop {
         let mut read_response_ok = true;
         let response_result_msg = match read_raw_data::read_raw(&mut remote_reader).await {
-            Ok(data) => ProxyResponseResult::Ok(data),
+            Ok(data) => {
+                //等待客户端的接收窗口
+                send_window.acquire(data.len()).await?;
+                ProxyResponseResult::Ok(data)
+            }
             Err(e) => {
                 read_response_ok = false;
                 if e.kind() == ErrorKind::UnexpectedEof {
@@ -22,9 +28,12 @@ pub async fn read_remote_stream(
             }
         };
         //把read远端的结果发给客户端
-        tx.send(response_result_msg.into())
-            .await
-            .map_err(|_| ProxyError::WriteChannel)?;
+        tx.send(ServerMessage::ResponseResult(
+            stream_id,
+            response_result_msg,
+        ))
+        .await
+        .map_err(|_| ProxyError::WriteChannel)?;
         //response失败跳出循环
         if !read_response_ok {
             break;
@@ -0,0 +1,245 @@
+use super::{proxy_error::ProxyError, read_remote_stream};
+use crate::common::msg::{
+    server::{BindResult, ConnectResult, RequestFail},
+    ServerMessage, WindowUpdate,
+};
+use crate::services::flow_window::{RecvPending, RecvWindow, SendWindow};
+use bytes::Bytes;
+use std::{
+    io::Error as IoError,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    io::AsyncWriteExt,
+    net::{tcp::WriteHalf, TcpListener, TcpStream},
+    sync::mpsc::{Sender, UnboundedReceiver},
+    time,
+};
+
+///bind命令等待远端连接的超时时间
+const BIND_TIMEOUT: Duration = Duration::from_secs(60);
+
+///websocket连接上的一个stream, 对应一个远端tcp连接
+pub struct RemoteStream {
+    pub stream_id: u32,
+    pub username: String,
+    ///这是当前会话的第几个stream
+    pub use_count: u32,
+    ///还没写入远端的数据
+    pub recv_pending: Arc<RecvPending>,
+    ///向客户端发送数据的窗口
+    pub send_window: Arc<SendWindow>,
+}
+
+impl RemoteStream {
+    ///连接远端并转发数据, 直到任意一方断开
+    pub async fn run(
+        &self,
+        conn_dest: String,
+        tx: Sender<ServerMessage>,
+        data_rx: UnboundedReceiver<Bytes>,
+    ) {
+        let remote_stream = match self.connect(&conn_dest, &tx).await {
+            Ok(Some(s)) => s,
+            Ok(None) => return,
+            Err(e) => {
+                log::error!("[{}]{e}", self.username);
+                return;
+            }
+        };
+        log::info!(
+            "[{}]server connect {conn_dest} ok (#{})",
+            self.username,
+            self.use_count
+        );
+        if let Err(e) = self.process_remote(remote_stream, tx, data_rx).await {
+            log::error!("[{}]{e}", self.username);
+        }
+    }
+
+    ///监听一个端口, 把第一个接受的远端连接作为这个stream的远端
+    ///
+    ///`expect_dest` 为客户端预期的远端地址, 如果是ip地址则只接受这个ip的连接
+    pub async fn run_bind(
+        &self,
+        expect_dest: String,
+        tx: Sender<ServerMessage>,
+        mut data_rx: UnboundedReceiver<Bytes>,
+    ) {
+        let remote_stream = match self.accept(&expect_dest, &tx, &mut data_rx).await {
+            Ok(Some(s)) => s,
+            Ok(None) => return,
+            Err(e) => {
+                log::error!("[{}]{e}", self.username);
+                return;
+            }
+        };
+        if let Err(e) = self.process_remote(remote_stream, tx, data_rx).await {
+            log::error!("[{}]{e}", self.username);
+        }
+    }
+
+    ///监听端口并等待远端连接, 两个阶段的结果都会发给客户端
+    async fn accept(
+        &self,
+        expect_dest: &str,
+        tx: &Sender<ServerMessage>,
+        data_rx: &mut UnboundedReceiver<Bytes>,
+    ) -> Result<Option<TcpStream>, ProxyError> {
+        let expect_ip = expect_dest
+            .parse::<SocketAddr>()
+            .ok()
+            .map(|addr| addr.ip())
+            .filter(|ip| !ip.is_unspecified());
+        //在服务端所有地址上监听一个随机端口
+        let bind_result = TcpListener::bind((IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0))
+            .await
+            .and_then(|s| s.local_addr().map(|addr| (s, addr)));
+        let (listener, listen_addr) = match bind_result {
+            Ok(s) => s,
+            Err(e) => {
+                log::error!("[{}]server bind failed: {e}", self.username);
+                self.send_bind_result(tx, BindResult::Err(e.to_string()))
+                    .await?;
+                return Ok(None);
+            }
+        };
+        self.send_bind_result(tx, BindResult::Listening(listen_addr.into()))
+            .await?;
+        let accept_future = async {
+            loop {
+                let (stream, peer_addr) = listener.accept().await?;
+                match expect_ip {
+                    Some(ip) if ip != peer_addr.ip() => {
+                        log::warn!("[{}]server bind drop {peer_addr}", self.username);
+                    }
+                    _ => return Ok::<_, IoError>((stream, peer_addr)),
+                }
+            }
+        };
+        //客户端断开stream时停止等待
+        let stream_closed = async {
+            while let Some(data) = data_rx.recv().await {
+                self.recv_pending.pop(data.len());
+            }
+        };
+        let (bind_result_msg, option_stream) = tokio::select! {
+            accept_result = time::timeout(BIND_TIMEOUT, accept_future) => match accept_result {
+                Ok(Ok((stream, peer_addr))) => {
+                    log::info!(
+                        "[{}]server bind accept {peer_addr} (#{})",
+                        self.username,
+                        self.use_count
+                    );
+                    (BindResult::Accepted(peer_addr.into()), Some(stream))
+                }
+                Ok(Err(e)) => {
+                    log::error!("[{}]server bind accept failed: {e}", self.username);
+                    (BindResult::Err(e.to_string()), None)
+                }
+                Err(_) => {
+                    log::error!("[{}]server bind accept timeout", self.username);
+                    (BindResult::Timeout, None)
+                }
+            },
+            _ = stream_closed => return Ok(None),
+        };
+        self.send_bind_result(tx, bind_result_msg).await?;
+        Ok(option_stream)
+    }
+
+    ///向客户端发送bind结果
+    async fn send_bind_result(
+        &self,
+        tx: &Sender<ServerMessage>,
+        bind_result: BindResult,
+    ) -> Result<(), ProxyError> {
+        tx.send(ServerMessage::BindResult(self.stream_id, bind_result))
+            .await
+            .map_err(|_| ProxyError::WriteChannel)
+    }
+
+    ///连接远端,并向客户端发送连接结果
+    async fn connect(
+        &self,
+        conn_dest: &str,
+        tx: &Sender<ServerMessage>,
+    ) -> Result<Option<TcpStream>, ProxyError> {
+        //指定超时时间, 执行connect
+        log::info!("[{}]server connect {conn_dest}", self.username);
+        let timeout_duration = Duration::from_secs(5);
+        let (conn_result_msg, option_stream) =
+            match time::timeout(timeout_duration, TcpStream::connect(conn_dest)).await {
+                Ok(inner_result) => match inner_result {
+                    //成功
+                    Ok(s) => (ConnectResult::Ok, Some(s)),
+                    //失败
+                    Err(e) => {
+                        log::error!("[{}]server connect {conn_dest} failed: {e}", self.username);
+                        (ConnectResult::Err(e.to_string()), None)
+                    }
+                },
+                //超时
+                Err(_) => {
+                    log::error!("[{}]server connect {conn_dest} timeout", self.username);
+                    (ConnectResult::Timeout, None)
+                }
+            };
+        //向客户端发送连接结果
+        tx.send(ServerMessage::ConnResult(self.stream_id, conn_result_msg))
+            .await
+            .map_err(|_| ProxyError::WriteChannel)?;
+        Ok(option_stream)
+    }
+
+    //处理远程连接io
+    async fn process_remote(
+        &self,
+        mut remote_stream: TcpStream,
+        tx: Sender<ServerMessage>,
+        data_rx: UnboundedReceiver<Bytes>,
+    ) -> Result<(), ProxyError> {
+        let (remote_reader, remote_writer) = remote_stream.split();
+        //写入远端和读取远端一起运行
+        let proxy_result = tokio::select! {
+            request_result = self.process_request(remote_writer, &tx, data_rx)=>request_result,
+            remote_result = read_remote_stream::read_remote_stream(self.stream_id, remote_reader, &tx, &self.send_window)=>remote_result,
+        };
+        //关闭远端连接
+        _ = remote_stream.shutdown().await;
+        proxy_result
+    }
+
+    ///把请求数据发给远端,客户端断开stream时结束
+    async fn process_request(
+        &self,
+        mut remote_writer: WriteHalf<'_>,
+        tx: &Sender<ServerMessage>,
+        mut data_rx: UnboundedReceiver<Bytes>,
+    ) -> Result<(), ProxyError> {
+        let mut recv_window = RecvWindow::default();
+        while let Some(request_data) = data_rx.recv().await {
+            let write_result = remote_writer.write_all(&request_data).await;
+            self.recv_pending.pop(request_data.len());
+            if let Err(e) = write_result {
+                log::error!("write remote failed: {e}");
+                //把write远端的失败信息发给客户端
+                let req_fail_msg = RequestFail(e.to_string());
+                tx.send(ServerMessage::RequestFail(self.stream_id, req_fail_msg))
+                    .await
+                    .map_err(|_| ProxyError::WriteChannel)?;
+                break;
+            }
+            //数据已写入远端, 通知客户端继续发送
+            if let Some(update_size) = recv_window.consume(request_data.len()) {
+                let update_msg = WindowUpdate(update_size);
+                tx.send(ServerMessage::WindowUpdate(self.stream_id, update_msg))
+                    .await
+                    .map_err(|_| ProxyError::WriteChannel)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+use crate::common::AuthUser;
+use std::sync::{Arc, RwLock};
+
+///可以在运行时替换的授权用户列表
+///
+///只在握手时检查, 替换之后已经建立的会话不受影响
+#[derive(Clone)]
+pub struct SharedAuthUsers {
+    inner: Arc<RwLock<Arc<Vec<AuthUser>>>>,
+}
+
+impl SharedAuthUsers {
+    pub fn new(auth_users: Vec<AuthUser>) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(Arc::new(auth_users))),
+        }
+    }
+
+    ///当前的用户列表
+    pub fn load(&self) -> Arc<Vec<AuthUser>> {
+        self.inner.read().unwrap().clone()
+    }
+
+    ///替换用户列表
+    pub fn store(&self, auth_users: Vec<AuthUser>) {
+        *self.inner.write().unwrap() = Arc::new(auth_users);
+    }
+}
@@ -0,0 +1,213 @@
+use super::proxy_error::ProxyError;
+use crate::common::msg::{server::ConnectResult, Datagram, ServerMessage};
+use crate::common::socks5::ConnDestAddr;
+use crate::services::relay_socket::RelaySocket;
+use bytes::Bytes;
+use std::{collections::HashMap, io::Error as IoError, net::SocketAddr};
+use tokio::sync::mpsc::{self, Receiver, Sender, UnboundedSender};
+
+///udp数据报的最大长度
+const MAX_DATAGRAM_SIZE: usize = 65535;
+///域名解析结果缓存的最大条数
+const MAX_RESOLVE_CACHE_SIZE: usize = 1024;
+///解析域名期间最多暂存的数据报个数
+const MAX_RESOLVING_DATAGRAMS: usize = 16;
+
+///域名解析的结果, 目标地址 => 解析出的地址
+type ResolveResult = (String, Result<SocketAddr, IoError>);
+
+///目标域名的解析状态
+enum TargetAddr {
+    ///正在解析, 暂存等待发送的数据报
+    Resolving(Vec<Bytes>),
+    Resolved(SocketAddr),
+}
+
+///websocket连接上的一个udp转发stream
+pub struct UdpRelay {
+    pub stream_id: u32,
+    pub username: String,
+    ///这是当前会话的第几个stream
+    pub use_count: u32,
+}
+
+impl UdpRelay {
+    ///绑定udp端口并转发数据报, 直到客户端断开stream
+    pub async fn run(&self, tx: Sender<ServerMessage>, datagram_rx: Receiver<Datagram>) {
+        let relay_socket = match self.bind(&tx).await {
+            Ok(Some(s)) => s,
+            Ok(None) => return,
+            Err(e) => {
+                log::error!("[{}]{e}", self.username);
+                return;
+            }
+        };
+        log::info!(
+            "[{}]server udp associate ok (#{})",
+            self.username,
+            self.use_count
+        );
+        let proxy_result = tokio::select! {
+            send_result = self.send_datagrams(&relay_socket, datagram_rx)=>send_result,
+            recv_result = self.recv_datagrams(&relay_socket, &tx)=>recv_result,
+        };
+        if let Err(e) = proxy_result {
+            log::error!("[{}]{e}", self.username);
+        }
+    }
+
+    ///绑定udp端口,并向客户端发送结果
+    async fn bind(&self, tx: &Sender<ServerMessage>) -> Result<Option<RelaySocket>, ProxyError> {
+        let (conn_result_msg, opt_socket) = match RelaySocket::bind().await {
+            Ok(s) => (ConnectResult::Ok, Some(s)),
+            Err(e) => {
+                log::error!("[{}]server udp associate failed: {e}", self.username);
+                (ConnectResult::Err(e.to_string()), None)
+            }
+        };
+        tx.send(ServerMessage::ConnResult(self.stream_id, conn_result_msg))
+            .await
+            .map_err(|_| ProxyError::WriteChannel)?;
+        Ok(opt_socket)
+    }
+
+    ///把客户端的数据报发给目标地址
+    ///
+    ///目标为域名时在单独的task中解析, 避免一个很慢的域名阻塞其他数据报, 解析结果在这次转发中缓存
+    async fn send_datagrams(
+        &self,
+        relay_socket: &RelaySocket,
+        mut datagram_rx: Receiver<Datagram>,
+    ) -> Result<(), ProxyError> {
+        let (resolve_tx, mut resolve_rx) = mpsc::unbounded_channel();
+        //目标地址 => 解析状态
+        let mut resolve_cache = HashMap::new();
+        loop {
+            tokio::select! {
+                datagram = datagram_rx.recv() => match datagram {
+                    Some(datagram) => {
+                        self.send_datagram(relay_socket, datagram, &mut resolve_cache, &resolve_tx)
+                            .await;
+                    }
+                    None => break,
+                },
+                Some((dest_str, resolve_result)) = resolve_rx.recv() => {
+                    self.process_resolve_result(relay_socket, &mut resolve_cache, dest_str, resolve_result)
+                        .await;
+                },
+            }
+        }
+        Ok(())
+    }
+
+    ///发送一个数据报, 目标域名还没有解析时启动解析并暂存数据报
+    async fn send_datagram(
+        &self,
+        relay_socket: &RelaySocket,
+        datagram: Datagram,
+        resolve_cache: &mut HashMap<String, TargetAddr>,
+        resolve_tx: &UnboundedSender<ResolveResult>,
+    ) {
+        let dest_str = datagram.dest.to_string();
+        let domain = match &datagram.dest.addr {
+            ConnDestAddr::Ip(ip) => {
+                let target_addr = SocketAddr::new(*ip, datagram.dest.port);
+                self.send_to_addr(relay_socket, &datagram.data, target_addr, &dest_str)
+                    .await;
+                return;
+            }
+            ConnDestAddr::Domain(domain) => domain,
+        };
+        match resolve_cache.get_mut(&dest_str) {
+            Some(TargetAddr::Resolved(target_addr)) => {
+                let target_addr = *target_addr;
+                self.send_to_addr(relay_socket, &datagram.data, target_addr, &dest_str)
+                    .await;
+            }
+            Some(TargetAddr::Resolving(datagrams)) => {
+                if datagrams.len() < MAX_RESOLVING_DATAGRAMS {
+                    datagrams.push(datagram.data);
+                }
+            }
+            None => {
+                if resolve_cache.len() >= MAX_RESOLVE_CACHE_SIZE {
+                    resolve_cache.retain(|_, s| matches!(s, TargetAddr::Resolving(_)));
+                }
+                let (domain, port) = (domain.to_string(), datagram.dest.port);
+                let is_ipv6 = relay_socket.is_ipv6();
+                let resolve_tx = resolve_tx.clone();
+                resolve_cache.insert(
+                    dest_str.to_string(),
+                    TargetAddr::Resolving(vec![datagram.data]),
+                );
+                tokio::spawn(async move {
+                    let resolve_result = RelaySocket::resolve(&domain, port, is_ipv6).await;
+                    _ = resolve_tx.send((dest_str, resolve_result));
+                });
+            }
+        }
+    }
+
+    ///域名解析完成, 发送暂存的数据报
+    async fn process_resolve_result(
+        &self,
+        relay_socket: &RelaySocket,
+        resolve_cache: &mut HashMap<String, TargetAddr>,
+        dest_str: String,
+        resolve_result: Result<SocketAddr, IoError>,
+    ) {
+        let target_addr = match resolve_result {
+            Ok(s) => s,
+            Err(e) => {
+                //下一个数据报重新解析
+                resolve_cache.remove(&dest_str);
+                log::warn!("[{}]udp send to {dest_str} failed: {e}", self.username);
+                return;
+            }
+        };
+        let datagrams =
+            match resolve_cache.insert(dest_str.to_string(), TargetAddr::Resolved(target_addr)) {
+                Some(TargetAddr::Resolving(s)) => s,
+                _ => return,
+            };
+        for data in datagrams {
+            self.send_to_addr(relay_socket, &data, target_addr, &dest_str)
+                .await;
+        }
+    }
+
+    ///udp不保证送达, 发送失败只记录日志
+    async fn send_to_addr(
+        &self,
+        relay_socket: &RelaySocket,
+        data: &[u8],
+        target_addr: SocketAddr,
+        dest_str: &str,
+    ) {
+        if let Err(e) = relay_socket.send_to_addr(data, target_addr).await {
+            log::warn!("[{}]udp send to {dest_str} failed: {e}", self.username);
+        }
+    }
+
+    ///把收到的数据报发给客户端
+    async fn recv_datagrams(
+        &self,
+        relay_socket: &RelaySocket,
+        tx: &Sender<ServerMessage>,
+    ) -> Result<(), ProxyError> {
+        let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+        loop {
+            let (size, src_addr) = relay_socket
+                .recv_from(&mut buf)
+                .await
+                .map_err(ProxyError::UdpRecv)?;
+            let datagram = Datagram {
+                dest: src_addr.into(),
+                data: Bytes::copy_from_slice(&buf[..size]),
+            };
+            tx.send(ServerMessage::Datagram(self.stream_id, datagram))
+                .await
+                .map_err(|_| ProxyError::WriteChannel)?;
+        }
+    }
+}
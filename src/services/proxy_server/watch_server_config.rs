@@ -0,0 +1,94 @@
+use super::shared_auth_users::SharedAuthUsers;
+use crate::{
+    common::{ServerConfig, ServerError},
+    services::{self, HangupSignal},
+};
+use axum_server::tls_rustls::RustlsConfig;
+use std::{path::PathBuf, time::Duration};
+use tokio::time;
+
+///检查文件是否修改的间隔
+const WATCH_INTERVAL: Duration = Duration::from_secs(3);
+
+///监视配置文件和ssl证书, 文件修改或者收到SIGHUP时重新加载授权用户和证书
+///
+///只重新加载 `auth_users` 和证书, 其他配置需要重启才会生效. 加载失败时继续使用旧的配置
+pub async fn watch_server_config(
+    config_file: &str,
+    config: &ServerConfig,
+    auth_users: SharedAuthUsers,
+    tls_config: Option<RustlsConfig>,
+) {
+    let mut watch_paths = config_watch_paths(config_file, config, tls_config.is_some());
+    let mut hangup = HangupSignal::listen();
+    let mut last_state = services::files_state(watch_paths.clone()).await;
+    let mut interval = time::interval(WATCH_INTERVAL);
+    interval.tick().await;
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let state = services::files_state(watch_paths.clone()).await;
+                if state == last_state {
+                    continue;
+                }
+                last_state = state;
+                log::info!("server config changed, reloading ...");
+            }
+            _ = hangup.recv() => {
+                last_state = services::files_state(watch_paths.clone()).await;
+                log::info!("received SIGHUP, reloading server config ...");
+            }
+        }
+        match reload_server_config(config_file, &auth_users, tls_config.as_ref()).await {
+            Ok(new_config) => {
+                log::info!("server config reloaded");
+                //证书路径可能已经修改, 改为监视新的文件
+                let new_watch_paths =
+                    config_watch_paths(config_file, &new_config, tls_config.is_some());
+                if new_watch_paths != watch_paths {
+                    watch_paths = new_watch_paths;
+                    last_state = services::files_state(watch_paths.clone()).await;
+                }
+            }
+            Err(e) => log::error!("reload server config failed, keep the old config: {e}"),
+        }
+    }
+}
+
+///需要监视的文件: 配置文件, 使用ssl时还有证书和私钥
+fn config_watch_paths(config_file: &str, config: &ServerConfig, use_ssl: bool) -> Vec<PathBuf> {
+    let mut watch_paths = vec![PathBuf::from(config_file)];
+    if use_ssl {
+        watch_paths.extend(config.ssl_cert_path.iter().map(PathBuf::from));
+        watch_paths.extend(config.ssl_key_path.iter().map(PathBuf::from));
+    }
+    watch_paths
+}
+
+///重新加载授权用户和证书, 返回新的配置
+async fn reload_server_config(
+    config_file: &str,
+    auth_users: &SharedAuthUsers,
+    tls_config: Option<&RustlsConfig>,
+) -> Result<ServerConfig, ServerError> {
+    let config: ServerConfig = services::load_config(config_file, "server")
+        .await
+        .map_err(|e| ServerError::Config(config_file.to_string(), e))?;
+    //先加载证书, 证书有错误时用户列表也不替换
+    if let Some(tls_config) = tls_config {
+        let cert_path = config
+            .ssl_cert_path
+            .as_deref()
+            .ok_or(ServerError::ConfigSSlCertNone)?;
+        let key_path = config
+            .ssl_key_path
+            .as_deref()
+            .ok_or(ServerError::ConfigSSlKeyNone)?;
+        tls_config
+            .reload_from_pem_file(cert_path, key_path)
+            .await
+            .map_err(ServerError::Cert)?;
+    }
+    auth_users.store(config.auth_users.clone());
+    Ok(config)
+}
@@ -0,0 +1,86 @@
+use std::{
+    io::{Error as IoError, ErrorKind},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+use tokio::{
+    net::{self, UdpSocket},
+    time,
+};
+
+///解析域名的超时时间
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+///转发udp数据报的socket
+///
+///优先使用双栈socket, 这样同一个socket可以同时发往ipv4和ipv6地址
+pub struct RelaySocket {
+    socket: UdpSocket,
+    is_ipv6: bool,
+}
+
+impl RelaySocket {
+    ///绑定随机端口
+    pub async fn bind() -> Result<Self, IoError> {
+        let ipv6_addr = SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0);
+        if let Ok(socket) = UdpSocket::bind(ipv6_addr).await {
+            return Ok(Self {
+                socket,
+                is_ipv6: true,
+            });
+        }
+        //系统不支持ipv6
+        let ipv4_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
+        let socket = UdpSocket::bind(ipv4_addr).await?;
+        Ok(Self {
+            socket,
+            is_ipv6: false,
+        })
+    }
+
+    ///发送数据报到已经解析的地址
+    pub async fn send_to_addr(&self, data: &[u8], target_addr: SocketAddr) -> Result<(), IoError> {
+        let target_addr = match target_addr {
+            //双栈socket需要使用ipv4映射地址
+            SocketAddr::V4(addr) if self.is_ipv6 => {
+                SocketAddr::new(IpAddr::V6(addr.ip().to_ipv6_mapped()), addr.port())
+            }
+            _ => target_addr,
+        };
+        self.socket.send_to(data, target_addr).await?;
+        Ok(())
+    }
+
+    ///是否为双栈socket, 只有双栈socket可以发往ipv6地址
+    pub fn is_ipv6(&self) -> bool {
+        self.is_ipv6
+    }
+
+    ///解析域名, `is_ipv6` 为 `false` 时只使用ipv4地址
+    pub async fn resolve(domain: &str, port: u16, is_ipv6: bool) -> Result<SocketAddr, IoError> {
+        let lookup_result = time::timeout(RESOLVE_TIMEOUT, net::lookup_host((domain, port)))
+            .await
+            .map_err(|_| IoError::new(ErrorKind::TimedOut, "resolve timeout"))?;
+        let mut addrs_iter = lookup_result?;
+        let opt_addr = if is_ipv6 {
+            addrs_iter.next()
+        } else {
+            addrs_iter.find(|s| s.is_ipv4())
+        };
+        opt_addr.ok_or_else(|| IoError::new(ErrorKind::NotFound, "no address found"))
+    }
+
+    ///接收数据报, 返回数据长度和来源地址
+    pub async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr), IoError> {
+        let (size, src_addr) = self.socket.recv_from(buf).await?;
+        //把ipv4映射地址还原
+        let src_addr = match src_addr {
+            SocketAddr::V6(addr) => match addr.ip().to_ipv4_mapped() {
+                Some(ip) => SocketAddr::new(IpAddr::V4(ip), addr.port()),
+                None => src_addr,
+            },
+            _ => src_addr,
+        };
+        Ok((size, src_addr))
+    }
+}
@@ -0,0 +1,5 @@
+mod load_rule_sets;
+mod parse_rule_set;
+
+pub use load_rule_sets::{load_rule_sets, LoadRuleSetError};
+pub use parse_rule_set::{parse_rule_set, ParseRuleSetError};
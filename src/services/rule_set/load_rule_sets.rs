@@ -0,0 +1,56 @@
+use super::{parse_rule_set, ParseRuleSetError};
+use crate::common::{RuleSet, RuleSetConfig};
+use std::{
+    collections::HashMap,
+    io::{Error as IoError, ErrorKind},
+};
+use thiserror::Error;
+use tokio::fs;
+
+///加载规则集出现的错误
+#[derive(Error, Debug)]
+pub enum LoadRuleSetError {
+    #[error("duplicate rule-set tag {0}")]
+    DuplicateTag(String),
+    #[error("rule-set {0} has neither path nor url")]
+    NoSource(String),
+    #[error("read rule-set {0} failed: {1}")]
+    Read(String, IoError),
+    #[error("parse rule-set {0} failed: {1}")]
+    Parse(String, ParseRuleSetError),
+}
+
+///加载所有的规则集, 返回tag对应的规则
+///
+///需要下载的规则文件还不存在时使用空的规则集, 下载完成之后会重新加载路由配置
+pub async fn load_rule_sets(
+    rule_sets: &[RuleSetConfig],
+    data_dir: &str,
+) -> Result<HashMap<String, RuleSet>, LoadRuleSetError> {
+    let mut rule_set_map = HashMap::with_capacity(rule_sets.len());
+    for rule_set in rule_sets {
+        if rule_set_map.contains_key(&rule_set.tag) {
+            return Err(LoadRuleSetError::DuplicateTag(rule_set.tag.to_string()));
+        }
+        if rule_set.path.is_none() && rule_set.url.is_none() {
+            return Err(LoadRuleSetError::NoSource(rule_set.tag.to_string()));
+        }
+        let path = rule_set.file_path(data_dir);
+        let text = match fs::read_to_string(&path).await {
+            Ok(s) => s,
+            Err(e) if e.kind() == ErrorKind::NotFound && rule_set.url.is_some() => {
+                log::warn!(
+                    "rule-set {} not downloaded yet: {}",
+                    rule_set.tag,
+                    path.display()
+                );
+                String::new()
+            }
+            Err(e) => return Err(LoadRuleSetError::Read(path.display().to_string(), e)),
+        };
+        let data = parse_rule_set(rule_set.rule_type, &text)
+            .map_err(|e| LoadRuleSetError::Parse(path.display().to_string(), e))?;
+        rule_set_map.insert(rule_set.tag.to_string(), data);
+    }
+    Ok(rule_set_map)
+}
@@ -0,0 +1,68 @@
+use crate::common::{
+    geoip::{self, IpRuleGroup, IpRuleType},
+    geosite::{DomainRule, DomainRuleGroup, DomainRuleType, ParseDomainRuleError},
+    RuleSet, RuleSetType,
+};
+use std::str::FromStr;
+use thiserror::Error;
+
+///解析规则文件出现的错误, 带有出错的行号
+#[derive(Error, Debug)]
+pub enum ParseRuleSetError {
+    #[error("line {0}: {1}")]
+    Domain(usize, ParseDomainRuleError),
+    #[error("line {0}: include is not supported")]
+    Include(usize),
+    #[error("line {0}: invalid ip rule {1}")]
+    Ip(usize, String),
+}
+
+///解析规则文件的内容, 每行一条规则, 忽略空行和 `#` 之后的注释
+pub fn parse_rule_set(rule_type: RuleSetType, text: &str) -> Result<RuleSet, ParseRuleSetError> {
+    match rule_type {
+        RuleSetType::Domain => parse_domain_rules(text).map(RuleSet::Domain),
+        RuleSetType::Ip => parse_ip_rules(text).map(RuleSet::Ip),
+    }
+}
+
+///域名规则, 格式与geosite源文件相同, 没有类型前缀时为 `domain:`
+fn parse_domain_rules(text: &str) -> Result<DomainRuleGroup, ParseRuleSetError> {
+    let mut group = DomainRuleGroup::default();
+    for (index, line) in text.lines().enumerate() {
+        let rule = match DomainRule::from_str(line) {
+            Ok(s) => s,
+            Err(ParseDomainRuleError::Empty) => continue,
+            Err(e) => return Err(ParseRuleSetError::Domain(index + 1, e)),
+        };
+        if matches!(rule.rule_type, DomainRuleType::Include) {
+            return Err(ParseRuleSetError::Include(index + 1));
+        }
+        group.add_rule(rule);
+    }
+    Ok(group)
+}
+
+///ip规则, 每行一个cidr、ip地址或者 `geoip:` 国家/地区代码
+fn parse_ip_rules(text: &str) -> Result<IpRuleGroup, ParseRuleSetError> {
+    let mut group = IpRuleGroup::default();
+    for (index, line) in text.lines().enumerate() {
+        let line = match line.find('#') {
+            Some(pos) => &line[..pos],
+            None => line,
+        }
+        .trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(country_code) = line.strip_prefix("geoip:") {
+            group.add_rule(IpRuleType::CountryCode, country_code.trim().to_string());
+            continue;
+        }
+        let ip_net = match geoip::parse_ip_net(line) {
+            Some(s) => s,
+            None => return Err(ParseRuleSetError::Ip(index + 1, line.to_string())),
+        };
+        group.add_rule(IpRuleType::Cidr, ip_net.to_string());
+    }
+    Ok(group)
+}